@@ -0,0 +1,71 @@
+// Byte/integer conversion, encryption and decryption over key and input sizes. Run with
+// `cargo bench --bench encryption`, or pick a group with `cargo bench --bench encryption
+// -- decrypt/2048`
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num_bigint_dig::BigUint;
+
+use student_rsa_tool::decrypt;
+use student_rsa_tool::encrypt;
+use student_rsa_tool::key_generator;
+use student_rsa_tool::utils;
+
+const MODULUS_SIZES: [usize; 3] = [1024, 2048, 4096];
+const INPUT_SIZES: [usize; 3] = [1024, 64 * 1024, 1024 * 1024];
+
+fn message(size: usize) -> String {
+    "0123456789abcdef".repeat(size / 16)
+}
+
+fn conversion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("conversion");
+    for size in INPUT_SIZES {
+        let bytes = message(size).into_bytes();
+        let integer = utils::bytes_array_to_integer(&bytes);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("bytes_array_to_integer", size),
+            &bytes,
+            |b, bytes| b.iter(|| utils::bytes_array_to_integer(bytes)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("integer_to_bytes_array", size),
+            &integer,
+            |b, integer: &BigUint| b.iter(|| utils::integer_to_bytes_array(integer.clone())),
+        );
+    }
+    group.finish();
+}
+
+fn encryption(c: &mut Criterion) {
+    for modulus_size in MODULUS_SIZES {
+        let key_pair = key_generator::generate_multi_prime_key_pair(modulus_size, 2);
+        let mut encrypt_group = c.benchmark_group(format!("encrypt/{}", modulus_size));
+        encrypt_group.sample_size(10);
+        for size in INPUT_SIZES {
+            let text = message(size);
+            encrypt_group.throughput(Throughput::Bytes(size as u64));
+            encrypt_group.bench_with_input(BenchmarkId::from_parameter(size), &text, |b, text| {
+                b.iter(|| encrypt::encrypt(text, &key_pair.modulus, &key_pair.e).unwrap())
+            });
+        }
+        encrypt_group.finish();
+
+        // Every block is a private-key operation, so the large inputs take a while
+        let mut decrypt_group = c.benchmark_group(format!("decrypt/{}", modulus_size));
+        decrypt_group.sample_size(10);
+        for size in INPUT_SIZES {
+            let ciphertext =
+                encrypt::encrypt(&message(size), &key_pair.modulus, &key_pair.e).unwrap();
+            decrypt_group.throughput(Throughput::Bytes(size as u64));
+            decrypt_group.bench_with_input(
+                BenchmarkId::from_parameter(size),
+                &ciphertext,
+                |b, ciphertext| b.iter(|| decrypt::decrypt_crt(ciphertext, &key_pair).unwrap()),
+            );
+        }
+        decrypt_group.finish();
+    }
+}
+
+criterion_group!(benches, conversion, encryption);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+// Compares private-key operations of multi-prime keys with two-prime keys of the same
+// modulus size. Run with `cargo bench --bench multi_prime`, or pick a modulus size with
+// `cargo bench --bench multi_prime -- private_key/4096`
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use num_bigint_dig::{BigUint, RandBigInt};
+
+use student_rsa_tool::crt;
+use student_rsa_tool::key_generator;
+
+const MODULUS_SIZES: [usize; 2] = [2048, 4096];
+
+fn private_key(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+
+    for modulus_size in MODULUS_SIZES {
+        let mut group = c.benchmark_group(format!("private_key/{}", modulus_size));
+        group.sample_size(20);
+        for prime_count in 2..=key_generator::MAX_PRIMES {
+            let key_pair = key_generator::generate_multi_prime_key_pair(modulus_size, prime_count);
+            let ciphertext: BigUint = rng.gen_biguint_below(&key_pair.modulus);
+
+            if prime_count == 2 {
+                let d = key_pair.d.to_biguint().unwrap();
+                group.bench_with_input("without_crt", &ciphertext, |b, ciphertext| {
+                    b.iter(|| ciphertext.modpow(&d, &key_pair.modulus))
+                });
+            }
+            group.bench_with_input(
+                BenchmarkId::new("crt", prime_count),
+                &ciphertext,
+                |b, ciphertext| b.iter(|| crt::crt_modpow(ciphertext, &key_pair)),
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, private_key);
+criterion_main!(benches);
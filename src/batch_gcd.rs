@@ -0,0 +1,197 @@
+use std::fs;
+
+use num_bigint_dig::{BigInt, BigUint, ModInverse};
+use num_integer::Integer;
+use num_traits::{Num, One};
+
+use crate::key_storage::{GenerationMethod, KeyPair, Storage};
+
+const DEFAULT_EXPONENT: u64 = 65537;
+
+#[derive(Clone)]
+pub struct PublicKey {
+    pub name: String,
+    pub modulus: BigUint,
+    pub e: BigUint,
+}
+
+// Two scanned keys whose moduli have a common factor
+pub struct SharedFactor {
+    pub first: usize,
+    pub second: usize,
+    pub factor: BigUint,
+}
+
+pub struct ScanResult {
+    pub first: PublicKey,
+    pub second: PublicKey,
+    pub factor: BigUint,
+    pub recovered: Option<(KeyPair, KeyPair)>,
+}
+
+// Product tree: the first level holds the moduli, every next level holds products of
+// neighbouring pairs of the previous one and the last level holds the product of everything
+pub fn product_tree(moduli: &[BigUint]) -> Vec<Vec<BigUint>> {
+    let mut tree: Vec<Vec<BigUint>> = vec![moduli.to_vec()];
+
+    while tree.last().unwrap().len() > 1 {
+        let level = tree.last().unwrap();
+        let next_level: Vec<BigUint> = level.chunks(2).map(|pair| pair.iter().product()).collect();
+        tree.push(next_level);
+    }
+    tree
+}
+
+// Batch GCD (Bernstein): for every modulus N_i computes gcd(N_i, product of all other moduli)
+// using a remainder tree instead of comparing every pair
+pub fn batch_gcd(moduli: &[BigUint]) -> Vec<BigUint> {
+    if moduli.is_empty() {
+        return Vec::new();
+    }
+
+    let tree = product_tree(moduli);
+    let mut remainders: Vec<BigUint> = tree.last().unwrap().clone();
+
+    for level in tree.iter().rev().skip(1) {
+        remainders = level
+            .iter()
+            .enumerate()
+            .map(|(i, node)| &remainders[i / 2] % (node * node))
+            .collect();
+    }
+
+    remainders
+        .iter()
+        .zip(moduli.iter())
+        .map(|(remainder, modulus)| (remainder / modulus).gcd(modulus))
+        .collect()
+}
+
+// Finds every pair of moduli sharing a factor. Batch GCD narrows the set down to
+// vulnerable moduli, then only those are compared with each other
+pub fn find_shared_factors(moduli: &[BigUint]) -> Vec<SharedFactor> {
+    let vulnerable: Vec<usize> = batch_gcd(moduli)
+        .iter()
+        .enumerate()
+        .filter(|(_, divisor)| !divisor.is_one())
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut shared_factors: Vec<SharedFactor> = Vec::new();
+    for (position, &first) in vulnerable.iter().enumerate() {
+        for &second in vulnerable.iter().skip(position + 1) {
+            let factor = moduli[first].gcd(&moduli[second]);
+            if !factor.is_one() {
+                shared_factors.push(SharedFactor {
+                    first,
+                    second,
+                    factor,
+                });
+            }
+        }
+    }
+    shared_factors
+}
+
+// Rebuilds the private key from one known factor of the modulus
+pub fn recover_key_pair(public_key: &PublicKey, factor: &BigUint) -> Option<KeyPair> {
+    if factor.is_one() || factor >= &public_key.modulus {
+        return None;
+    }
+
+    let p: BigUint = factor.clone();
+    let q: BigUint = &public_key.modulus / &p;
+    let phi: BigUint = (&p - BigUint::one()) * (&q - BigUint::one());
+    let d: BigInt = (&public_key.e).mod_inverse(&phi)?;
+
+    let mut key_pair = KeyPair::new(
+        public_key.name.clone(),
+        p,
+        q,
+        public_key.modulus.clone(),
+        public_key.e.clone(),
+        d,
+    );
+    key_pair.generation_method = GenerationMethod::Recovered;
+    Some(key_pair)
+}
+
+pub fn scan(public_keys: &[PublicKey]) -> Vec<ScanResult> {
+    let moduli: Vec<BigUint> = public_keys.iter().map(|key| key.modulus.clone()).collect();
+
+    find_shared_factors(&moduli)
+        .into_iter()
+        .map(|shared| {
+            let first = public_keys[shared.first].clone();
+            let second = public_keys[shared.second].clone();
+            let recovered = recover_key_pair(&first, &shared.factor)
+                .zip(recover_key_pair(&second, &shared.factor));
+
+            ScanResult {
+                first,
+                second,
+                factor: shared.factor,
+                recovered,
+            }
+        })
+        .collect()
+}
+
+pub fn public_keys_from_storage(storage: &Storage) -> Vec<PublicKey> {
+    storage
+        .key_pairs()
+        .map(|key_pair| PublicKey {
+            name: key_pair.name.clone(),
+            modulus: key_pair.modulus.clone(),
+            e: key_pair.e.clone(),
+        })
+        .collect()
+}
+
+// Reads a text file with one public key per line in one of the forms
+//   <modulus>
+//   <modulus> <exponent>
+//   <name> <modulus> <exponent>
+// Numbers are decimal or hexadecimal with a `0x` prefix, lines starting with `#` are skipped
+pub fn load_public_keys_from_file(path: &str) -> Result<Vec<PublicKey>, String> {
+    let content = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    let mut public_keys: Vec<PublicKey> = Vec::new();
+
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (name, modulus, e) = match fields.as_slice() {
+            [modulus] => (None, *modulus, None),
+            [modulus, e] => (None, *modulus, Some(*e)),
+            [name, modulus, e] => (Some(*name), *modulus, Some(*e)),
+            _ => return Err(format!("line {}: expected 1 to 3 fields", line_number + 1)),
+        };
+
+        let parse = |value: &str| {
+            parse_number(value).ok_or(format!("line {}: invalid number", line_number + 1))
+        };
+
+        public_keys.push(PublicKey {
+            name: name
+                .map(str::to_string)
+                .unwrap_or(format!("line {}", line_number + 1)),
+            modulus: parse(modulus)?,
+            e: match e {
+                Some(e) => parse(e)?,
+                None => BigUint::from(DEFAULT_EXPONENT),
+            },
+        });
+    }
+    Ok(public_keys)
+}
+
+fn parse_number(value: &str) -> Option<BigUint> {
+    match value.strip_prefix("0x") {
+        Some(hex) => BigUint::from_str_radix(hex, 16).ok(),
+        None => BigUint::from_str_radix(value, 10).ok(),
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use num_bigint_dig::{BigInt, BigUint, ModInverse, RandBigInt};
+use num_integer::Integer;
+use num_traits::One;
+use rand::rngs::OsRng;
+
+use crate::exponentiation::{self, Exponentiation};
+
+// Blinding of private-key operations is on unless it is switched off explicitly
+static BLINDING_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn is_enabled() -> bool {
+    BLINDING_ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    BLINDING_ENABLED.store(enabled, Ordering::Relaxed)
+}
+
+// Computes input^d mod n for decryption and signing, blinded when blinding is enabled
+pub fn private_key_operation(
+    input: &BigUint,
+    modulus: &BigUint,
+    e: &BigUint,
+    d: &BigInt,
+    exponentiation: Exponentiation,
+) -> BigUint {
+    let d = d.to_biguint().unwrap();
+
+    if is_enabled() {
+        blinded_modpow(input, modulus, e, &d, exponentiation)
+    } else {
+        exponentiation::modpow(input, &d, modulus, exponentiation)
+    }
+}
+
+// Base blinding: the exponentiation runs on c * r^e instead of the attacker-chosen c,
+// (c * r^e)^d = c^d * r mod n, so multiplying by r^-1 gives back c^d.
+// A fresh r is drawn from the OS CSPRNG for every operation
+pub fn blinded_modpow(
+    input: &BigUint,
+    modulus: &BigUint,
+    e: &BigUint,
+    d: &BigUint,
+    exponentiation: Exponentiation,
+) -> BigUint {
+    blind(input, modulus, e, |blinded| {
+        exponentiation::modpow(blinded, d, modulus, exponentiation)
+    })
+}
+
+// Runs any implementation of x -> x^d mod n on a blinded input and unblinds the result
+pub fn blind<F>(input: &BigUint, modulus: &BigUint, e: &BigUint, operation: F) -> BigUint
+where
+    F: FnOnce(&BigUint) -> BigUint,
+{
+    let (r, r_inverse) = loop {
+        let r = OsRng.gen_biguint_range(&BigUint::from(2u32), modulus);
+        if !r.gcd(modulus).is_one() {
+            continue;
+        }
+        if let Some(r_inverse) = (&r).mod_inverse(modulus).and_then(|r| r.to_biguint()) {
+            break (r, r_inverse);
+        }
+    };
+
+    let blinded = (input * r.modpow(e, modulus)) % modulus;
+    (operation(&blinded) * r_inverse) % modulus
+}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::key_storage::LEGACY_KEY_STORAGE_PATH;
+
+const APPLICATION_DIR: &str = "student-rsa-tool";
+const CONFIG_FILE: &str = "config.json";
+const KEYRING_DIR: &str = "keyrings";
+pub const DEFAULT_KEYRING: &str = "default";
+
+pub const DATA_DIR_VARIABLE: &str = "STUDENT_RSA_TOOL_DATA_DIR";
+pub const KEYRING_VARIABLE: &str = "STUDENT_RSA_TOOL_KEYRING";
+pub const CONFIG_VARIABLE: &str = "STUDENT_RSA_TOOL_CONFIG";
+
+// Contents of the config file, every field is optional
+#[derive(Serialize, Deserialize, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub data_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub keyring: Option<String>,
+}
+
+struct Settings {
+    data_dir: PathBuf,
+    keyring: String,
+}
+
+static SETTINGS: RwLock<Option<Settings>> = RwLock::new(None);
+
+// Values given on the command line, they win over everything else
+#[derive(Default)]
+pub struct Overrides {
+    pub config: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub keyring: Option<String>,
+}
+
+// Resolves the storage location: command line first, then the environment variables,
+// then the config file and finally the XDG data directory with the "default" keyring
+pub fn init(overrides: Overrides) -> Result<(), String> {
+    let config_path = overrides
+        .config
+        .or_else(|| std::env::var_os(CONFIG_VARIABLE).map(PathBuf::from))
+        .unwrap_or_else(default_config_path);
+    let config = load_config(&config_path)?;
+
+    let data_dir = overrides
+        .data_dir
+        .or_else(|| std::env::var_os(DATA_DIR_VARIABLE).map(PathBuf::from))
+        .or(config.data_dir)
+        .unwrap_or_else(default_data_dir);
+    let keyring = overrides
+        .keyring
+        .or_else(|| std::env::var(KEYRING_VARIABLE).ok())
+        .or(config.keyring)
+        .unwrap_or_else(|| DEFAULT_KEYRING.to_string());
+    check_keyring_name(&keyring)?;
+
+    *SETTINGS.write().unwrap() = Some(Settings { data_dir, keyring });
+    Ok(())
+}
+
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let content = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&content)
+        .map_err(|error| format!("invalid config file {}: {}", path.display(), error))
+}
+
+pub fn default_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APPLICATION_DIR)
+        .join(CONFIG_FILE)
+}
+
+// $XDG_DATA_HOME/student-rsa-tool, ~/.local/share/student-rsa-tool when it is not set
+pub fn default_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APPLICATION_DIR)
+}
+
+fn with_settings<T, F>(read: F) -> Result<T, String>
+where
+    F: FnOnce(&Settings) -> T,
+{
+    if SETTINGS.read().unwrap().is_none() {
+        // Library users that never call `init` get the environment and config file values
+        init(Overrides::default())?;
+    }
+    Ok(read(SETTINGS.read().unwrap().as_ref().unwrap()))
+}
+
+pub fn data_dir() -> Result<PathBuf, String> {
+    with_settings(|settings| settings.data_dir.clone())
+}
+
+pub fn keyring() -> Result<String, String> {
+    with_settings(|settings| settings.keyring.clone())
+}
+
+pub fn set_keyring(name: &str) -> Result<(), String> {
+    check_keyring_name(name)?;
+    with_settings(|_| ())?;
+    SETTINGS.write().unwrap().as_mut().unwrap().keyring = name.to_string();
+    Ok(())
+}
+
+// Keyring names become file names, so only letters, digits, '-' and '_' are allowed
+pub fn check_keyring_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "invalid keyring name \"{}\", use letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+pub fn keyring_dir() -> Result<PathBuf, String> {
+    Ok(data_dir()?.join(KEYRING_DIR))
+}
+
+pub fn keyring_path(name: &str) -> Result<PathBuf, String> {
+    Ok(keyring_dir()?.join(format!("{}.json", name)))
+}
+
+// Names of every keyring in the data directory, sorted, always including the active one
+pub fn keyrings() -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = fs::read_dir(keyring_dir()?)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "json")
+                })
+                .filter_map(|path| path.file_stem()?.to_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let active = keyring()?;
+    if !names.contains(&active) {
+        names.push(active);
+    }
+    names.sort();
+    Ok(names)
+}
+
+// Path of the active keyring file. The first time the default keyring is used, a
+// key_storage.json left in the working directory by older versions is copied into it
+pub fn storage_path() -> Result<String, String> {
+    let name = keyring()?;
+    let path = keyring_path(&name)?;
+
+    if !path.exists() {
+        let directory = keyring_dir()?;
+        fs::create_dir_all(&directory).map_err(|error| {
+            format!(
+                "could not create the keyring directory {}: {}",
+                directory.display(),
+                error
+            )
+        })?;
+        if name == DEFAULT_KEYRING && Path::new(LEGACY_KEY_STORAGE_PATH).exists() {
+            fs::copy(LEGACY_KEY_STORAGE_PATH, &path).map_err(|error| {
+                format!(
+                    "could not copy {} to {}: {}",
+                    LEGACY_KEY_STORAGE_PATH,
+                    path.display(),
+                    error
+                )
+            })?;
+        }
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
@@ -0,0 +1,45 @@
+use num_bigint_dig::BigUint;
+
+use crate::blinding;
+use crate::key_storage::KeyPair;
+
+// RSADP with the Chinese remainder theorem (RFC 8017, 5.1.2) over every prime of the key.
+// Each exponentiation works with a prime and an exponent u times shorter than n and d.
+// Without qInv the primes are not coprime and the plain exponentiation is used
+pub fn crt_modpow(input: &BigUint, key_pair: &KeyPair) -> BigUint {
+    let p = &key_pair.p;
+    let q = &key_pair.q;
+    let qinv = match key_pair.qinv() {
+        Some(qinv) => qinv,
+        None => return input.modpow(&key_pair.d.to_biguint().unwrap(), &key_pair.modulus),
+    };
+
+    let m_1 = input.modpow(&key_pair.dp(), p);
+    let m_2 = input.modpow(&key_pair.dq(), q);
+
+    // h = (m_1 - m_2) * qInv mod p, m = m_2 + q * h
+    let h = ((m_1 + p - (&m_2 % p)) * qinv) % p;
+    let mut m = m_2 + q * h;
+
+    // R = r_1 * ... * r_(i-1), h = (m_i - m) * t_i mod r_i, m = m + R * h
+    let mut r: BigUint = p * q;
+    for info in key_pair.other_primes.iter() {
+        let prime = &info.prime;
+        let m_i = input.modpow(&info.exponent, prime);
+        let h = ((m_i + prime - (&m % prime)) * &info.coefficient) % prime;
+        m += &r * h;
+        r *= prime;
+    }
+    m
+}
+
+// CRT private-key operation, blinded when blinding is enabled
+pub fn private_key_operation(input: &BigUint, key_pair: &KeyPair) -> BigUint {
+    if blinding::is_enabled() {
+        blinding::blind(input, &key_pair.modulus, &key_pair.e, |blinded| {
+            crt_modpow(blinded, key_pair)
+        })
+    } else {
+        crt_modpow(input, key_pair)
+    }
+}
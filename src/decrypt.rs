@@ -2,12 +2,112 @@ use num_bigint_dig::{BigInt, BigUint};
 use num_traits::{Pow, Zero};
 use std::string::FromUtf8Error;
 
+use crate::blinding;
+use crate::crt;
+use crate::encrypt;
+use crate::exponentiation::Exponentiation;
+use crate::key_storage::KeyPair;
+use crate::sign::{self, HashAlgorithm};
 use crate::utils;
 
-pub fn decrypt(message: &String, modulus: &BigUint, d: &BigInt) -> Result<String, FromUtf8Error> {
-    let binding: String = BigUint::parse_bytes(message.as_bytes(), 16)
-        .unwrap()
-        .to_str_radix(10);
+pub fn decrypt(
+    ciphertext: &[u8],
+    modulus: &BigUint,
+    e: &BigUint,
+    d: &BigInt,
+) -> Result<String, String> {
+    decrypt_with(ciphertext, modulus, e, d, Exponentiation::Builtin)
+}
+
+// Private-key operations are blinded unless blinding is switched off with `blinding::set_enabled`
+pub fn decrypt_with(
+    ciphertext: &[u8],
+    modulus: &BigUint,
+    e: &BigUint,
+    d: &BigInt,
+    exponentiation: Exponentiation,
+) -> Result<String, String> {
+    decrypt_blocks(ciphertext, modulus, |block| {
+        blinding::private_key_operation(block, modulus, e, d, exponentiation)
+    })
+}
+
+// Decrypts with the Chinese remainder theorem over all primes of the key pair
+pub fn decrypt_crt(ciphertext: &[u8], key_pair: &KeyPair) -> Result<String, String> {
+    decrypt_blocks(ciphertext, &key_pair.modulus, |block| {
+        crt::private_key_operation(block, key_pair)
+    })
+}
+
+// Ciphertexts of `encrypt::encrypt` are whole blocks of k bytes. Anything else, or blocks
+// that do not decrypt to UTF-8 text, is read in the older layout. Keys too small for
+// `encrypt::encrypt` are refused
+fn decrypt_blocks<F>(
+    ciphertext: &[u8],
+    modulus: &BigUint,
+    private_key_operation: F,
+) -> Result<String, String>
+where
+    F: Fn(&BigUint) -> BigUint,
+{
+    encrypt::check_key_size(modulus)?;
+    let decrypted = decrypt_fixed_blocks(ciphertext, modulus, &private_key_operation);
+    match decrypted.map(String::from_utf8) {
+        Some(Ok(text)) => Ok(text),
+        _ => decrypt_legacy_blocks(ciphertext, modulus, &private_key_operation)
+            .map_err(|_| "the result is not UTF-8 text".to_string()),
+    }
+}
+
+// None unless every block is smaller than the modulus, decrypts to at most k - 1 bytes and
+// the text ends with the padding of `encrypt::encrypt`. Leading zero bytes the decimal
+// encoding dropped are put back by padding to whole blocks
+fn decrypt_fixed_blocks<F>(
+    ciphertext: &[u8],
+    modulus: &BigUint,
+    private_key_operation: &F,
+) -> Option<Vec<u8>>
+where
+    F: Fn(&BigUint) -> BigUint,
+{
+    let length = modulus.bits().div_ceil(8);
+    let mut padded = vec![0u8; (length - ciphertext.len() % length) % length];
+    padded.extend_from_slice(ciphertext);
+
+    let mut decrypted_data: Vec<u8> = Vec::new();
+    for block in padded.chunks(length) {
+        let block = BigUint::from_bytes_be(block);
+        if &block >= modulus {
+            return None;
+        }
+        let decrypted = private_key_operation(&block).to_bytes_be();
+        if decrypted.len() > length - 1 {
+            return None;
+        }
+        // Every block holds k - 1 bytes, the leading zero bytes are not part of the integer
+        decrypted_data.resize(decrypted_data.len() + length - 1 - decrypted.len(), 0);
+        decrypted_data.extend(decrypted);
+    }
+
+    let end = decrypted_data.iter().rposition(|byte| *byte != 0)?;
+    if decrypted_data[end] != 0x80 {
+        return None;
+    }
+    decrypted_data.truncate(end);
+    Some(decrypted_data)
+}
+
+// The layout before fixed-length blocks: every block as decimal digits, zero-padded to the
+// digit count of 256^step plus one, concatenated and read as one number
+fn decrypt_legacy_blocks<F>(
+    ciphertext: &[u8],
+    modulus: &BigUint,
+    private_key_operation: &F,
+) -> Result<String, FromUtf8Error>
+where
+    F: Fn(&BigUint) -> BigUint,
+{
+    let binding: String = BigUint::from_bytes_be(ciphertext).to_str_radix(10);
     let mut message: Vec<u8> = binding.as_bytes().to_vec();
     let mut decrypted_data: Vec<u8> = Vec::new();
     let mut step_size: u32 = 0;
@@ -25,26 +125,73 @@ pub fn decrypt(message: &String, modulus: &BigUint, d: &BigInt) -> Result<String
         .len()
         + 1;
 
-    while message.len() % cypher_len != 0 {
+    while !message.len().is_multiple_of(cypher_len) {
         // Concatenate two slices
         message.reverse();
         message.push("0".as_bytes()[0]);
         message.reverse();
     }
 
-    for i in (0..message.len()).step_by(cypher_len as usize) {
+    for i in (0..message.len()).step_by(cypher_len) {
         if i + step_size as usize > message.len() {
             slice = &message[i..message.len()];
         } else {
-            slice = &message[i..i + cypher_len as usize];
+            slice = &message[i..i + cypher_len];
         }
         let integer_slice = BigUint::parse_bytes(slice, 10).unwrap();
 
-        let convert_to_vec_string = &utils::integer_to_bytes_array(
-            integer_slice.modpow(&d.to_biguint().unwrap(), &modulus),
-        );
+        let convert_to_vec_string =
+            &utils::integer_to_bytes_array(private_key_operation(&integer_slice));
         decrypted_data.extend(convert_to_vec_string);
     }
 
-    return String::from_utf8(decrypted_data);
+    String::from_utf8(decrypted_data)
+}
+
+// RSAES-OAEP decryption (RFC 8017 7.1.2) matching `encrypt::encrypt_oaep`. Every failure
+// gives the same error, so it does not tell an attacker which check failed
+pub fn decrypt_oaep(ciphertext: &[u8], key_pair: &KeyPair) -> Result<Vec<u8>, String> {
+    let hash = HashAlgorithm::Sha256;
+    let hash_length = hash.output_length();
+    let length = key_pair.modulus.bits().div_ceil(8);
+    let error = || "decryption error".to_string();
+    if ciphertext.len() != length || length < 2 * hash_length + 2 {
+        return Err(error());
+    }
+    let ciphertext = BigUint::from_bytes_be(ciphertext);
+    if ciphertext >= key_pair.modulus {
+        return Err(error());
+    }
+
+    let message = crt::private_key_operation(&ciphertext, key_pair).to_bytes_be();
+    let mut encoded = vec![0u8; length - message.len()];
+    encoded.extend(message);
+
+    let (masked_seed, masked_db) = encoded[1..].split_at(hash_length);
+    let seed: Vec<u8> = masked_seed
+        .iter()
+        .zip(sign::mgf1(masked_db, hash_length, hash))
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    let db: Vec<u8> = masked_db
+        .iter()
+        .zip(sign::mgf1(&seed, masked_db.len(), hash))
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+
+    // DB = lHash || 0x00...0x00 || 0x01 || M
+    let separator = db[hash_length..]
+        .iter()
+        .position(|byte| *byte != 0)
+        .map(|position| position + hash_length);
+    match separator {
+        Some(separator)
+            if encoded[0] == 0
+                && db[..hash_length] == hash.digest(&[])[..]
+                && db[separator] == 0x01 =>
+        {
+            Ok(db[separator + 1..].to_vec())
+        }
+        _ => Err(error()),
+    }
 }
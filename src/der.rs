@@ -0,0 +1,233 @@
+use num_bigint_dig::BigUint;
+
+// Minimal DER (ITU-T X.690) encoder and decoder for the structures the tool exports and imports
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OBJECT_IDENTIFIER: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub const TAG_TELETEX_STRING: u8 = 0x14;
+pub const TAG_IA5_STRING: u8 = 0x16;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_BMP_STRING: u8 = 0x1e;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+// Tag of a context-specific field like [0] or [3], constructed for EXPLICIT tagging and for
+// IMPLICIT tagging of constructed types
+pub fn context_tag(number: u8, constructed: bool) -> u8 {
+    0x80 | if constructed { 0x20 } else { 0x00 } | number
+}
+
+pub fn encode_length(length: usize) -> Vec<u8> {
+    if length < 0x80 {
+        return vec![length as u8];
+    }
+
+    let bytes: Vec<u8> = length
+        .to_be_bytes()
+        .iter()
+        .skip_while(|&&byte| byte == 0)
+        .cloned()
+        .collect();
+    let mut encoded = vec![0x80 | bytes.len() as u8];
+    encoded.extend(bytes);
+    encoded
+}
+
+pub fn encode_tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    encoded.extend(encode_length(value.len()));
+    encoded.extend_from_slice(value);
+    encoded
+}
+
+// INTEGER is signed, a leading zero byte keeps big positive numbers positive
+pub fn encode_integer(value: &BigUint) -> Vec<u8> {
+    let mut bytes = value.to_bytes_be();
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    encode_tlv(TAG_INTEGER, &bytes)
+}
+
+pub fn encode_sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+    encode_tlv(TAG_SEQUENCE, &elements.concat())
+}
+
+// DER sorts the elements of a SET OF by their encoding
+pub fn encode_set(elements: &[Vec<u8>]) -> Vec<u8> {
+    let mut elements = elements.to_vec();
+    elements.sort();
+    encode_tlv(TAG_SET, &elements.concat())
+}
+
+pub fn encode_boolean(value: bool) -> Vec<u8> {
+    encode_tlv(TAG_BOOLEAN, &[if value { 0xff } else { 0x00 }])
+}
+
+// A BIT STRING of whole bytes, the first content byte counts the unused bits
+pub fn encode_bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut value = vec![0];
+    value.extend_from_slice(bytes);
+    encode_tlv(TAG_BIT_STRING, &value)
+}
+
+// BIT STRING of a named bit list (X.690 11.2.2): bit 0 is the most significant bit of the
+// first byte and trailing zero bits are left out
+pub fn encode_named_bits(bits: &[usize]) -> Vec<u8> {
+    let length = match bits.iter().max() {
+        Some(highest) => highest / 8 + 1,
+        None => return encode_tlv(TAG_BIT_STRING, &[0]),
+    };
+    let mut bytes = vec![0u8; length];
+    for bit in bits {
+        bytes[bit / 8] |= 0x80 >> (bit % 8);
+    }
+    let unused = bytes[length - 1].trailing_zeros() as u8;
+    let mut value = vec![unused];
+    value.extend(bytes);
+    encode_tlv(TAG_BIT_STRING, &value)
+}
+
+// Numbers of the set bits of a named bit list
+pub fn decode_named_bits(value: &[u8]) -> Vec<usize> {
+    value
+        .iter()
+        .skip(1)
+        .enumerate()
+        .flat_map(|(index, byte)| {
+            (0..8)
+                .filter(move |bit| byte & (0x80 >> bit) != 0)
+                .map(move |bit| index * 8 + bit)
+        })
+        .collect()
+}
+
+// Dotted OID text like "2.5.4.3" to its DER TLV. The first two arcs share one number
+// and every number is written in base 128, high bit set on all but the last byte
+pub fn encode_oid(oid: &str) -> Vec<u8> {
+    let arcs: Vec<u64> = oid.split('.').map(|arc| arc.parse().unwrap()).collect();
+    let mut value = Vec::new();
+    for arc in std::iter::once(arcs[0] * 40 + arcs[1]).chain(arcs[2..].iter().cloned()) {
+        let mut bytes = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            bytes.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        bytes.reverse();
+        value.extend(bytes);
+    }
+    encode_tlv(TAG_OBJECT_IDENTIFIER, &value)
+}
+
+// Content bytes of an OBJECT IDENTIFIER to dotted text
+pub fn oid_to_string(value: &[u8]) -> String {
+    let mut numbers = Vec::new();
+    let mut current: u64 = 0;
+    for byte in value {
+        current = current << 7 | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            numbers.push(current);
+            current = 0;
+        }
+    }
+
+    let mut arcs = match numbers.first() {
+        Some(&first) if first < 80 => vec![first / 40, first % 40],
+        Some(&first) => vec![2, first - 80],
+        None => return String::new(),
+    };
+    arcs.extend(&numbers[1..]);
+    arcs.iter()
+        .map(|arc| arc.to_string())
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+pub fn decode_oid(input: &[u8]) -> Result<(String, &[u8]), String> {
+    let (value, rest) = decode_expected(input, TAG_OBJECT_IDENTIFIER)?;
+    Ok((oid_to_string(value), rest))
+}
+
+// Any of the string types X.509 names use. TeletexString is read as Latin-1, which is what
+// it holds in practice
+pub fn decode_string(input: &[u8]) -> Result<(String, &[u8]), String> {
+    let (tag, value, rest) = decode_tlv(input)?;
+    let text = match tag {
+        TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING => {
+            String::from_utf8(value.to_vec()).map_err(|_| "invalid string".to_string())?
+        }
+        TAG_TELETEX_STRING => value.iter().map(|&byte| byte as char).collect(),
+        TAG_BMP_STRING => {
+            let units: Vec<u16> = value
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return Err(format!("expected a string, found DER tag 0x{:02x}", tag)),
+    };
+    Ok((text, rest))
+}
+
+// Splits the first TLV off `input`, returning its tag, value and the bytes after it
+pub fn decode_tlv(input: &[u8]) -> Result<(u8, &[u8], &[u8]), String> {
+    if input.len() < 2 {
+        return Err("DER data ends too early".to_string());
+    }
+
+    let tag = input[0];
+    let (length, header_length) = if input[1] < 0x80 {
+        (input[1] as usize, 2)
+    } else {
+        let length_bytes = (input[1] & 0x7f) as usize;
+        if length_bytes == 0 || length_bytes > std::mem::size_of::<usize>() {
+            return Err("unsupported DER length".to_string());
+        }
+        if input.len() < 2 + length_bytes {
+            return Err("DER data ends too early".to_string());
+        }
+        let length = input[2..2 + length_bytes]
+            .iter()
+            .fold(0usize, |length, &byte| length << 8 | byte as usize);
+        (length, 2 + length_bytes)
+    };
+
+    if input.len() - header_length < length {
+        return Err("DER data ends too early".to_string());
+    }
+    let (value, rest) = input[header_length..].split_at(length);
+    Ok((tag, value, rest))
+}
+
+// Like `decode_tlv`, but fails when the tag is not `expected_tag`
+pub fn decode_expected(input: &[u8], expected_tag: u8) -> Result<(&[u8], &[u8]), String> {
+    let (tag, value, rest) = decode_tlv(input)?;
+    if tag != expected_tag {
+        return Err(format!(
+            "expected DER tag 0x{:02x}, found 0x{:02x}",
+            expected_tag, tag
+        ));
+    }
+    Ok((value, rest))
+}
+
+// Only non-negative integers are accepted, RSA never needs negative ones
+pub fn decode_integer(input: &[u8]) -> Result<(BigUint, &[u8]), String> {
+    let (value, rest) = decode_expected(input, TAG_INTEGER)?;
+    if value.is_empty() || value[0] & 0x80 != 0 {
+        return Err("expected a non-negative INTEGER".to_string());
+    }
+    Ok((BigUint::from_bytes_be(value), rest))
+}
+
+pub fn decode_sequence(input: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    decode_expected(input, TAG_SEQUENCE)
+}
@@ -0,0 +1,173 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use num_bigint_dig::BigUint;
+
+use crate::openpgp;
+
+// Text forms of a ciphertext, the blocks of `encrypt::encrypt` or a message for several
+// recipients. Decimal drops leading zero bytes, decryption pads the blocks again
+pub const ARMOR_LABEL: &str = "RSA MESSAGE";
+
+const BASE32: base32::Alphabet = base32::Alphabet::Rfc4648 { padding: true };
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Encoding {
+    Hex,
+    Base64,
+    Base64Url,
+    Base32,
+    Base58,
+    Decimal,
+    Armor,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 7] = [
+        Encoding::Hex,
+        Encoding::Base64,
+        Encoding::Base64Url,
+        Encoding::Base32,
+        Encoding::Base58,
+        Encoding::Decimal,
+        Encoding::Armor,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64url",
+            Encoding::Base32 => "base32",
+            Encoding::Base58 => "base58",
+            Encoding::Decimal => "decimal",
+            Encoding::Armor => "armor",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Encoding, String> {
+        Encoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.name() == name.to_lowercase())
+            .ok_or_else(|| {
+                let names: Vec<&str> = Encoding::ALL
+                    .iter()
+                    .map(|encoding| encoding.name())
+                    .collect();
+                format!("unknown encoding \"{}\", use {}", name, names.join(", "))
+            })
+    }
+
+    // The encoding after this one, for cycling through them in the TUI
+    pub fn next(&self) -> Encoding {
+        let index = Encoding::ALL
+            .iter()
+            .position(|encoding| encoding == self)
+            .unwrap();
+        Encoding::ALL[(index + 1) % Encoding::ALL.len()]
+    }
+
+    pub fn encode(&self, data: &[u8]) -> String {
+        match self {
+            Encoding::Hex => data.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            Encoding::Base64 => STANDARD.encode(data),
+            Encoding::Base64Url => URL_SAFE_NO_PAD.encode(data),
+            Encoding::Base32 => base32::encode(BASE32, data),
+            Encoding::Base58 => bs58::encode(data).into_string(),
+            Encoding::Decimal => BigUint::from_bytes_be(data).to_str_radix(10),
+            Encoding::Armor => openpgp::armor(ARMOR_LABEL, data),
+        }
+    }
+
+    // Whitespace is ignored, so wrapped lines can be pasted
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>, String> {
+        if *self == Encoding::Armor {
+            let (label, data) = openpgp::read_armor(text)?;
+            return match label.as_str() {
+                ARMOR_LABEL => Ok(data),
+                openpgp::MESSAGE => Err("this is an OpenPGP message, use pgp decrypt".to_string()),
+                _ => Err(format!("the armor holds a \"{}\", not a ciphertext", label)),
+            };
+        }
+
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        if text.is_empty() {
+            return Err("the ciphertext is empty".to_string());
+        }
+        let invalid = || format!("the ciphertext is not valid {}", self.name());
+        match self {
+            // Odd lengths come from integers printed without a leading zero
+            Encoding::Hex => {
+                if !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                    return Err(invalid());
+                }
+                let text = if text.len().is_multiple_of(2) {
+                    text
+                } else {
+                    format!("0{}", text)
+                };
+                Ok((0..text.len())
+                    .step_by(2)
+                    .map(|index| u8::from_str_radix(&text[index..index + 2], 16).unwrap())
+                    .collect())
+            }
+            Encoding::Base64 => STANDARD_NO_PAD
+                .decode(text.trim_end_matches('='))
+                .map_err(|_| invalid()),
+            Encoding::Base64Url => URL_SAFE_NO_PAD
+                .decode(text.trim_end_matches('='))
+                .map_err(|_| invalid()),
+            Encoding::Base32 => base32::decode(BASE32, &text.to_uppercase()).ok_or_else(invalid),
+            Encoding::Base58 => bs58::decode(&text).into_vec().map_err(|_| invalid()),
+            Encoding::Decimal => BigUint::parse_bytes(text.as_bytes(), 10)
+                .filter(|_| text.bytes().all(|byte| byte.is_ascii_digit()))
+                .map(|integer| integer.to_bytes_be())
+                .ok_or_else(invalid),
+            Encoding::Armor => unreachable!(),
+        }
+    }
+}
+
+// Guesses the encoding from the characters used. Hex made of digits only or base64
+// without any of 0, O, I, l, + and / are possible but unlikely for ciphertexts of
+// a few hundred characters; the encoding can be chosen for those
+pub fn detect(text: &str) -> Option<Encoding> {
+    if text.trim_start().starts_with("-----BEGIN ") {
+        return Some(Encoding::Armor);
+    }
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let unpadded = text.trim_end_matches('=');
+    let padded = unpadded.len() != text.len();
+
+    if text.is_empty() {
+        None
+    } else if text.bytes().all(|byte| byte.is_ascii_digit()) {
+        Some(Encoding::Decimal)
+    } else if text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        Some(Encoding::Hex)
+    } else if unpadded
+        .bytes()
+        .all(|byte| byte.is_ascii_uppercase() || (b'2'..=b'7').contains(&byte))
+    {
+        Some(Encoding::Base32)
+    } else if !padded && unpadded.chars().all(|c| BASE58_ALPHABET.contains(c)) {
+        Some(Encoding::Base58)
+    } else if unpadded
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+    {
+        Some(Encoding::Base64Url)
+    } else if unpadded
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'+' || byte == b'/')
+    {
+        Some(Encoding::Base64)
+    } else {
+        None
+    }
+}
+
+pub fn decode_auto(text: &str) -> Result<(Vec<u8>, Encoding), String> {
+    let encoding = detect(text).ok_or("cannot tell the encoding of the ciphertext, choose one")?;
+    Ok((encoding.decode(text)?, encoding))
+}
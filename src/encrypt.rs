@@ -1,47 +1,82 @@
-use num_bigint_dig::{BigInt, BigUint};
-use num_traits::{Pow, Zero};
+use num_bigint_dig::BigUint;
+use rand::rngs::OsRng;
+use rand::RngCore;
 
-use crate::utils;
+use crate::sign::{self, HashAlgorithm};
 
-pub fn encrypt(message: &String, modulus: &BigUint, e: &BigUint) -> String {
-    let message = message.clone().into_bytes();
+// A block of k - 1 bytes holds at least one byte of the message and the 0x80 of the padding
+const MINIMUM_MODULUS_LENGTH: usize = 3;
 
-    let mut encrypted_data: Vec<u8> = Vec::new();
-    let mut step_size: u32 = 0;
-    let mut tmp: BigUint = modulus / BigUint::from(256u32);
-    let mut slice: &[u8];
+// The message gets 0x80 and zero bytes up to a whole number of blocks of k - 1 bytes
+// (ISO/IEC 7816-4 padding), where k is the length of the modulus in bytes, so every block
+// is smaller than the modulus and decrypts to exactly k - 1 bytes. Each encrypted block is
+// written as k big-endian bytes (I2OSP of RFC 8017 4.1)
+pub fn encrypt(message: &str, modulus: &BigUint, e: &BigUint) -> Result<Vec<u8>, String> {
+    check_key_size(modulus)?;
+    let length = modulus.bits().div_ceil(8);
+    let mut padded = message.as_bytes().to_vec();
+    padded.push(0x80);
+    padded.resize(padded.len().next_multiple_of(length - 1), 0);
 
-    while tmp != BigUint::zero() {
-        tmp /= BigUint::from(256u32);
-        step_size += 1;
+    let mut ciphertext: Vec<u8> = Vec::new();
+    for block in padded.chunks(length - 1) {
+        let encrypted = BigUint::from_bytes_be(block)
+            .modpow(e, modulus)
+            .to_bytes_be();
+        ciphertext.resize(ciphertext.len() + length - encrypted.len(), 0);
+        ciphertext.extend(encrypted);
     }
+    Ok(ciphertext)
+}
+
+pub(crate) fn check_key_size(modulus: &BigUint) -> Result<(), String> {
+    if modulus.bits().div_ceil(8) < MINIMUM_MODULUS_LENGTH {
+        return Err(format!(
+            "the key is too small to encrypt text, it needs at least {} bits",
+            (MINIMUM_MODULUS_LENGTH - 1) * 8 + 1
+        ));
+    }
+    Ok(())
+}
+
+// RSAES-OAEP (RFC 8017 7.1.1) with SHA-256, MGF1 over SHA-256 and an empty label. The
+// ciphertext is k bytes long where k is the length of the modulus in bytes
+pub fn encrypt_oaep(message: &[u8], modulus: &BigUint, e: &BigUint) -> Result<Vec<u8>, String> {
+    let hash = HashAlgorithm::Sha256;
+    let hash_length = hash.output_length();
+    let length = modulus.bits().div_ceil(8);
+    if length < 2 * hash_length + 2 || message.len() > length - 2 * hash_length - 2 {
+        return Err(format!(
+            "the key is too small for RSAES-OAEP with a {} byte message",
+            message.len()
+        ));
+    }
+
+    // DB = lHash || PS || 0x01 || M
+    let mut db = hash.digest(&[]);
+    db.resize(length - message.len() - hash_length - 2, 0);
+    db.push(0x01);
+    db.extend_from_slice(message);
 
-    let cypher_len = Pow::pow(&BigInt::from(256_u32), step_size)
-        .to_str_radix(10)
-        .len()
-        + 1;
-
-    for i in (0..message.len()).step_by(step_size as usize) {
-        if i + step_size as usize > message.len() {
-            slice = &message[i..message.len()];
-        } else {
-            slice = &message[i..i + step_size as usize];
-        }
-
-        let integer_slice = utils::bytes_array_to_integer(slice);
-        println!("integer_slice: {}", integer_slice);
-        let mut cypher_text = integer_slice.modpow(&e, &modulus).to_str_radix(10);
-        println!("cypher_text: {}", cypher_text);
-
-        if cypher_text.len() < cypher_len {
-            while cypher_len - cypher_text.len() > 0 {
-                cypher_text = "0".to_string() + &cypher_text;
-            }
-        }
-
-        encrypted_data.extend(cypher_text.into_bytes());
+    let mut seed = vec![0u8; hash_length];
+    OsRng.fill_bytes(&mut seed);
+    let db_mask = sign::mgf1(&seed, db.len(), hash);
+    for (byte, mask) in db.iter_mut().zip(db_mask) {
+        *byte ^= mask;
+    }
+    for (byte, mask) in seed.iter_mut().zip(sign::mgf1(&db, hash_length, hash)) {
+        *byte ^= mask;
     }
-    BigInt::parse_bytes(&encrypted_data, 10)
-        .unwrap()
-        .to_str_radix(16)
+
+    // EM = 0x00 || maskedSeed || maskedDB
+    let mut encoded = vec![0x00];
+    encoded.extend(seed);
+    encoded.extend(db);
+    let ciphertext = BigUint::from_bytes_be(&encoded)
+        .modpow(e, modulus)
+        .to_bytes_be();
+
+    let mut output = vec![0u8; length - ciphertext.len()];
+    output.extend(ciphertext);
+    Ok(output)
 }
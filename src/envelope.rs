@@ -0,0 +1,145 @@
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use num_bigint_dig::BigUint;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::decrypt;
+use crate::encrypt;
+use crate::fingerprint;
+use crate::key_storage::KeyPair;
+
+// A message for several recipients: the text is encrypted once with a random content key
+// and AES-256-GCM, and the content key is wrapped with RSAES-OAEP for every recipient.
+//
+// "MRE1" || recipient count (2 bytes) || per recipient: canonical fingerprint digest
+// (32 bytes), wrapped key length (2 bytes), wrapped key || nonce (12 bytes) || ciphertext
+// with the GCM tag. Everything before the nonce is authenticated as associated data, so
+// the recipient list cannot be changed unnoticed
+const MAGIC: &[u8] = b"MRE1";
+const CONTENT_KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const DIGEST_LENGTH: usize = 32;
+
+pub struct Recipient {
+    pub digest: Vec<u8>,
+    wrapped_key: Vec<u8>,
+}
+
+pub struct Envelope {
+    pub recipients: Vec<Recipient>,
+    header: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+pub fn is_envelope(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn seal(message: &[u8], recipients: &[(&BigUint, &BigUint)]) -> Result<Vec<u8>, String> {
+    if recipients.is_empty() {
+        return Err("choose at least one recipient".to_string());
+    }
+    let mut content_key = [0u8; CONTENT_KEY_LENGTH];
+    OsRng.fill_bytes(&mut content_key);
+
+    let mut header = MAGIC.to_vec();
+    header.extend_from_slice(&(recipients.len() as u16).to_be_bytes());
+    for (modulus, e) in recipients.iter() {
+        let wrapped_key = encrypt::encrypt_oaep(&content_key, modulus, e)?;
+        header.extend(fingerprint::canonical_digest(modulus, e));
+        header.extend_from_slice(&(wrapped_key.len() as u16).to_be_bytes());
+        header.extend(wrapped_key);
+    }
+
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = Aes256Gcm::new_from_slice(&content_key)
+        .unwrap()
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: message,
+                aad: &header,
+            },
+        )
+        .unwrap();
+
+    let mut envelope = header;
+    envelope.extend_from_slice(&nonce);
+    envelope.extend(ciphertext);
+    Ok(envelope)
+}
+
+fn take<'a>(input: &mut &'a [u8], length: usize) -> Result<&'a [u8], String> {
+    if input.len() < length {
+        return Err("the message is truncated".to_string());
+    }
+    let (value, rest) = input.split_at(length);
+    *input = rest;
+    Ok(value)
+}
+
+pub fn parse(data: &[u8]) -> Result<Envelope, String> {
+    if !is_envelope(data) {
+        return Err("not a message for several recipients".to_string());
+    }
+    let mut input = &data[MAGIC.len()..];
+
+    let count = u16::from_be_bytes(take(&mut input, 2)?.try_into().unwrap());
+    let mut recipients = Vec::new();
+    for _ in 0..count {
+        let digest = take(&mut input, DIGEST_LENGTH)?.to_vec();
+        let length = u16::from_be_bytes(take(&mut input, 2)?.try_into().unwrap()) as usize;
+        recipients.push(Recipient {
+            digest,
+            wrapped_key: take(&mut input, length)?.to_vec(),
+        });
+    }
+    let header = data[..data.len() - input.len()].to_vec();
+    let nonce = take(&mut input, NONCE_LENGTH)?.to_vec();
+
+    Ok(Envelope {
+        recipients,
+        header,
+        nonce,
+        ciphertext: input.to_vec(),
+    })
+}
+
+impl Envelope {
+    // Tries every key pair that is one of the recipients, returns the text and the key
+    // pair that opened it
+    pub fn open<'a>(&self, key_pairs: &[&'a KeyPair]) -> Result<(Vec<u8>, &'a KeyPair), String> {
+        for key_pair in key_pairs.iter() {
+            let digest = fingerprint::canonical_digest(&key_pair.modulus, &key_pair.e);
+            let recipient = match self
+                .recipients
+                .iter()
+                .find(|recipient| recipient.digest == digest)
+            {
+                Some(recipient) => recipient,
+                None => continue,
+            };
+
+            let content_key = decrypt::decrypt_oaep(&recipient.wrapped_key, key_pair)?;
+            let cipher = Aes256Gcm::new_from_slice(&content_key)
+                .map_err(|_| "the content key has the wrong length".to_string())?;
+            let message = cipher
+                .decrypt(
+                    Nonce::from_slice(&self.nonce),
+                    Payload {
+                        msg: &self.ciphertext,
+                        aad: &self.header,
+                    },
+                )
+                .map_err(|_| "the message was changed or is damaged".to_string())?;
+            return Ok((message, *key_pair));
+        }
+        Err(format!(
+            "none of your key pairs is one of the {} recipients",
+            self.recipients.len()
+        ))
+    }
+}
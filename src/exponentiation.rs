@@ -0,0 +1,116 @@
+use std::mem;
+
+use num_bigint_dig::BigUint;
+use num_traits::One;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Exponentiation {
+    // `BigUint::modpow` from num-bigint-dig
+    Builtin,
+    // Textbook left-to-right square-and-multiply, only multiplies on set bits (leaks d)
+    SquareAndMultiply,
+    // Montgomery ladder, one multiplication and one squaring for every bit
+    MontgomeryLadder,
+}
+
+impl Exponentiation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Exponentiation::Builtin => "builtin modpow",
+            Exponentiation::SquareAndMultiply => "square-and-multiply (leaky)",
+            Exponentiation::MontgomeryLadder => "Montgomery ladder",
+        }
+    }
+}
+
+pub fn modpow(
+    base: &BigUint,
+    exponent: &BigUint,
+    modulus: &BigUint,
+    exponentiation: Exponentiation,
+) -> BigUint {
+    match exponentiation {
+        Exponentiation::Builtin => base.modpow(exponent, modulus),
+        Exponentiation::SquareAndMultiply => square_and_multiply(base, exponent, modulus),
+        Exponentiation::MontgomeryLadder => montgomery_ladder(base, exponent, modulus),
+    }
+}
+
+pub fn square_and_multiply(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    square_and_multiply_traced(base, exponent, modulus, || {})
+}
+
+pub fn montgomery_ladder(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    montgomery_ladder_traced(base, exponent, modulus, || {})
+}
+
+// `on_step` is called after every processed exponent bit, starting from the most significant one
+pub fn square_and_multiply_traced<F>(
+    base: &BigUint,
+    exponent: &BigUint,
+    modulus: &BigUint,
+    mut on_step: F,
+) -> BigUint
+where
+    F: FnMut(),
+{
+    let base = base % modulus;
+    let mut result = BigUint::one() % modulus;
+
+    for bit in bits_msb_first(exponent, exponent.bits()) {
+        result = (&result * &result) % modulus;
+        // The extra multiplication only happens for set bits, so the time spent on
+        // every step tells whether the bit of the exponent is 0 or 1
+        if bit {
+            result = (&result * &base) % modulus;
+        }
+        on_step();
+    }
+    result
+}
+
+// Runs over every bit the modulus can have, so neither the number of operations nor
+// the length of the exponent depend on the secret. BigUint arithmetic itself is not
+// constant-time, the ladder only removes the operation pattern that leaks the bits
+pub fn montgomery_ladder_traced<F>(
+    base: &BigUint,
+    exponent: &BigUint,
+    modulus: &BigUint,
+    mut on_step: F,
+) -> BigUint
+where
+    F: FnMut(),
+{
+    let mut r0 = BigUint::one() % modulus;
+    let mut r1 = base % modulus;
+
+    for bit in bits_msb_first(exponent, modulus.bits().max(exponent.bits())) {
+        conditional_swap(&mut r0, &mut r1, bit);
+        r1 = (&r0 * &r1) % modulus;
+        r0 = (&r0 * &r0) % modulus;
+        conditional_swap(&mut r0, &mut r1, bit);
+        on_step();
+    }
+    r0
+}
+
+// Bits of `number` from the most significant one, left-padded with zeros to `length` bits
+pub fn bits_msb_first(number: &BigUint, length: usize) -> Vec<bool> {
+    let bytes = number.to_bytes_be();
+    let bits: Vec<bool> = bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+        .skip(bytes.len() * 8 - number.bits())
+        .collect();
+
+    let mut padded = vec![false; length.saturating_sub(bits.len())];
+    padded.extend(bits);
+    padded
+}
+
+fn conditional_swap(a: &mut BigUint, b: &mut BigUint, swap: bool) {
+    // Swapping two BigUint values only exchanges their heap pointers
+    if swap {
+        mem::swap(a, b);
+    }
+}
@@ -0,0 +1,103 @@
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use num_bigint_dig::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::openssh;
+use crate::pkcs1;
+
+// Size of the randomart field, the same as OpenSSH uses
+const FIELD_WIDTH: usize = 17;
+const FIELD_HEIGHT: usize = 9;
+
+// Characters for how often the bishop visited a square, 'S' and 'E' mark start and end
+const AUGMENTATION: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+// SHA-256 of the PKCS#1 RSAPublicKey DER. DER has exactly one encoding for a key, so
+// everybody holding the same public key gets the same digest
+pub fn canonical_digest(modulus: &BigUint, e: &BigUint) -> Vec<u8> {
+    Sha256::digest(pkcs1::public_key_to_der(modulus, e)).to_vec()
+}
+
+// Canonical fingerprint as colon separated hex
+pub fn canonical(modulus: &BigUint, e: &BigUint) -> String {
+    canonical_digest(modulus, e)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<String>>()
+        .join(":")
+}
+
+// SHA-256 of the SSH public key blob, what `ssh-keygen -l` hashes
+pub fn openssh_digest(modulus: &BigUint, e: &BigUint) -> Vec<u8> {
+    Sha256::digest(openssh::public_key_blob(modulus, e)).to_vec()
+}
+
+// "SHA256:" and the unpadded base64 digest, as printed by `ssh-keygen -l`
+pub fn openssh(modulus: &BigUint, e: &BigUint) -> String {
+    format!(
+        "SHA256:{}",
+        STANDARD_NO_PAD.encode(openssh_digest(modulus, e))
+    )
+}
+
+// The randomart `ssh-keygen -lv` prints for the key
+pub fn openssh_randomart(modulus: &BigUint, e: &BigUint) -> Vec<String> {
+    randomart(
+        &openssh_digest(modulus, e),
+        &format!("RSA {}", modulus.bits()),
+        "SHA256",
+    )
+}
+
+// "Drunken bishop" walk of OpenSSH: every byte of the digest moves the bishop four times
+// diagonally, two bits per move, starting in the middle of the field. Squares visited more
+// often get denser characters, so different digests give visibly different pictures
+pub fn randomart(digest: &[u8], title: &str, footer: &str) -> Vec<String> {
+    let mut field = [[0usize; FIELD_HEIGHT]; FIELD_WIDTH];
+    let last = AUGMENTATION.len() - 1;
+    let (mut x, mut y) = (FIELD_WIDTH / 2, FIELD_HEIGHT / 2);
+
+    for byte in digest {
+        let mut input = *byte;
+        for _ in 0..4 {
+            x = if input & 1 != 0 {
+                (x + 1).min(FIELD_WIDTH - 1)
+            } else {
+                x.saturating_sub(1)
+            };
+            y = if input & 2 != 0 {
+                (y + 1).min(FIELD_HEIGHT - 1)
+            } else {
+                y.saturating_sub(1)
+            };
+            // The last two characters are reserved for start and end
+            if field[x][y] < last - 2 {
+                field[x][y] += 1;
+            }
+            input >>= 2;
+        }
+    }
+    field[FIELD_WIDTH / 2][FIELD_HEIGHT / 2] = last - 1;
+    field[x][y] = last;
+
+    let mut lines = vec![border(&format!("[{}]", title))];
+    for row in 0..FIELD_HEIGHT {
+        let squares: String = (0..FIELD_WIDTH)
+            .map(|column| AUGMENTATION[field[column][row].min(last)] as char)
+            .collect();
+        lines.push(format!("|{}|", squares));
+    }
+    lines.push(border(&format!("[{}]", footer)));
+    lines
+}
+
+// "+---[label]----+", the label centered the way OpenSSH rounds it
+fn border(label: &str) -> String {
+    if label.len() > FIELD_WIDTH {
+        return format!("+{}+", "-".repeat(FIELD_WIDTH));
+    }
+    let left = (FIELD_WIDTH - label.len()) / 2;
+    let right = FIELD_WIDTH - label.len() - left;
+    format!("+{}{}{}+", "-".repeat(left), label, "-".repeat(right))
+}
@@ -0,0 +1,332 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use num_bigint_dig::{BigInt, BigUint, ModInverse, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+use crate::key_storage::{GenerationMethod, KeyPair};
+use crate::prime_search;
+
+// FIPS 186-5 only approves moduli of at least 2048 bits
+pub const MIN_MODULUS_SIZE: usize = 2048;
+
+// Output length of SHA-256, the hash used by the Shawe-Taylor construction
+const HASH_BITS: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PrimeKind {
+    // A.1.3: random primes accepted after the Miller-Rabin rounds of table B.1
+    Probable,
+    // A.1.2: primes built with the Shawe-Taylor algorithm, which proves them prime
+    Provable,
+}
+
+impl PrimeKind {
+    pub fn generation_method(&self) -> GenerationMethod {
+        match self {
+            PrimeKind::Probable => GenerationMethod::Fips186ProbablePrimes,
+            PrimeKind::Provable => GenerationMethod::Fips186ProvablePrimes,
+        }
+    }
+}
+
+// FIPS 186-5 A.1.1: e has to be odd and 2^16 < e < 2^256
+pub fn check_exponent(e: &BigUint) -> Result<(), String> {
+    if e.is_even() || e <= &(BigUint::one() << 16) || e >= &(BigUint::one() << 256) {
+        return Err("e has to be odd and between 2^16 and 2^256".to_string());
+    }
+    Ok(())
+}
+
+pub fn check_modulus_size(modulus_size: usize) -> Result<(), String> {
+    if modulus_size < MIN_MODULUS_SIZE || !modulus_size.is_multiple_of(2) {
+        return Err(format!(
+            "the modulus has to be an even number of at least {} bits",
+            MIN_MODULUS_SIZE
+        ));
+    }
+    Ok(())
+}
+
+// Security strength of a modulus in bits (SP 800-57), used for the seed length
+pub fn security_strength(modulus_size: usize) -> usize {
+    match modulus_size {
+        0..=2047 => 80,
+        2048..=3071 => 112,
+        3072..=4095 => 128,
+        4096..=7679 => 152,
+        7680..=15359 => 192,
+        _ => 256,
+    }
+}
+
+// Table B.1: Miller-Rabin rounds for the primes of a probable-prime key
+pub fn miller_rabin_rounds(modulus_size: usize) -> usize {
+    match modulus_size {
+        0..=2047 => 64,
+        2048..=3071 => 5,
+        _ => 4,
+    }
+}
+
+// FIPS 186-5 A.1 key pair: both primes of nlen / 2 bits with p, q >= sqrt(2) * 2^(nlen/2 - 1),
+// gcd(p - 1, e) = gcd(q - 1, e) = 1, |p - q| > 2^(nlen/2 - 100) and
+// 2^(nlen/2) < d < LCM(p - 1, q - 1). Gives up with an error once `cancel` is set
+pub fn generate_key_pair(
+    modulus_size: usize,
+    e: &BigUint,
+    kind: PrimeKind,
+    cancel: &AtomicBool,
+) -> Result<KeyPair, String> {
+    check_modulus_size(modulus_size)?;
+    check_exponent(e)?;
+
+    let half = modulus_size / 2;
+    let min_distance = BigUint::one() << (half - 100);
+    let min_d = BigUint::one() << half;
+
+    loop {
+        let generate_prime = match kind {
+            PrimeKind::Probable => generate_probable_prime,
+            PrimeKind::Provable => generate_provable_prime,
+        };
+        // A prime search that gives up starts the generation over
+        let p = match generate_prime(modulus_size, e, None, cancel)? {
+            Some(p) => p,
+            None => continue,
+        };
+        let q = match generate_prime(modulus_size, e, Some(&p), cancel)? {
+            Some(q) => q,
+            None => continue,
+        };
+
+        if distance(&p, &q) <= min_distance {
+            continue;
+        }
+
+        let one = BigUint::one();
+        let lcm = (&p - &one).lcm(&(&q - &one));
+        let d: BigInt = match e.mod_inverse(&lcm) {
+            Some(d) => d,
+            None => continue,
+        };
+        // A tiny d would be open to Wiener-style attacks, the standard starts over
+        if d.to_biguint().unwrap() <= min_d {
+            continue;
+        }
+
+        let modulus = &p * &q;
+        let mut key_pair = KeyPair::new("default".to_string(), p, q, modulus, e.clone(), d);
+        key_pair.generation_method = kind.generation_method();
+        return Ok(key_pair);
+    }
+}
+
+// A.1.3 steps 4 and 5: random odd candidates of nlen / 2 bits, giving up with None after
+// 5 * nlen / 2 tries. `other` is p when generating q
+fn generate_probable_prime(
+    modulus_size: usize,
+    e: &BigUint,
+    other: Option<&BigUint>,
+    cancel: &AtomicBool,
+) -> Result<Option<BigUint>, String> {
+    let half = modulus_size / 2;
+    let rounds = miller_rabin_rounds(modulus_size);
+    let min_distance = BigUint::one() << (half - 100);
+    let mut rng = OsRng;
+
+    for _ in 0..5 * half {
+        check_cancel(cancel)?;
+        let candidate = rng.gen_biguint(half) | BigUint::one();
+
+        if !is_acceptable(&candidate, modulus_size, e) {
+            continue;
+        }
+        if let Some(p) = other {
+            if distance(p, &candidate) <= min_distance {
+                continue;
+            }
+        }
+        if prime_search::is_probable_prime(&candidate, rounds, &mut rng) {
+            return Ok(Some(candidate));
+        }
+    }
+    Ok(None)
+}
+
+// A.1.2 with primes from the Shawe-Taylor construction (B.10). The random seed is
+// 2 * security_strength bits long, a prime that does not meet the A.1 bounds is
+// replaced by the next one derived from the updated seed
+fn generate_provable_prime(
+    modulus_size: usize,
+    e: &BigUint,
+    other: Option<&BigUint>,
+    cancel: &AtomicBool,
+) -> Result<Option<BigUint>, String> {
+    let half = modulus_size / 2;
+    let min_distance = BigUint::one() << (half - 100);
+    let seed_bytes = 2 * security_strength(modulus_size) / 8;
+    let mut seed = OsRng.gen_biguint(seed_bytes * 8);
+
+    for _ in 0..5 * half {
+        check_cancel(cancel)?;
+        let (prime, next_seed) = shawe_taylor_random_prime(half, &seed, seed_bytes)?;
+        seed = next_seed;
+
+        if !is_acceptable(&prime, modulus_size, e) {
+            continue;
+        }
+        if let Some(p) = other {
+            if distance(p, &prime) <= min_distance {
+                continue;
+            }
+        }
+        return Ok(Some(prime));
+    }
+    Ok(None)
+}
+
+// p >= sqrt(2) * 2^(nlen/2 - 1), checked as p^2 >= 2^(nlen - 1), and gcd(p - 1, e) = 1
+fn is_acceptable(candidate: &BigUint, modulus_size: usize, e: &BigUint) -> bool {
+    candidate * candidate >= BigUint::one() << (modulus_size - 1)
+        && (candidate - BigUint::one()).gcd(e).is_one()
+}
+
+fn check_cancel(cancel: &AtomicBool) -> Result<(), String> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err("key generation cancelled".to_string());
+    }
+    Ok(())
+}
+
+fn distance(a: &BigUint, b: &BigUint) -> BigUint {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+// ST_Random_Prime (FIPS 186-5 B.10 / FIPS 186-4 C.6) with SHA-256. Returns the prime
+// and the updated seed. Small primes come from hashing the seed, bigger ones are
+// built from a prime c0 of half the size and proven with Pocklington's criterion
+pub fn shawe_taylor_random_prime(
+    length: usize,
+    seed: &BigUint,
+    seed_bytes: usize,
+) -> Result<(BigUint, BigUint), String> {
+    let (prime, seed, _) = shawe_taylor(length, seed, seed_bytes)?;
+    Ok((prime, seed))
+}
+
+fn shawe_taylor(
+    length: usize,
+    input_seed: &BigUint,
+    seed_bytes: usize,
+) -> Result<(BigUint, BigUint, usize), String> {
+    if length < 2 {
+        return Err("prime length has to be at least 2 bits".to_string());
+    }
+
+    let one = BigUint::one();
+    let two = BigUint::from(2u32);
+
+    if length < 33 {
+        let mut prime_seed = input_seed.clone();
+        let mut prime_gen_counter = 0;
+
+        loop {
+            let hash_xor: Vec<u8> = hash(&prime_seed, seed_bytes)
+                .iter()
+                .zip(hash(&(&prime_seed + &one), seed_bytes).iter())
+                .map(|(a, b)| a ^ b)
+                .collect();
+            let c = BigUint::from_bytes_be(&hash_xor);
+            let c = (&one << (length - 1)) + (c % (&one << (length - 1)));
+            let c = &c | &one;
+
+            prime_gen_counter += 1;
+            prime_seed += &two;
+
+            if is_prime_by_trial_division(c.to_u64().unwrap()) {
+                return Ok((c, prime_seed, prime_gen_counter));
+            }
+            if prime_gen_counter > 4 * length {
+                return Err("Shawe-Taylor construction failed".to_string());
+            }
+        }
+    }
+
+    let (c0, mut prime_seed, mut prime_gen_counter) =
+        shawe_taylor(length.div_ceil(2) + 1, input_seed, seed_bytes)?;
+
+    let iterations = length.div_ceil(HASH_BITS) - 1;
+    let old_counter = prime_gen_counter;
+
+    let x = hash_expand(&prime_seed, iterations, seed_bytes);
+    prime_seed += BigUint::from(iterations + 1);
+    let x = (&one << (length - 1)) + (x % (&one << (length - 1)));
+
+    let two_c0 = &two * &c0;
+    let mut t = ceil_div(&x, &two_c0);
+
+    loop {
+        if &two_c0 * &t + &one > &one << length {
+            t = ceil_div(&(&one << (length - 1)), &two_c0);
+        }
+        let c = &two_c0 * &t + &one;
+        prime_gen_counter += 1;
+
+        let a = hash_expand(&prime_seed, iterations, seed_bytes);
+        prime_seed += BigUint::from(iterations + 1);
+        let a = &two + (a % (&c - BigUint::from(3u32)));
+
+        let z = a.modpow(&(&two * &t), &c);
+        if (&z - &one).gcd(&c).is_one() && z.modpow(&c0, &c).is_one() {
+            return Ok((c, prime_seed, prime_gen_counter));
+        }
+
+        if prime_gen_counter >= 4 * length + old_counter {
+            return Err("Shawe-Taylor construction failed".to_string());
+        }
+        t += &one;
+    }
+}
+
+// Hash(seed) || ... concatenated as the number sum of Hash(seed + i) * 2^(i * outlen)
+fn hash_expand(seed: &BigUint, iterations: usize, seed_bytes: usize) -> BigUint {
+    let mut x = BigUint::zero();
+    for i in 0..=iterations {
+        let block = BigUint::from_bytes_be(&hash(&(seed + BigUint::from(i)), seed_bytes));
+        x += block << (i * HASH_BITS);
+    }
+    x
+}
+
+// SHA-256 of the seed as a big-endian bit string of the original seed length
+fn hash(seed: &BigUint, seed_bytes: usize) -> Vec<u8> {
+    let bytes = seed.to_bytes_be();
+    let mut padded = vec![0u8; seed_bytes.saturating_sub(bytes.len())];
+    padded.extend(bytes);
+    Sha256::digest(&padded).to_vec()
+}
+
+fn ceil_div(a: &BigUint, b: &BigUint) -> BigUint {
+    (a + b - BigUint::one()) / b
+}
+
+fn is_prime_by_trial_division(number: u64) -> bool {
+    if number < 2 {
+        return false;
+    }
+    let mut divisor = 2;
+    while divisor * divisor <= number {
+        if number.is_multiple_of(divisor) {
+            return false;
+        }
+        divisor += 1;
+    }
+    true
+}
@@ -0,0 +1,115 @@
+use num_bigint_dig::{BigInt, BigUint, ModInverse, RandBigInt};
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::key_storage::KeyPair;
+
+pub struct HomomorphismDemo {
+    pub a: BigUint,
+    pub b: BigUint,
+    pub encrypted_a: BigUint,
+    pub encrypted_b: BigUint,
+    pub encrypted_product: BigUint,
+    pub decrypted_product: BigUint,
+    // a * b mod n, what the decrypted product has to be equal to
+    pub expected_product: BigUint,
+}
+
+pub struct BlindingAttack {
+    pub target: BigUint,
+    pub r: BigUint,
+    pub blinded_ciphertext: BigUint,
+    pub blinded_plaintext: BigUint,
+    pub recovered: BigUint,
+}
+
+// Decryption oracle that decrypts any ciphertext except the one it was asked to protect
+pub struct DecryptionOracle<'a> {
+    key_pair: &'a KeyPair,
+    refused: BigUint,
+}
+
+impl<'a> DecryptionOracle<'a> {
+    pub fn new(key_pair: &'a KeyPair, refused: BigUint) -> Self {
+        DecryptionOracle { key_pair, refused }
+    }
+
+    pub fn decrypt(&self, ciphertext: &BigUint) -> Option<BigUint> {
+        if ciphertext % &self.key_pair.modulus == self.refused {
+            return None;
+        }
+        Some(decrypt_number(
+            ciphertext,
+            &self.key_pair.modulus,
+            &self.key_pair.d,
+        ))
+    }
+}
+
+// Textbook RSA on a single number: m^e mod n
+pub fn encrypt_number(message: &BigUint, modulus: &BigUint, e: &BigUint) -> BigUint {
+    message.modpow(e, modulus)
+}
+
+// Textbook RSA on a single number: c^d mod n
+pub fn decrypt_number(ciphertext: &BigUint, modulus: &BigUint, d: &BigInt) -> BigUint {
+    ciphertext.modpow(&d.to_biguint().unwrap(), modulus)
+}
+
+// E(a) * E(b) = (a * b)^e = E(a * b) mod n
+pub fn multiply_ciphertexts(first: &BigUint, second: &BigUint, modulus: &BigUint) -> BigUint {
+    (first * second) % modulus
+}
+
+pub fn demonstrate_homomorphism(a: &BigUint, b: &BigUint, key_pair: &KeyPair) -> HomomorphismDemo {
+    let encrypted_a = encrypt_number(a, &key_pair.modulus, &key_pair.e);
+    let encrypted_b = encrypt_number(b, &key_pair.modulus, &key_pair.e);
+    let encrypted_product = multiply_ciphertexts(&encrypted_a, &encrypted_b, &key_pair.modulus);
+    let decrypted_product = decrypt_number(&encrypted_product, &key_pair.modulus, &key_pair.d);
+
+    HomomorphismDemo {
+        a: a.clone(),
+        b: b.clone(),
+        encrypted_a,
+        encrypted_b,
+        encrypted_product,
+        decrypted_product,
+        expected_product: (a * b) % &key_pair.modulus,
+    }
+}
+
+// Chosen-ciphertext attack: the target is blinded with a random r as c * r^e mod n,
+// the oracle decrypts the blinded ciphertext to m * r and dividing by r reveals m
+pub fn blinding_attack(
+    target: &BigUint,
+    modulus: &BigUint,
+    e: &BigUint,
+    oracle: &DecryptionOracle,
+) -> Option<BlindingAttack> {
+    let mut rng = rand::thread_rng();
+
+    loop {
+        let r = rng.gen_biguint_range(&BigUint::from(2u32), modulus);
+        if !r.gcd(modulus).is_one() {
+            continue;
+        }
+
+        let blinded_ciphertext =
+            multiply_ciphertexts(target, &encrypt_number(&r, modulus, e), modulus);
+        if &blinded_ciphertext == target {
+            continue;
+        }
+
+        let blinded_plaintext = oracle.decrypt(&blinded_ciphertext)?;
+        let r_inverse: BigInt = (&r).mod_inverse(modulus)?;
+        let recovered = (&blinded_plaintext * r_inverse.to_biguint()?) % modulus;
+
+        return Some(BlindingAttack {
+            target: target.clone(),
+            r,
+            blinded_ciphertext,
+            blinded_plaintext,
+            recovered,
+        });
+    }
+}
@@ -0,0 +1,184 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use num_bigint_dig::{BigInt, BigUint};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::key_storage::{self, GenerationMethod, KeyPair, Storage};
+
+const KEY_TYPE: &str = "RSA";
+
+// RFC 7517 JSON Web Key with the RSA members of RFC 7518 6.3. All numbers are unsigned
+// big-endian base64url without padding. Members this tool does not use, like "use" or
+// "alg", are ignored when reading
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    pub n: String,
+    pub e: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dq: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qi: Option<String>,
+    // Primes after the second of a multi-prime key
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub oth: Vec<OtherPrime>,
+}
+
+// RFC 7518 6.3.2.7: prime r, CRT exponent d and CRT coefficient t
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct OtherPrime {
+    pub r: String,
+    pub d: String,
+    pub t: String,
+}
+
+// RFC 7517 5 JWK Set
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+pub fn encode_integer(value: &BigUint) -> String {
+    URL_SAFE_NO_PAD.encode(value.to_bytes_be())
+}
+
+pub fn decode_integer(value: &str) -> Result<BigUint, String> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map(|bytes| BigUint::from_bytes_be(&bytes))
+        .map_err(|error| format!("invalid base64url \"{}\": {}", value, error))
+}
+
+// RFC 7638: SHA-256 over the required members in lexicographic order without whitespace,
+// so the same public key always gets the same id no matter who wrote the JWK
+pub fn thumbprint(modulus: &BigUint, e: &BigUint) -> String {
+    let canonical = format!(
+        r#"{{"e":"{}","kty":"{}","n":"{}"}}"#,
+        encode_integer(e),
+        KEY_TYPE,
+        encode_integer(modulus)
+    );
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+pub fn public_key_to_jwk(modulus: &BigUint, e: &BigUint) -> Jwk {
+    Jwk {
+        kty: KEY_TYPE.to_string(),
+        kid: Some(thumbprint(modulus, e)),
+        n: encode_integer(modulus),
+        e: encode_integer(e),
+        d: None,
+        p: None,
+        q: None,
+        dp: None,
+        dq: None,
+        qi: None,
+        oth: Vec::new(),
+    }
+}
+
+pub fn private_key_to_jwk(key_pair: &KeyPair) -> Jwk {
+    Jwk {
+        d: Some(encode_integer(&key_pair.d.to_biguint().unwrap())),
+        p: Some(encode_integer(&key_pair.p)),
+        q: Some(encode_integer(&key_pair.q)),
+        dp: Some(encode_integer(&key_pair.dp())),
+        dq: Some(encode_integer(&key_pair.dq())),
+        qi: key_pair.qinv().map(|qinv| encode_integer(&qinv)),
+        oth: key_pair
+            .other_primes
+            .iter()
+            .map(|info| OtherPrime {
+                r: encode_integer(&info.prime),
+                d: encode_integer(&info.exponent),
+                t: encode_integer(&info.coefficient),
+            })
+            .collect(),
+        ..public_key_to_jwk(&key_pair.modulus, &key_pair.e)
+    }
+}
+
+// Public keys of every key pair and contact, in storage order
+pub fn storage_to_jwks(storage: &Storage) -> Jwks {
+    let mut keys: Vec<Jwk> = storage
+        .key_pairs()
+        .map(|key_pair| public_key_to_jwk(&key_pair.modulus, &key_pair.e))
+        .collect();
+    keys.extend(
+        storage
+            .contacts()
+            .map(|contact| public_key_to_jwk(&contact.modulus, &contact.e)),
+    );
+    Jwks { keys }
+}
+
+// Reads a single JWK or every key of a JWK Set
+pub fn parse(json: &str) -> Result<Vec<Jwk>, String> {
+    let document: serde_json::Value =
+        serde_json::from_str(json).map_err(|error| format!("invalid JSON: {}", error))?;
+    let keys = if document.get("keys").is_some() {
+        serde_json::from_value::<Jwks>(document).map(|jwks| jwks.keys)
+    } else {
+        serde_json::from_value::<Jwk>(document).map(|jwk| vec![jwk])
+    }
+    .map_err(|error| format!("invalid JWK: {}", error))?;
+
+    for jwk in keys.iter() {
+        if jwk.kty != KEY_TYPE {
+            return Err(format!("unsupported key type {}", jwk.kty));
+        }
+    }
+    Ok(keys)
+}
+
+impl Jwk {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.d.is_some()
+    }
+
+    // Returns the modulus and the public exponent
+    pub fn public_key(&self) -> Result<(BigUint, BigUint), String> {
+        Ok((decode_integer(&self.n)?, decode_integer(&self.e)?))
+    }
+
+    // RFC 7518 allows private keys with only "d", but decryption with CRT needs the primes
+    pub fn to_key_pair(&self, name: String) -> Result<KeyPair, String> {
+        let (modulus, e) = self.public_key()?;
+        let member = |value: &Option<String>, name: &str| match value {
+            Some(value) => decode_integer(value),
+            None => Err(format!("the JWK has no \"{}\"", name)),
+        };
+        let d = member(&self.d, "d")?;
+        let mut primes = vec![member(&self.p, "p")?, member(&self.q, "q")?];
+        for other in self.oth.iter() {
+            primes.push(decode_integer(&other.r)?);
+        }
+        key_storage::check_private_key(&modulus, &e, &d, &primes)?;
+
+        let mut key_pair = KeyPair::new_multi_prime(name, primes, modulus, e, BigInt::from(d))
+            .ok_or("the primes have to be distinct and pairwise coprime")?;
+        key_pair.generation_method = GenerationMethod::Imported;
+        Ok(key_pair)
+    }
+}
+
+impl Jwks {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
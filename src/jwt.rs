@@ -0,0 +1,228 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{TimeZone, Utc};
+use num_bigint_dig::BigUint;
+use serde_json::{Map, Value};
+
+use crate::jwk;
+use crate::key_storage::KeyPair;
+use crate::sign::{self, HashAlgorithm};
+
+// Seconds of clock difference tolerated when checking "exp" and "nbf"
+pub const LEEWAY: i64 = 60;
+
+// RFC 7518 3.1 algorithms that need an RSA key
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Algorithm {
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::RS256,
+        Algorithm::RS384,
+        Algorithm::RS512,
+        Algorithm::PS256,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::RS256 => "RS256",
+            Algorithm::RS384 => "RS384",
+            Algorithm::RS512 => "RS512",
+            Algorithm::PS256 => "PS256",
+        }
+    }
+
+    // "none" and the HMAC algorithms are refused, a token is only trusted if an RSA key
+    // signed it
+    pub fn from_name(name: &str) -> Result<Algorithm, String> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+            .ok_or_else(|| format!("unsupported algorithm \"{}\"", name))
+    }
+
+    fn hash(&self) -> HashAlgorithm {
+        match self {
+            Algorithm::RS256 | Algorithm::PS256 => HashAlgorithm::Sha256,
+            Algorithm::RS384 => HashAlgorithm::Sha384,
+            Algorithm::RS512 => HashAlgorithm::Sha512,
+        }
+    }
+
+    fn sign(&self, data: &[u8], key_pair: &KeyPair) -> Result<Vec<u8>, String> {
+        match self {
+            Algorithm::PS256 => sign::sign_pss(data, self.hash(), key_pair),
+            _ => sign::sign_pkcs1_v15(data, self.hash(), key_pair),
+        }
+        .map_err(|error| format!("cannot sign with {}: {}", self.name(), error))
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8], modulus: &BigUint, e: &BigUint) -> bool {
+        match self {
+            Algorithm::PS256 => sign::verify_pss(data, signature, self.hash(), modulus, e),
+            _ => sign::verify_pkcs1_v15(data, signature, self.hash(), modulus, e),
+        }
+    }
+}
+
+// A JWS in compact serialization, split into its parts but not yet verified
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub header: Map<String, Value>,
+    pub claims: Map<String, Value>,
+    pub signature: Vec<u8>,
+    // "header.claims" exactly as received, this is what the signature covers
+    signing_input: String,
+}
+
+pub fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+// Registered claims of RFC 7519 4.1 for a token valid from `now` for `lifetime` seconds
+pub fn claims(subject: &str, issuer: &str, now: i64, lifetime: i64) -> Map<String, Value> {
+    let mut claims = Map::new();
+    if !issuer.is_empty() {
+        claims.insert("iss".to_string(), Value::from(issuer));
+    }
+    claims.insert("sub".to_string(), Value::from(subject));
+    claims.insert("iat".to_string(), Value::from(now));
+    claims.insert("nbf".to_string(), Value::from(now));
+    claims.insert("exp".to_string(), Value::from(now + lifetime));
+    claims
+}
+
+// The header names the key by its RFC 7638 thumbprint, the same "kid" the JWK export uses
+pub fn encode(
+    claims: &Map<String, Value>,
+    algorithm: Algorithm,
+    key_pair: &KeyPair,
+) -> Result<String, String> {
+    let mut header = Map::new();
+    header.insert("alg".to_string(), Value::from(algorithm.name()));
+    header.insert("typ".to_string(), Value::from("JWT"));
+    header.insert(
+        "kid".to_string(),
+        Value::from(jwk::thumbprint(&key_pair.modulus, &key_pair.e)),
+    );
+
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(Value::Object(header).to_string()),
+        URL_SAFE_NO_PAD.encode(Value::Object(claims.clone()).to_string())
+    );
+    let signature = algorithm.sign(signing_input.as_bytes(), key_pair)?;
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+pub fn decode(token: &str) -> Result<Token, String> {
+    let token = token.trim();
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err(format!(
+            "a JWT has 3 parts separated by '.', this one has {}",
+            parts.len()
+        ));
+    }
+
+    let object = |part: &str, name: &str| -> Result<Map<String, Value>, String> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|error| format!("the {} is not base64url: {}", name, error))?;
+        match serde_json::from_slice(&bytes) {
+            Ok(Value::Object(object)) => Ok(object),
+            Ok(_) => Err(format!("the {} is not a JSON object", name)),
+            Err(error) => Err(format!("the {} is not JSON: {}", name, error)),
+        }
+    };
+    let signature = URL_SAFE_NO_PAD
+        .decode(parts[2])
+        .map_err(|error| format!("the signature is not base64url: {}", error))?;
+
+    Ok(Token {
+        header: object(parts[0], "header")?,
+        claims: object(parts[1], "claims")?,
+        signature,
+        signing_input: format!("{}.{}", parts[0], parts[1]),
+    })
+}
+
+impl Token {
+    pub fn algorithm(&self) -> Result<Algorithm, String> {
+        match self.header.get("alg") {
+            Some(Value::String(name)) => Algorithm::from_name(name),
+            _ => Err("the header has no \"alg\"".to_string()),
+        }
+    }
+
+    pub fn kid(&self) -> Option<&str> {
+        self.header.get("kid").and_then(Value::as_str)
+    }
+
+    pub fn verify_signature(&self, modulus: &BigUint, e: &BigUint) -> Result<(), String> {
+        let algorithm = self.algorithm()?;
+        if algorithm.verify(self.signing_input.as_bytes(), &self.signature, modulus, e) {
+            Ok(())
+        } else {
+            Err(format!("the {} signature is not valid", algorithm.name()))
+        }
+    }
+
+    // RFC 7519 4.1.4 and 4.1.5, both claims are optional
+    pub fn check_time(&self, now: i64, leeway: i64) -> Result<(), String> {
+        if let Some(expires) = self.time_claim("exp")? {
+            if now - leeway >= expires {
+                return Err(format!("the token expired at {}", time_text(expires)));
+            }
+        }
+        if let Some(not_before) = self.time_claim("nbf")? {
+            if now + leeway < not_before {
+                return Err(format!(
+                    "the token is not valid before {}",
+                    time_text(not_before)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Signature first, a token with a bad signature says nothing about its times
+    pub fn verify(&self, modulus: &BigUint, e: &BigUint, now: i64) -> Result<(), String> {
+        self.verify_signature(modulus, e)?;
+        self.check_time(now, LEEWAY)
+    }
+
+    fn time_claim(&self, name: &str) -> Result<Option<i64>, String> {
+        match self.claims.get(name) {
+            None => Ok(None),
+            Some(value) => value
+                .as_f64()
+                .map(|seconds| Some(seconds as i64))
+                .ok_or_else(|| format!("\"{}\" is not a number", name)),
+        }
+    }
+
+    pub fn header_json(&self) -> String {
+        serde_json::to_string_pretty(&self.header).unwrap()
+    }
+
+    pub fn claims_json(&self) -> String {
+        serde_json::to_string_pretty(&self.claims).unwrap()
+    }
+}
+
+pub fn time_text(seconds: i64) -> String {
+    match Utc.timestamp_opt(seconds, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => seconds.to_string(),
+    }
+}
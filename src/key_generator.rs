@@ -1,12 +1,26 @@
-use num_bigint_dig::{BigInt, BigUint, ModInverse};
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::Sender;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_bigint_dig::{BigInt, BigUint, ModInverse, RandPrime};
 use num_primes::Generator;
 use num_traits::One;
+use rand::rngs::{OsRng, StdRng};
+use rand::{CryptoRng, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+use crate::crt;
+use crate::key_storage::{GenerationMethod, KeyPair};
+use crate::prime_search::{self, PrimeSearch, SearchProgress};
 
-use crate::decrypt::decrypt;
-use crate::encrypt::encrypt;
-use crate::key_storage::KeyPair;
+pub const EXPONENT: u64 = 65537;
 
-const EXPONENT: u64 = 65537;
+// Number of distinct seeds the weak generator can draw from
+const WEAK_RNG_SEEDS: u64 = 4;
+
+// RFC 8017 allows any number of primes, the tool supports 2 to 5 of them
+pub const MAX_PRIMES: usize = 5;
 
 pub fn generate_primes(key_size: usize) -> (BigUint, BigUint) {
     let p = Generator::new_prime(key_size);
@@ -17,35 +31,208 @@ pub fn generate_primes(key_size: usize) -> (BigUint, BigUint) {
     (p, q)
 }
 
+// DEMO ONLY: p comes from an RNG seeded with one of a handful of values, so keys
+// generated this way regularly share a prime and can be broken by a batch-GCD scan.
+// Returns None once `cancel` is set
+pub fn generate_weak_primes(key_size: usize, cancel: &AtomicBool) -> Option<(BigUint, BigUint)> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos() as u64;
+    let mut weak_rng = StdRng::seed_from_u64(nanos % WEAK_RNG_SEEDS);
+    let p = prime_search::gen_prime(&mut weak_rng, key_size, cancel)?;
+
+    let q = prime_search::gen_prime(&mut OsRng, key_size, cancel)?;
+    Some((p, q))
+}
+
+pub fn generate_multi_primes(modulus_size: usize, prime_count: usize) -> Vec<BigUint> {
+    // gen_prime sets the two top bits, so the product is rarely short of `modulus_size`
+    prime_sizes(modulus_size, prime_count)
+        .into_iter()
+        .map(|size| OsRng.gen_prime(size))
+        .collect()
+}
+
+// Splits `modulus_size` bits between `prime_count` primes
+fn prime_sizes(modulus_size: usize, prime_count: usize) -> Vec<usize> {
+    (0..prime_count)
+        .map(|i| modulus_size / prime_count + usize::from(i < modulus_size % prime_count))
+        .collect()
+}
+
+// Primes with their two top bits set multiply to the full size when there are two of
+// them, three or more can come out a bit short and are generated again
+fn has_modulus_size(primes: &[BigUint], modulus_size: usize) -> bool {
+    primes.iter().product::<BigUint>().bits() == modulus_size
+}
+
+// ChaCha20 seeded with SHA-256 of the seed phrase. Anyone who knows the phrase can
+// regenerate the key, so seeded keys are only fit for exercises and tests
+pub fn seeded_rng(seed: &str) -> ChaCha20Rng {
+    ChaCha20Rng::from_seed(Sha256::digest(seed.as_bytes()).into())
+}
+
 pub fn generate_key_pair(key_size: usize) -> KeyPair {
     loop {
-        let exponent: BigUint = BigUint::from(EXPONENT);
-
         let (p, q) = generate_primes(key_size);
-        let modulus: BigUint = &p * &q;
-        let phi: BigUint = (&p - BigUint::one()) * (&q - BigUint::one());
-        let d: BigInt = (&exponent).mod_inverse(&phi).unwrap();
-        let key_pair = KeyPair::new("default".to_string(), p, q, modulus, exponent, d);
 
-        if test_key_pair(&key_pair) == true {
+        if let Some(key_pair) = build_key_pair(vec![p, q], GenerationMethod::Student) {
+            return key_pair;
+        }
+    }
+}
+
+// Returns None as soon as `cancel` is set
+pub fn generate_weak_key_pair(key_size: usize, cancel: &AtomicBool) -> Option<KeyPair> {
+    loop {
+        let (p, q) = generate_weak_primes(key_size, cancel)?;
+
+        if let Some(key_pair) = build_key_pair(vec![p, q], GenerationMethod::WeakRng) {
+            return Some(key_pair);
+        }
+    }
+}
+
+// Same as `generate_key_pair`, but every random number comes from `rng`,
+// so a seeded RNG always produces the same key pair
+pub fn generate_key_pair_with_rng<R>(key_size: usize, rng: &mut R) -> KeyPair
+where
+    R: Rng + CryptoRng,
+{
+    loop {
+        let p = rng.gen_prime(key_size);
+        let q = rng.gen_prime(key_size);
+
+        if let Some(key_pair) = build_key_pair(vec![p, q], GenerationMethod::Seeded) {
+            return key_pair;
+        }
+    }
+}
+
+// Multi-prime key (RFC 8017) with a modulus of about `modulus_size` bits
+pub fn generate_multi_prime_key_pair(modulus_size: usize, prime_count: usize) -> KeyPair {
+    assert!(
+        (2..=MAX_PRIMES).contains(&prime_count),
+        "a key needs 2 to {} primes",
+        MAX_PRIMES
+    );
+
+    loop {
+        let primes = generate_multi_primes(modulus_size, prime_count);
+        if !has_modulus_size(&primes, modulus_size) {
+            continue;
+        }
+
+        if let Some(key_pair) = build_key_pair(primes, GenerationMethod::Student) {
             return key_pair;
         }
     }
 }
 
+// Returns None as soon as `cancel` is set
+pub fn generate_multi_prime_key_pair_with_rng<R>(
+    modulus_size: usize,
+    prime_count: usize,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<KeyPair>
+where
+    R: Rng + CryptoRng,
+{
+    assert!(
+        (2..=MAX_PRIMES).contains(&prime_count),
+        "a key needs 2 to {} primes",
+        MAX_PRIMES
+    );
+
+    loop {
+        let primes: Vec<BigUint> = prime_sizes(modulus_size, prime_count)
+            .into_iter()
+            .map(|size| prime_search::gen_prime(rng, size, cancel))
+            .collect::<Option<_>>()?;
+        if !has_modulus_size(&primes, modulus_size) {
+            continue;
+        }
+
+        if let Some(key_pair) = build_key_pair(primes, GenerationMethod::Seeded) {
+            return Some(key_pair);
+        }
+    }
+}
+
+// Searches the primes on every core, reporting the progress through `progress`.
+// Returns None as soon as `cancel` is set
+pub fn generate_key_pair_parallel(
+    modulus_size: usize,
+    prime_count: usize,
+    cancel: &AtomicBool,
+    progress: &Sender<SearchProgress>,
+) -> Option<KeyPair> {
+    assert!(
+        (2..=MAX_PRIMES).contains(&prime_count),
+        "a key needs 2 to {} primes",
+        MAX_PRIMES
+    );
+
+    let search = PrimeSearch::on_all_cores();
+    loop {
+        let primes =
+            search.find_primes(&prime_sizes(modulus_size, prime_count), cancel, progress)?;
+        if !has_modulus_size(&primes, modulus_size) {
+            continue;
+        }
+
+        if let Some(key_pair) = build_key_pair(primes, GenerationMethod::Student) {
+            return Some(key_pair);
+        }
+    }
+}
+
+fn build_key_pair(primes: Vec<BigUint>, method: GenerationMethod) -> Option<KeyPair> {
+    let mut distinct_primes = primes.clone();
+    distinct_primes.sort();
+    distinct_primes.dedup();
+    if distinct_primes.len() != primes.len() {
+        return None;
+    }
+
+    let exponent: BigUint = BigUint::from(EXPONENT);
+    let modulus: BigUint = primes.iter().product();
+    let phi: BigUint = primes.iter().map(|prime| prime - BigUint::one()).product();
+    let d: BigInt = (&exponent).mod_inverse(&phi)?;
+    let mut key_pair =
+        KeyPair::new_multi_prime("default".to_string(), primes, modulus, exponent, d)?;
+    key_pair.generation_method = method;
+
+    if test_key_pair(&key_pair) {
+        Some(key_pair)
+    } else {
+        None
+    }
+}
+
 fn test_key_pair(key_pair: &KeyPair) -> bool {
-    let test_messages = vec![
-        "0".to_string(),
-        ".".to_string(),
-        "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~ abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ"
-            .to_string(),
+    // Raw RSA round trip on a few integers, independent of how encrypt splits text into blocks
+    let d = match key_pair.d.to_biguint() {
+        Some(d) => d,
+        None => return false,
+    };
+    let test_messages = [
+        BigUint::from(0u32),
+        BigUint::from(46u32),
+        &key_pair.modulus - BigUint::one(),
     ];
     for test_message in test_messages.iter() {
-        let encrypted = encrypt(test_message, &key_pair.modulus, &key_pair.e);
-        let decrypted = decrypt(&encrypted, &key_pair.modulus, &key_pair.d);
+        let encrypted = test_message.modpow(&key_pair.e, &key_pair.modulus);
+        let decrypted = encrypted.modpow(&d, &key_pair.modulus);
+        let decrypted_crt = crt::private_key_operation(&encrypted, key_pair);
 
-        if decrypted.is_err() || &decrypted.unwrap() != test_message {
-            false;
+        if &decrypted != test_message {
+            return false;
+        }
+        if &decrypted_crt != test_message {
+            return false;
         }
     }
     true
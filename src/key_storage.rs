@@ -1,27 +1,55 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
 
-use num_bigint_dig::{BigInt, BigUint};
+use chrono::{TimeZone, Utc};
+use fs2::FileExt;
+use num_bigint_dig::{BigInt, BigUint, ModInverse};
+use num_integer::Integer;
+use num_traits::One;
 use serde::{Deserialize, Serialize};
 use serde_json;
 
-pub const KEY_STORAGE_PATH: &str = "./key_storage.json";
+use crate::config;
+use crate::fingerprint;
+use crate::storage_migration::{self, Migration, SCHEMA_VERSION};
+
+// Where versions without keyrings kept the storage, relative to the working directory
+pub const LEGACY_KEY_STORAGE_PATH: &str = "./key_storage.json";
+
+// Number of previous versions kept next to the storage file
+pub const BACKUP_COUNT: usize = 3;
 
 #[derive(Serialize, Deserialize)]
 pub struct Storage {
+    // Schema version of the JSON, older files are upgraded by `storage_migration`
+    version: u64,
     key_pairs: HashMap<usize, KeyPair>,
+    contacts: HashMap<usize, Contact>,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Storage {
     pub fn new() -> Self {
         let key_pair_map = HashMap::new();
         Self {
+            version: SCHEMA_VERSION,
             key_pairs: key_pair_map,
+            contacts: HashMap::new(),
         }
     }
 
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
     pub fn add_key_pair(&mut self, key_pair: KeyPair) {
         let last_id = self.key_pairs.len();
         self.key_pairs.insert(last_id, key_pair);
@@ -35,54 +63,385 @@ impl Storage {
         &self.key_pairs
     }
 
+    // Key pairs in id order, the order the key browser shows them in
+    pub fn key_pairs(&self) -> impl Iterator<Item = &KeyPair> {
+        (0..self.key_pairs.len()).filter_map(move |id| self.key_pairs.get(&id))
+    }
+
+    // Later ids move down by one, so the remaining key pairs keep their order
     pub fn delete_key_pair_by_id(&mut self, id: usize) -> Option<KeyPair> {
         let removed_key_pair = self.key_pairs.remove(&id);
 
         if removed_key_pair.is_some() {
-            let mut new_key_pair_map = HashMap::new();
-            let mut new_id = 0;
-            for (_, key_pair) in self.key_pairs.iter() {
-                new_key_pair_map.insert(new_id, key_pair.clone());
-                new_id += 1;
+            for later_id in id + 1..=self.key_pairs.len() {
+                let key_pair = self.key_pairs.remove(&later_id).unwrap();
+                self.key_pairs.insert(later_id - 1, key_pair);
             }
-            self.key_pairs = new_key_pair_map;
         }
 
         removed_key_pair
     }
 
-    pub fn load_storage() -> Self {
-        Self::load_storage_from_file(KEY_STORAGE_PATH)
+    // Puts a key pair back at `id`, moving later ids up by one. Undoes a delete
+    pub fn insert_key_pair(&mut self, id: usize, key_pair: KeyPair) {
+        let id = id.min(self.key_pairs.len());
+        for later_id in (id..self.key_pairs.len()).rev() {
+            let later = self.key_pairs.remove(&later_id).unwrap();
+            self.key_pairs.insert(later_id + 1, later);
+        }
+        self.key_pairs.insert(id, key_pair);
+    }
+
+    // The setters return false when there is no key pair with that id
+    pub fn rename_key_pair(&mut self, id: usize, name: String) -> bool {
+        match self.key_pairs.get_mut(&id) {
+            Some(key_pair) => {
+                key_pair.name = name;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_key_pair_comment(&mut self, id: usize, comment: String) -> bool {
+        match self.key_pairs.get_mut(&id) {
+            Some(key_pair) => {
+                key_pair.comment = comment;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_key_pair_tags(&mut self, id: usize, tags: Vec<String>) -> bool {
+        match self.key_pairs.get_mut(&id) {
+            Some(key_pair) => {
+                key_pair.tags = tags;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Adds a contact with only the public key of key pair `id` and returns its id,
+    // e.g. to hand the key to somebody else without the private parts
+    pub fn duplicate_as_contact(&mut self, id: usize, name: String) -> Option<usize> {
+        let key_pair = self.key_pairs.get(&id)?;
+        let contact = Contact::new(name, key_pair.modulus.clone(), key_pair.e.clone());
+        self.add_contact(contact);
+        Some(self.contacts.len() - 1)
+    }
+
+    // Ids of the key pairs whose `filter_field` (any field when None) contains `filter`,
+    // ignoring case, sorted by `sort_field`
+    pub fn find_key_pairs(
+        &self,
+        filter: &str,
+        filter_field: Option<KeyField>,
+        sort_field: KeyField,
+        descending: bool,
+    ) -> Vec<usize> {
+        let filter = filter.to_lowercase();
+        let fields: Vec<KeyField> = match filter_field {
+            Some(field) => vec![field],
+            None => KeyField::ALL.to_vec(),
+        };
+
+        let mut ids: Vec<usize> = (0..self.key_pairs.len())
+            .filter(|id| {
+                let key_pair = &self.key_pairs[id];
+                fields
+                    .iter()
+                    .any(|&field| key_pair.field_text(field).to_lowercase().contains(&filter))
+            })
+            .collect();
+
+        ids.sort_by(|a, b| {
+            let ordering = self.key_pairs[a].compare_by(&self.key_pairs[b], sort_field);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        ids
+    }
+
+    pub fn add_contact(&mut self, contact: Contact) {
+        let last_id = self.contacts.len();
+        self.contacts.insert(last_id, contact);
+    }
+
+    pub fn get_contact_by_id(&self, id: usize) -> Option<&Contact> {
+        self.contacts.get(&id)
+    }
+
+    pub fn get_contacts(&self) -> &HashMap<usize, Contact> {
+        &self.contacts
+    }
+
+    pub fn contacts(&self) -> impl Iterator<Item = &Contact> {
+        (0..self.contacts.len()).filter_map(move |id| self.contacts.get(&id))
+    }
+
+    pub fn delete_contact_by_id(&mut self, id: usize) -> Option<Contact> {
+        let removed_contact = self.contacts.remove(&id);
+
+        if removed_contact.is_some() {
+            for later_id in id + 1..=self.contacts.len() {
+                let contact = self.contacts.remove(&later_id).unwrap();
+                self.contacts.insert(later_id - 1, contact);
+            }
+        }
+
+        removed_contact
+    }
+
+    pub fn insert_contact(&mut self, id: usize, contact: Contact) {
+        let id = id.min(self.contacts.len());
+        for later_id in (id..self.contacts.len()).rev() {
+            let later = self.contacts.remove(&later_id).unwrap();
+            self.contacts.insert(later_id + 1, later);
+        }
+        self.contacts.insert(id, contact);
+    }
+
+    pub fn rename_contact(&mut self, id: usize, name: String) -> bool {
+        match self.contacts.get_mut(&id) {
+            Some(contact) => {
+                contact.name = name;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn load_storage() -> Result<Self, String> {
+        Self::load_storage_from_file(&config::storage_path()?)
     }
 
-    pub fn load_storage_from_file(path: &str) -> Self {
+    pub fn load_storage_from_file(path: &str) -> Result<Self, String> {
         if !Path::new(&path).exists() {
-            fs::File::create(&path).unwrap();
+            let _lock = StorageLock::exclusive(path).map_err(|error| lock_error(path, error))?;
+            if !Path::new(&path).exists() {
+                Self::new().write_to_path(path)?;
+            }
+        }
+
+        let lock = StorageLock::shared(path).map_err(|error| lock_error(path, error))?;
+        let (storage, migrations) = Self::read_from_path(path)?;
+        if migrations.is_empty() {
+            return Ok(storage);
+        }
+
+        // Upgrade the file once, the old version is kept as the newest backup
+        drop(lock);
+        Self::update_at_path(path, |_| {})
+    }
+
+    pub fn save_storage(&self) -> Result<(), String> {
+        self.save_storage_to_path(&config::storage_path()?)
+    }
+
+    pub fn save_storage_to_path(&self, path: &str) -> Result<(), String> {
+        let _lock = StorageLock::exclusive(path).map_err(|error| lock_error(path, error))?;
+        self.write_to_path(path)
+    }
+
+    // Loads the latest storage, applies `change` and saves it while holding the lock,
+    // so a change made by another running instance in between is never overwritten
+    pub fn update<F>(change: F) -> Result<Self, String>
+    where
+        F: FnOnce(&mut Storage),
+    {
+        Self::update_at_path(&config::storage_path()?, change)
+    }
+
+    pub fn update_at_path<F>(path: &str, change: F) -> Result<Self, String>
+    where
+        F: FnOnce(&mut Storage),
+    {
+        Self::try_update_at_path(path, |storage| {
+            change(storage);
+            Ok(())
+        })
+    }
+
+    // Like `update`, but `change` can refuse, e.g. when the entry it should change has
+    // moved in the latest storage. Nothing is written then
+    pub fn try_update<F>(change: F) -> Result<Self, String>
+    where
+        F: FnOnce(&mut Storage) -> Result<(), String>,
+    {
+        Self::try_update_at_path(&config::storage_path()?, change)
+    }
+
+    pub fn try_update_at_path<F>(path: &str, change: F) -> Result<Self, String>
+    where
+        F: FnOnce(&mut Storage) -> Result<(), String>,
+    {
+        let _lock = StorageLock::exclusive(path).map_err(|error| lock_error(path, error))?;
+        let mut storage = if Path::new(&path).exists() {
+            Self::read_from_path(path)?.0
+        } else {
+            Self::new()
+        };
+        change(&mut storage)?;
+        storage.write_to_path(path)?;
+        Ok(storage)
+    }
+
+    // Dry run of `migrate_file`: the migrations the file at `path` needs, after checking
+    // that the upgraded storage loads. Nothing is written
+    pub fn check_migration(path: &str) -> Result<Vec<&'static Migration>, String> {
+        if !Path::new(path).exists() {
+            return Err(format!("{} does not exist", path));
+        }
+        let _lock = StorageLock::shared(path).map_err(|error| lock_error(path, error))?;
+        let content = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        if content.is_empty() {
+            return Ok(Vec::new());
         }
+        Self::from_json(&content).map(|(_, migrations)| migrations)
+    }
 
-        if Path::new(&path).metadata().unwrap().len() == 0 {
-            Self::new().save_storage_to_path(path);
+    // Upgrades the file at `path` to SCHEMA_VERSION and returns the migrations that ran
+    pub fn migrate_file(path: &str) -> Result<Vec<&'static Migration>, String> {
+        if !Path::new(path).exists() {
+            return Err(format!("{} does not exist", path));
+        }
+        let _lock = StorageLock::exclusive(path).map_err(|error| lock_error(path, error))?;
+        let content = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        if content.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (storage, migrations) = Self::from_json(&content)?;
+        if !migrations.is_empty() {
+            storage.write_to_path(path)?;
         }
+        Ok(migrations)
+    }
 
-        let mut storage: Self = Self::new();
-        let file = fs::File::open(&path);
+    // Parses a storage of any schema version, upgrading it to SCHEMA_VERSION
+    pub fn from_json(json: &str) -> Result<(Self, Vec<&'static Migration>), String> {
+        let mut document: serde_json::Value =
+            serde_json::from_str(json).map_err(|error| error.to_string())?;
+        let migrations = storage_migration::migrate(&mut document)?;
+        let storage: Storage =
+            serde_json::from_value(document).map_err(|error| error.to_string())?;
+        Ok((storage, migrations))
+    }
 
-        if let Ok(file) = file {
-            let reader = std::io::BufReader::new(file);
-            let storage_from_file: Storage = serde_json::from_reader(reader).unwrap();
-            storage.key_pairs = storage_from_file.key_pairs;
+    // A file that cannot be read or parsed is an error, never an empty storage, so the
+    // next save does not overwrite the keys in it
+    fn read_from_path(path: &str) -> Result<(Self, Vec<&'static Migration>), String> {
+        let content = fs::read_to_string(path)
+            .map_err(|error| format!("could not read the key storage {}: {}", path, error))?;
+        // An empty file is left behind by versions that truncated it before writing
+        if content.is_empty() {
+            return Ok((Self::new(), Vec::new()));
         }
-        storage
+        Self::from_json(&content).map_err(|error| {
+            format!(
+                "could not load the key storage {}: {}. Run `student_rsa_tool storage migrate` \
+                 to upgrade it or restore one of the backups {}.bak.1 to {}.bak.{}",
+                path, error, path, path, BACKUP_COUNT
+            )
+        })
     }
 
-    pub fn save_storage(&self) {
-        self.save_storage_to_path(KEY_STORAGE_PATH)
+    // The new version goes to a temporary file in the same directory, is flushed to disk
+    // and then renamed over the old one, so a crash leaves either the old or the new
+    // storage but never a half written one. The previous version is kept as a backup
+    fn write_to_path(&self, path: &str) -> Result<(), String> {
+        let temp_path = format!("{}.tmp.{}", path, std::process::id());
+        let written = fs::File::create(&temp_path).and_then(|mut file| {
+            file.write_all(serde_json::to_string(self).unwrap().as_bytes())?;
+            file.sync_all()
+        });
+        if let Err(error) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(format!(
+                "could not save the key storage {}: {}",
+                path, error
+            ));
+        }
+
+        rotate_backups(path)
+            .and_then(|_| fs::rename(&temp_path, path))
+            .map_err(|error| {
+                let _ = fs::remove_file(&temp_path);
+                format!("could not save the key storage {}: {}", path, error)
+            })?;
+        sync_directory(path);
+        Ok(())
     }
+}
 
-    pub fn save_storage_to_path(&self, path: &str) {
-        let mut file = fs::File::create(&path).unwrap();
-        file.write_all(serde_json::to_string(self).unwrap().as_bytes())
-            .unwrap();
+// `path.bak.1` is the newest backup, `path.bak.<BACKUP_COUNT>` the oldest
+fn rotate_backups(path: &str) -> std::io::Result<()> {
+    if !Path::new(path).exists() {
+        return Ok(());
+    }
+
+    for number in (1..BACKUP_COUNT).rev() {
+        let older = format!("{}.bak.{}", path, number);
+        if Path::new(&older).exists() {
+            fs::rename(&older, format!("{}.bak.{}", path, number + 1))?;
+        }
+    }
+    fs::copy(path, format!("{}.bak.1", path))?;
+    Ok(())
+}
+
+// A rename is only durable once the directory entry itself reached the disk
+fn sync_directory(path: &str) {
+    let directory = match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if let Ok(directory) = fs::File::open(directory) {
+        // Not every platform can sync a directory, the rename already happened anyway
+        let _ = directory.sync_all();
+    }
+}
+
+// Advisory lock on `path.lock`, so the TUI and the CLI never write the storage at the
+// same time. The lock is released when this is dropped
+struct StorageLock {
+    file: fs::File,
+}
+
+impl StorageLock {
+    fn shared(path: &str) -> std::io::Result<Self> {
+        let lock = Self::open(path)?;
+        lock.file.lock_shared()?;
+        Ok(lock)
+    }
+
+    fn exclusive(path: &str) -> std::io::Result<Self> {
+        let lock = Self::open(path)?;
+        lock.file.lock_exclusive()?;
+        Ok(lock)
+    }
+
+    fn open(path: &str) -> std::io::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(format!("{}.lock", path))?;
+        Ok(StorageLock { file })
+    }
+}
+
+fn lock_error(path: &str, error: std::io::Error) -> String {
+    format!("could not lock the key storage {}: {}", path, error)
+}
+
+impl Drop for StorageLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
     }
 }
 
@@ -94,6 +453,132 @@ pub struct KeyPair {
     pub modulus: BigUint,
     pub e: BigUint,
     pub d: BigInt,
+    // Primes r_3..r_u of a multi-prime key (RFC 8017), empty for two-prime keys
+    pub other_primes: Vec<OtherPrimeInfo>,
+    pub generation_method: GenerationMethod,
+    // Unix timestamp in seconds, 0 for keys saved before it was recorded
+    pub created_at: i64,
+    pub comment: String,
+    pub tags: Vec<String>,
+}
+
+// Fields the key browser can filter and sort key pairs by
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KeyField {
+    Name,
+    Created,
+    BitLength,
+    Exponent,
+    Method,
+    Comment,
+    Tags,
+}
+
+impl KeyField {
+    pub const ALL: [KeyField; 7] = [
+        KeyField::Name,
+        KeyField::Created,
+        KeyField::BitLength,
+        KeyField::Exponent,
+        KeyField::Method,
+        KeyField::Comment,
+        KeyField::Tags,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyField::Name => "name",
+            KeyField::Created => "created",
+            KeyField::BitLength => "bit length",
+            KeyField::Exponent => "exponent",
+            KeyField::Method => "method",
+            KeyField::Comment => "comment",
+            KeyField::Tags => "tags",
+        }
+    }
+
+    // The field after this one, wrapping around
+    pub fn next(&self) -> KeyField {
+        let position = KeyField::ALL
+            .iter()
+            .position(|field| field == self)
+            .unwrap();
+        KeyField::ALL[(position + 1) % KeyField::ALL.len()]
+    }
+}
+
+// How the primes of a key were produced, keys saved before this was recorded are Unknown
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum GenerationMethod {
+    #[default]
+    Unknown,
+    Student,
+    WeakRng,
+    Seeded,
+    Fips186ProbablePrimes,
+    Fips186ProvablePrimes,
+    // Recovered from a public key, e.g. by the batch-GCD scan
+    Recovered,
+    // Read from a key file written by another program
+    Imported,
+}
+
+impl GenerationMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GenerationMethod::Unknown => "unknown",
+            GenerationMethod::Student => "student",
+            GenerationMethod::WeakRng => "weak RNG (demo)",
+            GenerationMethod::Seeded => "seeded (reproducible)",
+            GenerationMethod::Fips186ProbablePrimes => "FIPS 186-5 A.1.3 probable primes",
+            GenerationMethod::Fips186ProvablePrimes => "FIPS 186-5 A.1.2 provable primes",
+            GenerationMethod::Recovered => "recovered from a shared prime",
+            GenerationMethod::Imported => "imported",
+        }
+    }
+}
+
+// Public key of somebody else: enough to encrypt messages to them and to verify
+// their signatures, but not to decrypt or sign
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Contact {
+    pub name: String,
+    pub modulus: BigUint,
+    pub e: BigUint,
+    pub fingerprint: String,
+}
+
+impl Contact {
+    pub fn new(name: String, modulus: BigUint, e: BigUint) -> Self {
+        let fingerprint = fingerprint::canonical(&modulus, &e);
+        Contact {
+            name,
+            modulus,
+            e,
+            fingerprint,
+        }
+    }
+
+    pub fn openssh_fingerprint(&self) -> String {
+        fingerprint::openssh(&self.modulus, &self.e)
+    }
+}
+
+// Tags as typed by the user: separated by commas, surrounding spaces and empty tags dropped
+pub fn parse_tags(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+// RFC 8017 OtherPrimeInfo: prime r_i, CRT exponent d_i = d mod (r_i - 1) and
+// CRT coefficient t_i = (r_1 * r_2 * ... * r_(i-1))^-1 mod r_i
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OtherPrimeInfo {
+    pub prime: BigUint,
+    pub exponent: BigUint,
+    pub coefficient: BigUint,
 }
 
 impl KeyPair {
@@ -112,6 +597,159 @@ impl KeyPair {
             modulus,
             e,
             d,
+            other_primes: Vec::new(),
+            generation_method: GenerationMethod::Unknown,
+            created_at: Utc::now().timestamp(),
+            comment: String::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    // Builds a key from u >= 2 primes, computing the CRT values of r_3..r_u. None if a
+    // prime shares a factor with the product of the primes before it
+    pub fn new_multi_prime(
+        name: String,
+        primes: Vec<BigUint>,
+        modulus: BigUint,
+        e: BigUint,
+        d: BigInt,
+    ) -> Option<Self> {
+        let d_unsigned = d.to_biguint().unwrap();
+        let mut product: BigUint = &primes[0] * &primes[1];
+        let mut other_primes: Vec<OtherPrimeInfo> = Vec::new();
+
+        for prime in primes.iter().skip(2) {
+            other_primes.push(OtherPrimeInfo {
+                prime: prime.clone(),
+                exponent: &d_unsigned % (prime - BigUint::one()),
+                coefficient: mod_inverse(&product, prime)?,
+            });
+            product *= prime;
+        }
+
+        Some(KeyPair {
+            name,
+            p: primes[0].clone(),
+            q: primes[1].clone(),
+            modulus,
+            e,
+            d,
+            other_primes,
+            generation_method: GenerationMethod::Unknown,
+            created_at: Utc::now().timestamp(),
+            comment: String::new(),
+            tags: Vec::new(),
+        })
+    }
+
+    pub fn fingerprint(&self) -> String {
+        fingerprint::canonical(&self.modulus, &self.e)
+    }
+
+    pub fn openssh_fingerprint(&self) -> String {
+        fingerprint::openssh(&self.modulus, &self.e)
+    }
+
+    pub fn bit_length(&self) -> usize {
+        self.modulus.bits()
+    }
+
+    pub fn created_at_text(&self) -> String {
+        match Utc.timestamp_opt(self.created_at, 0).single() {
+            Some(created_at) if self.created_at != 0 => {
+                created_at.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+            }
+            _ => "unknown".to_string(),
+        }
+    }
+
+    // The field as it is shown and matched by the filter
+    pub fn field_text(&self, field: KeyField) -> String {
+        match field {
+            KeyField::Name => self.name.clone(),
+            KeyField::Created => self.created_at_text(),
+            KeyField::BitLength => self.bit_length().to_string(),
+            KeyField::Exponent => self.e.to_string(),
+            KeyField::Method => self.generation_method.name().to_string(),
+            KeyField::Comment => self.comment.clone(),
+            KeyField::Tags => self.tags.join(", "),
+        }
+    }
+
+    pub fn compare_by(&self, other: &KeyPair, field: KeyField) -> Ordering {
+        match field {
+            KeyField::Created => self.created_at.cmp(&other.created_at),
+            KeyField::BitLength => self.bit_length().cmp(&other.bit_length()),
+            KeyField::Exponent => self.e.cmp(&other.e),
+            _ => self
+                .field_text(field)
+                .to_lowercase()
+                .cmp(&other.field_text(field).to_lowercase()),
         }
     }
+
+    pub fn primes(&self) -> Vec<BigUint> {
+        let mut primes = vec![self.p.clone(), self.q.clone()];
+        primes.extend(self.other_primes.iter().map(|info| info.prime.clone()));
+        primes
+    }
+
+    // dP = d mod (p - 1)
+    pub fn dp(&self) -> BigUint {
+        self.d.to_biguint().unwrap() % (&self.p - BigUint::one())
+    }
+
+    // dQ = d mod (q - 1)
+    pub fn dq(&self) -> BigUint {
+        self.d.to_biguint().unwrap() % (&self.q - BigUint::one())
+    }
+
+    // qInv = q^-1 mod p, None if p and q are not coprime
+    pub fn qinv(&self) -> Option<BigUint> {
+        mod_inverse(&self.q, &self.p)
+    }
+}
+
+// Checks the numbers of an imported private key: primes greater than 1 that are distinct and
+// pairwise coprime, multiply to n, and e * d = 1 mod lambda(n), otherwise the key would not
+// decrypt. Every importer calls this before building the key pair
+pub fn check_private_key(
+    modulus: &BigUint,
+    e: &BigUint,
+    d: &BigUint,
+    primes: &[BigUint],
+) -> Result<(), String> {
+    // 0 or 1 would pass the product check with the modulus as the other prime
+    if primes.iter().any(|prime| prime <= &BigUint::one()) {
+        return Err("every prime has to be greater than 1".to_string());
+    }
+    // A repeated prime passes both checks below, n = p^2 has lambda = p - 1
+    for (i, prime) in primes.iter().enumerate() {
+        if primes[i + 1..]
+            .iter()
+            .any(|other| !prime.gcd(other).is_one())
+        {
+            return Err("the primes have to be distinct and pairwise coprime".to_string());
+        }
+    }
+
+    let product = primes
+        .iter()
+        .fold(BigUint::one(), |product, prime| product * prime);
+    if &product != modulus {
+        return Err("the primes do not multiply to the modulus".to_string());
+    }
+    let lambda = primes.iter().fold(BigUint::one(), |lambda, prime| {
+        lambda.lcm(&(prime - BigUint::one()))
+    });
+    if (e * d) % lambda != BigUint::one() {
+        return Err("the private exponent does not match the public exponent".to_string());
+    }
+    Ok(())
+}
+
+fn mod_inverse(value: &BigUint, modulus: &BigUint) -> Option<BigUint> {
+    value
+        .mod_inverse(modulus)
+        .and_then(|inverse| inverse.to_biguint())
 }
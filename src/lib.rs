@@ -0,0 +1,27 @@
+pub mod batch_gcd;
+pub mod blinding;
+pub mod config;
+pub mod crt;
+pub mod decrypt;
+pub mod der;
+pub mod encoding;
+pub mod encrypt;
+pub mod envelope;
+pub mod exponentiation;
+pub mod fingerprint;
+pub mod fips186;
+pub mod homomorphism;
+pub mod jwk;
+pub mod jwt;
+pub mod key_generator;
+pub mod key_storage;
+pub mod openpgp;
+pub mod openssh;
+pub mod pkcs1;
+pub mod prime_search;
+pub mod sign;
+pub mod storage_migration;
+pub mod timing_lab;
+pub mod utils;
+pub mod widgets;
+pub mod x509;
@@ -1,19 +1,398 @@
 use std::io;
+use std::path::PathBuf;
 
+use clap::{Args, Parser, Subcommand};
 use crossterm::cursor;
 use crossterm::style;
 use crossterm::terminal;
 
-mod decrypt;
-mod encrypt;
-mod key_generator;
-mod key_storage;
-mod utils;
-mod widgets;
+use num_bigint_dig::BigUint;
+use serde_json::Value;
+
+use student_rsa_tool::config::{self, Overrides};
+use student_rsa_tool::decrypt;
+use student_rsa_tool::encoding::{self, Encoding};
+use student_rsa_tool::encrypt;
+use student_rsa_tool::envelope;
+use student_rsa_tool::jwk;
+use student_rsa_tool::jwt::{self, Algorithm};
+use student_rsa_tool::key_storage::{KeyPair, Storage};
+use student_rsa_tool::openpgp::{self, PublicKey};
+use student_rsa_tool::storage_migration::SCHEMA_VERSION;
+use student_rsa_tool::widgets;
+use student_rsa_tool::x509::{self, Name, Validity};
+
+#[derive(Parser)]
+#[command(version, about = "RSA playground for students")]
+struct Cli {
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Directory holding the keyrings, overrides STUDENT_RSA_TOOL_DATA_DIR and the config file"
+    )]
+    data_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Keyring to open, overrides STUDENT_RSA_TOOL_KEYRING and the config file"
+    )]
+    keyring: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Config file to read instead of the one in the XDG config directory"
+    )]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Maintain the key storage files")]
+    Storage {
+        #[command(subcommand)]
+        command: StorageCommand,
+    },
+    #[command(about = "Create and verify JSON Web Tokens")]
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+    #[command(about = "Create, sign and show X.509 certificates and certificate requests")]
+    Cert {
+        #[command(subcommand)]
+        command: CertCommand,
+    },
+    #[command(about = "Exchange OpenPGP keys, messages and signatures with GnuPG")]
+    Pgp {
+        #[command(subcommand)]
+        command: PgpCommand,
+    },
+    #[command(about = "Encrypt text to key pairs or contacts of the keyring")]
+    Encrypt {
+        #[arg(
+            long,
+            value_name = "NAME",
+            required = true,
+            help = "Key pair or contact to encrypt to, repeat it for a message for several recipients"
+        )]
+        to: Vec<String>,
+        #[arg(
+            long,
+            default_value = "hex",
+            help = "hex, base64, base64url, base32, base58, decimal or armor"
+        )]
+        encoding: String,
+        #[arg(help = "File to encrypt, standard input when left out")]
+        input: Option<PathBuf>,
+    },
+    #[command(about = "Decrypt a ciphertext of the encrypt command or the TUI")]
+    Decrypt {
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Key pair to decrypt with, messages for several recipients find it themselves"
+        )]
+        key: Option<String>,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "auto, hex, base64, base64url, base32, base58, decimal or armor"
+        )]
+        encoding: String,
+        #[arg(help = "File with the ciphertext, standard input when left out")]
+        input: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum StorageCommand {
+    #[command(
+        about = "Upgrade the keyrings to the current schema version, or only the one given with --keyring"
+    )]
+    Migrate {
+        #[arg(
+            long,
+            help = "Only report the migrations that would run, exit with 1 if any is needed"
+        )]
+        check: bool,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Storage file to migrate instead of the keyrings"
+        )]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommand {
+    #[command(about = "Sign a JWT with a key pair of the keyring and print it")]
+    Sign {
+        #[arg(long, value_name = "NAME", help = "Name of the key pair that signs")]
+        key: String,
+        #[arg(long, default_value = "RS256", help = "RS256, RS384, RS512 or PS256")]
+        alg: String,
+        #[arg(long, default_value = "", help = "The \"sub\" claim")]
+        subject: String,
+        #[arg(
+            long,
+            default_value = "",
+            help = "The \"iss\" claim, left out when empty"
+        )]
+        issuer: String,
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 3600,
+            help = "Lifetime of the token, sets \"exp\""
+        )]
+        expires_in: i64,
+        #[arg(
+            long,
+            value_name = "JSON",
+            help = "JSON object with further claims, they replace the generated ones"
+        )]
+        claims: Option<String>,
+    },
+    #[command(
+        about = "Decode a JWT and check its signature, \"exp\" and \"nbf\", exit with 1 if it is not valid"
+    )]
+    Verify {
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Key pair or contact to verify with, by default the one matching the \"kid\" header"
+        )]
+        key: Option<String>,
+        token: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CertCommand {
+    #[command(about = "Create a self-signed certificate for a key pair of the keyring")]
+    SelfSigned {
+        #[command(flatten)]
+        profile: ProfileArgs,
+        #[arg(long, default_value_t = 365, help = "Days the certificate is valid")]
+        days: i64,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    #[command(about = "Create a PKCS#10 certificate signing request for a key pair")]
+    Request {
+        #[command(flatten)]
+        profile: ProfileArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    #[command(about = "Issue a certificate for a request, signed by a CA key of the keyring")]
+    Sign {
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Certificate signing request, PEM or DER"
+        )]
+        csr: PathBuf,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Certificate of the CA, PEM or DER, it must belong to --issuer-key"
+        )]
+        issuer_cert: PathBuf,
+        #[arg(long, value_name = "NAME", help = "Key pair of the CA")]
+        issuer_key: String,
+        #[arg(long, default_value_t = 365, help = "Days the certificate is valid")]
+        days: i64,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    #[command(about = "Show a certificate or a certificate request in readable form")]
+    Show {
+        #[arg(value_name = "FILE", help = "Certificate or request, PEM or DER")]
+        file: PathBuf,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Certificate of the issuer, to check the signature of a certificate"
+        )]
+        issuer: Option<PathBuf>,
+    },
+}
+
+// Key pairs keep their OpenPGP key ID through their creation time. Contacts have none, so
+// keys of other people come from their key block files
+#[derive(Subcommand)]
+enum PgpCommand {
+    #[command(about = "Export a key pair as an OpenPGP public or secret key block")]
+    Export {
+        #[arg(long, value_name = "NAME", help = "Key pair to export")]
+        key: String,
+        #[arg(long, help = "Export the unprotected secret key, for gpg --import")]
+        secret: bool,
+        #[arg(
+            long,
+            value_name = "TEXT",
+            help = "User ID like \"Alice <alice@example.org>\", by default the key pair name"
+        )]
+        user_id: Option<String>,
+        #[command(flatten)]
+        output: ArmorArgs,
+    },
+    #[command(about = "Show the keys, user IDs and subkeys of a key block")]
+    Show {
+        #[arg(
+            value_name = "FILE",
+            help = "Public or secret key block, armored or binary"
+        )]
+        file: PathBuf,
+    },
+    #[command(
+        about = "Add the secret keys of a key block, subkeys included, to the keyring as key pairs"
+    )]
+    Import {
+        #[arg(
+            value_name = "FILE",
+            help = "Secret key block without a passphrase, from gpg --export-secret-keys"
+        )]
+        file: PathBuf,
+    },
+    #[command(about = "Encrypt a file to a key pair or to the key of a key block")]
+    Encrypt {
+        #[arg(
+            long,
+            value_name = "NAME",
+            required_unless_present = "to_file",
+            conflicts_with = "to_file",
+            help = "Key pair of the recipient"
+        )]
+        to: Option<String>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Public key block of the recipient, its encryption subkey is used"
+        )]
+        to_file: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Key pair that signs the message too"
+        )]
+        sign: Option<String>,
+        #[arg(
+            value_name = "FILE",
+            help = "File to encrypt, standard input when left out"
+        )]
+        input: Option<PathBuf>,
+        #[command(flatten)]
+        output: ArmorArgs,
+    },
+    #[command(
+        about = "Decrypt a message with the matching key pair of the keyring and check its signatures"
+    )]
+    Decrypt {
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Public key block to check signatures with, besides the key pairs"
+        )]
+        key_file: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "File to write instead of standard output"
+        )]
+        out: Option<PathBuf>,
+        #[arg(value_name = "FILE", help = "Message, standard input when left out")]
+        input: Option<PathBuf>,
+    },
+    #[command(about = "Sign a file with a key pair, as a signed message or a detached signature")]
+    Sign {
+        #[arg(long, value_name = "NAME", help = "Key pair that signs")]
+        key: String,
+        #[arg(long, help = "Write a detached signature")]
+        detach: bool,
+        #[arg(
+            value_name = "FILE",
+            help = "File to sign, standard input when left out"
+        )]
+        input: Option<PathBuf>,
+        #[command(flatten)]
+        output: ArmorArgs,
+    },
+    #[command(
+        about = "Check a signed message or, with --signature, a detached signature, exit with 1 if one is not valid"
+    )]
+    Verify {
+        #[arg(long, value_name = "FILE", help = "Detached signature of the input")]
+        signature: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Public key block to check signatures with, besides the key pairs"
+        )]
+        key_file: Option<PathBuf>,
+        #[arg(
+            value_name = "FILE",
+            help = "Signed message or signed file, standard input when left out"
+        )]
+        input: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct ProfileArgs {
+    #[arg(long, value_name = "NAME", help = "Key pair of the subject")]
+    key: String,
+    #[arg(long, help = "Distinguished name like \"CN=Alice, O=School, C=DE\"")]
+    subject: String,
+    #[arg(
+        long,
+        default_value = "",
+        help = "Alternative names like \"DNS:example.org, IP:127.0.0.1, email:a@example.org\""
+    )]
+    san: String,
+    #[arg(long, help = "Mark the key as a certificate authority")]
+    ca: bool,
+    #[arg(long, help = "Number of CAs allowed below this one")]
+    path_length: Option<u32>,
+    #[arg(
+        long,
+        default_value = "",
+        help = "Key usage like \"digitalSignature, keyEncipherment\", the default depends on --ca"
+    )]
+    key_usage: String,
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    #[arg(long, help = "Write DER instead of PEM")]
+    der: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "File to write instead of standard output"
+    )]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ArmorArgs {
+    #[arg(long, help = "Write binary OpenPGP data instead of ASCII armor")]
+    binary: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "File to write instead of standard output"
+    )]
+    out: Option<PathBuf>,
+}
 
 fn run<W>(w: &mut W) -> crossterm::Result<()>
-    where
-        W: io::Write,
+where
+    W: io::Write,
 {
     crossterm::execute!(w, terminal::EnterAlternateScreen,)?;
 
@@ -30,7 +409,708 @@ fn run<W>(w: &mut W) -> crossterm::Result<()>
     terminal::disable_raw_mode()
 }
 
+// Exit code 0 when everything is up to date or was migrated, 1 when --check found files
+// that need a migration and 2 when a file could not be read or upgraded
+fn migrate(check: bool, file: Option<PathBuf>, keyring_given: bool) -> i32 {
+    let paths: Result<Vec<PathBuf>, String> = match file {
+        Some(file) => Ok(vec![file]),
+        None if keyring_given => config::keyring()
+            .and_then(|keyring| config::keyring_path(&keyring))
+            .map(|path| vec![path]),
+        None => config::keyrings().and_then(|keyrings| {
+            keyrings
+                .iter()
+                .map(|name| config::keyring_path(name))
+                .filter(|path| path.as_ref().map_or(true, |path| path.exists()))
+                .collect()
+        }),
+    };
+    let paths = match paths {
+        Ok(paths) => paths,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return 2;
+        }
+    };
+
+    let mut exit_code = 0;
+    for path in paths.iter() {
+        let path = path.to_string_lossy();
+        let result = if check {
+            Storage::check_migration(&path)
+        } else {
+            Storage::migrate_file(&path)
+        };
+
+        match result {
+            Ok(migrations) if migrations.is_empty() => {
+                println!("{}: schema version {}, up to date", path, SCHEMA_VERSION);
+            }
+            Ok(migrations) => {
+                println!(
+                    "{}: {} schema version {} to {}",
+                    path,
+                    if check { "would migrate" } else { "migrated" },
+                    migrations[0].from,
+                    SCHEMA_VERSION
+                );
+                for migration in migrations.iter() {
+                    println!(
+                        "  {} -> {}: {}",
+                        migration.from,
+                        migration.from + 1,
+                        migration.description
+                    );
+                }
+                if check {
+                    exit_code = exit_code.max(1);
+                }
+            }
+            Err(error) => {
+                eprintln!("{}: error: {}", path, error);
+                exit_code = 2;
+            }
+        }
+    }
+    exit_code
+}
+
+// Public key of the key pair or contact called `name`, or with the thumbprint `kid`
+fn find_public_key(
+    storage: &Storage,
+    name: Option<&str>,
+    kid: Option<&str>,
+) -> Option<(String, BigUint, BigUint)> {
+    let key_pairs = storage
+        .key_pairs()
+        .map(|key_pair| (key_pair.name.clone(), &key_pair.modulus, &key_pair.e));
+    let contacts = storage
+        .contacts()
+        .map(|contact| (contact.name.clone(), &contact.modulus, &contact.e));
+
+    key_pairs
+        .chain(contacts)
+        .find(|(key_name, modulus, e)| match name {
+            Some(name) => key_name == name,
+            None => kid == Some(jwk::thumbprint(modulus, e).as_str()),
+        })
+        .map(|(name, modulus, e)| (name, modulus.clone(), e.clone()))
+}
+
+fn sign_token(
+    key: &str,
+    alg: &str,
+    subject: &str,
+    issuer: &str,
+    expires_in: i64,
+    claims: Option<String>,
+) -> i32 {
+    let algorithm = match Algorithm::from_name(alg) {
+        Ok(algorithm) => algorithm,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return 2;
+        }
+    };
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return 2;
+        }
+    };
+    let key_pair = match storage.key_pairs().find(|key_pair| key_pair.name == key) {
+        Some(key_pair) => key_pair,
+        None => {
+            eprintln!("error: no key pair called \"{}\"", key);
+            return 2;
+        }
+    };
+
+    let mut token_claims = jwt::claims(subject, issuer, jwt::now(), expires_in);
+    if let Some(claims) = claims {
+        match serde_json::from_str(&claims) {
+            Ok(Value::Object(extra)) => token_claims.extend(extra),
+            _ => {
+                eprintln!("error: --claims must be a JSON object");
+                return 2;
+            }
+        }
+    }
+    match jwt::encode(&token_claims, algorithm, key_pair) {
+        Ok(token) => {
+            println!("{}", token);
+            0
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            2
+        }
+    }
+}
+
+// Exit code 0 when the signature and the times are valid, 1 when they are not and 2 when
+// the token cannot be decoded or no key was found
+fn verify_token(key: Option<String>, token: &str) -> i32 {
+    let token = match jwt::decode(token) {
+        Ok(token) => token,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return 2;
+        }
+    };
+    println!("Header:\n{}", token.header_json());
+    println!("Claims:\n{}", token.claims_json());
+
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return 2;
+        }
+    };
+    let (name, modulus, e) = match find_public_key(&storage, key.as_deref(), token.kid()) {
+        Some(found) => found,
+        None => {
+            eprintln!(
+                "error: {}",
+                match key {
+                    Some(key) => format!("no key pair or contact called \"{}\"", key),
+                    None => "no key matches the \"kid\" header, choose one with --key".to_string(),
+                }
+            );
+            return 2;
+        }
+    };
+
+    let mut exit_code = 0;
+    match token.verify_signature(&modulus, &e) {
+        Ok(()) => println!("Signature: valid ({})", name),
+        Err(error) => {
+            println!("Signature: NOT valid ({}): {}", name, error);
+            exit_code = 1;
+        }
+    }
+    match token.check_time(jwt::now(), jwt::LEEWAY) {
+        Ok(()) => println!("Time: valid"),
+        Err(error) => {
+            println!("Time: NOT valid: {}", error);
+            exit_code = 1;
+        }
+    }
+    exit_code
+}
+
+fn find_key_pair<'a>(storage: &'a Storage, name: &str) -> Result<&'a KeyPair, String> {
+    storage
+        .key_pairs()
+        .find(|key_pair| key_pair.name == name)
+        .ok_or_else(|| format!("no key pair called \"{}\"", name))
+}
+
+fn write_output(output: &OutputArgs, der: &[u8], pem: fn(&[u8]) -> String) -> Result<(), String> {
+    let bytes = if output.der {
+        der.to_vec()
+    } else {
+        pem(der).into_bytes()
+    };
+    match &output.out {
+        Some(path) => std::fs::write(path, bytes)
+            .map_err(|error| format!("cannot write {}: {}", path.display(), error)),
+        None => io::Write::write_all(&mut io::stdout(), &bytes).map_err(|error| error.to_string()),
+    }
+}
+
+fn read_file(path: &PathBuf) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|error| format!("cannot read {}: {}", path.display(), error))
+}
+
+// Exit code 0 on success and 2 on any error, `show` exits with 1 when a signature it checks
+// is not valid
+fn cert(command: CertCommand) -> i32 {
+    match run_cert(command) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            2
+        }
+    }
+}
+
+fn run_cert(command: CertCommand) -> Result<bool, String> {
+    let storage = Storage::load_storage()?;
+    match command {
+        CertCommand::SelfSigned {
+            profile,
+            days,
+            output,
+        } => {
+            let key_pair = find_key_pair(&storage, &profile.key)?;
+            let der = x509::self_signed_certificate(
+                &Name::parse(&profile.subject)?,
+                &x509::extensions_from_text(
+                    &profile.san,
+                    profile.ca,
+                    profile.path_length,
+                    &profile.key_usage,
+                )?,
+                Validity::days_from_now(days),
+                key_pair,
+            )?;
+            write_output(&output, &der, x509::certificate_to_pem)?;
+        }
+        CertCommand::Request { profile, output } => {
+            let key_pair = find_key_pair(&storage, &profile.key)?;
+            let der = x509::certificate_request(
+                &Name::parse(&profile.subject)?,
+                &x509::extensions_from_text(
+                    &profile.san,
+                    profile.ca,
+                    profile.path_length,
+                    &profile.key_usage,
+                )?,
+                key_pair,
+            )?;
+            write_output(&output, &der, x509::certificate_request_to_pem)?;
+        }
+        CertCommand::Sign {
+            csr,
+            issuer_cert,
+            issuer_key,
+            days,
+            output,
+        } => {
+            let request = x509::read_certificate_request(&read_file(&csr)?)?;
+            let issuer = x509::read_certificate(&read_file(&issuer_cert)?)?;
+            let der = x509::sign_request(
+                &request,
+                &issuer,
+                find_key_pair(&storage, &issuer_key)?,
+                Validity::days_from_now(days),
+            )?;
+            write_output(&output, &der, x509::certificate_to_pem)?;
+        }
+        CertCommand::Show { file, issuer } => {
+            let input = read_file(&file)?;
+            // A request has no serial number, so it never parses as a certificate
+            if let Ok(certificate) = x509::read_certificate(&input) {
+                for line in certificate.describe() {
+                    println!("{}", line);
+                }
+                if let Some(issuer) = issuer {
+                    let issuer = x509::read_certificate(&read_file(&issuer)?)?;
+                    return Ok(match certificate.verify_signed_by(&issuer) {
+                        Ok(()) => {
+                            println!("Signed by {}: valid", issuer.subject.text());
+                            true
+                        }
+                        Err(error) => {
+                            println!("Signed by {}: NOT valid, {}", issuer.subject.text(), error);
+                            false
+                        }
+                    });
+                }
+                if certificate.is_self_issued() {
+                    return Ok(certificate
+                        .verify_signature(&certificate.modulus, &certificate.e)
+                        .is_ok());
+                }
+            } else {
+                let request = x509::read_certificate_request(&input)
+                    .map_err(|error| format!("neither a certificate nor a request: {}", error))?;
+                for line in request.describe() {
+                    println!("{}", line);
+                }
+                return Ok(request.verify_signature().is_ok());
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn read_input(input: &Option<PathBuf>) -> Result<Vec<u8>, String> {
+    match input {
+        Some(path) => read_file(path),
+        None => {
+            let mut data = Vec::new();
+            io::Read::read_to_end(&mut io::stdin(), &mut data)
+                .map_err(|error| error.to_string())?;
+            Ok(data)
+        }
+    }
+}
+
+// The name a literal data packet records, empty for standard input
+fn literal_file_name(input: &Option<PathBuf>) -> String {
+    input
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn write_bytes(out: &Option<PathBuf>, bytes: &[u8]) -> Result<(), String> {
+    match out {
+        Some(path) => std::fs::write(path, bytes)
+            .map_err(|error| format!("cannot write {}: {}", path.display(), error)),
+        None => io::Write::write_all(&mut io::stdout(), bytes).map_err(|error| error.to_string()),
+    }
+}
+
+fn write_armored(output: &ArmorArgs, label: &str, data: &[u8]) -> Result<(), String> {
+    if output.binary {
+        write_bytes(&output.out, data)
+    } else {
+        write_bytes(&output.out, openpgp::armor(label, data).as_bytes())
+    }
+}
+
+// Every key pair of the keyring and the keys of `key_file`, with the name to show
+fn verification_keys(
+    storage: &Storage,
+    key_file: &Option<PathBuf>,
+) -> Result<Vec<(String, PublicKey)>, String> {
+    let mut keys: Vec<(String, PublicKey)> = storage
+        .key_pairs()
+        .map(|key_pair| (key_pair.name.clone(), PublicKey::from_key_pair(key_pair)))
+        .collect();
+    if let Some(key_file) = key_file {
+        for key in openpgp::read_keys(&read_file(key_file)?)? {
+            for subkey in key.keys() {
+                keys.push((key.user_id().to_string(), subkey.public.clone()));
+            }
+        }
+    }
+    Ok(keys)
+}
+
+// Prints one line per signature, returns whether all of them are valid
+fn check_signatures(
+    signatures: &[openpgp::Signature],
+    data: &[u8],
+    keys: &[(String, PublicKey)],
+    to_stderr: bool,
+) -> bool {
+    let mut all_valid = true;
+    for signature in signatures.iter() {
+        let issuer = signature
+            .issuer
+            .map(openpgp::key_id_text)
+            .unwrap_or("an unknown key".to_string());
+        let made = signature
+            .created
+            .map(|created| format!(", made {}", openpgp::time_text(created)))
+            .unwrap_or_default();
+        let key = keys
+            .iter()
+            .find(|(_, key)| Some(key.key_id()) == signature.issuer);
+
+        let line = match key {
+            Some((name, key)) => match signature.verify(data, key) {
+                Ok(()) => format!(
+                    "Signature by {} ({}), {}{}: valid",
+                    issuer,
+                    name,
+                    signature.algorithm_name(),
+                    made
+                ),
+                Err(error) => {
+                    all_valid = false;
+                    format!(
+                        "Signature by {} ({}){}: NOT valid, {}",
+                        issuer, name, made, error
+                    )
+                }
+            },
+            None => {
+                all_valid = false;
+                format!(
+                    "Signature by {}{}: no key to check it, use --key-file",
+                    issuer, made
+                )
+            }
+        };
+        if to_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+    all_valid
+}
+
+// Exit code 0 on success and 2 on any error
+fn encrypt_text(to: &[String], encoding: &str, input: &Option<PathBuf>) -> i32 {
+    let result = Encoding::from_name(encoding).and_then(|encoding| {
+        let storage = Storage::load_storage()?;
+        let mut recipients = Vec::new();
+        for name in to.iter() {
+            let (_, modulus, e) = find_public_key(&storage, Some(name), None)
+                .ok_or_else(|| format!("no key pair or contact called \"{}\"", name))?;
+            recipients.push((modulus, e));
+        }
+        let data = read_input(input)?;
+
+        let ciphertext = if let [(modulus, e)] = recipients.as_slice() {
+            let text = String::from_utf8(data)
+                .map_err(|_| "only UTF-8 text can be encrypted to a single recipient")?;
+            encrypt::encrypt(&text, modulus, e)?
+        } else {
+            let recipients: Vec<(&BigUint, &BigUint)> =
+                recipients.iter().map(|(modulus, e)| (modulus, e)).collect();
+            envelope::seal(&data, &recipients)?
+        };
+        Ok(encoding.encode(&ciphertext))
+    });
+    match result {
+        Ok(text) => {
+            println!("{}", text.trim_end());
+            0
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            2
+        }
+    }
+}
+
+fn decrypt_text(key: Option<String>, encoding: &str, input: &Option<PathBuf>) -> i32 {
+    let result = read_input(input).and_then(|data| {
+        let text = String::from_utf8(data).map_err(|_| "the ciphertext is not text")?;
+        let (ciphertext, encoding) = match encoding {
+            "auto" => encoding::decode_auto(&text)?,
+            name => {
+                let encoding = Encoding::from_name(name)?;
+                (encoding.decode(&text)?, encoding)
+            }
+        };
+
+        let storage = Storage::load_storage()?;
+        let key_pairs: Vec<&KeyPair> = match &key {
+            Some(key) => vec![find_key_pair(&storage, key)?],
+            None => storage.key_pairs().collect(),
+        };
+        if envelope::is_envelope(&ciphertext) {
+            let (message, key_pair) = envelope::parse(&ciphertext)?.open(&key_pairs)?;
+            eprintln!(
+                "Read {}, decrypted with \"{}\"",
+                encoding.name(),
+                key_pair.name
+            );
+            return Ok(message);
+        }
+
+        let key_pair = match key_pairs.as_slice() {
+            [key_pair] if key.is_some() => *key_pair,
+            _ => return Err("choose the key pair with --key".to_string()),
+        };
+        let message = decrypt::decrypt_crt(&ciphertext, key_pair)
+            .map_err(|error| format!("could not decrypt with \"{}\": {}", key_pair.name, error))?;
+        eprintln!("Read {}", encoding.name());
+        Ok(message.into_bytes())
+    });
+    match result.and_then(|message| write_bytes(&None, &message)) {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            2
+        }
+    }
+}
+
+// Exit code 0 on success, 1 when a signature is not valid and 2 on any error
+fn pgp(command: PgpCommand) -> i32 {
+    match run_pgp(command) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            2
+        }
+    }
+}
+
+fn run_pgp(command: PgpCommand) -> Result<bool, String> {
+    let storage = Storage::load_storage()?;
+    match command {
+        PgpCommand::Export {
+            key,
+            secret,
+            user_id,
+            output,
+        } => {
+            let key_pair = find_key_pair(&storage, &key)?;
+            let user_id = user_id.unwrap_or(key_pair.name.clone());
+            if secret {
+                let data = openpgp::export_secret_key(key_pair, &user_id)?;
+                write_armored(&output, openpgp::PRIVATE_KEY_BLOCK, &data)?;
+            } else {
+                let data = openpgp::export_public_key(key_pair, &user_id)?;
+                write_armored(&output, openpgp::PUBLIC_KEY_BLOCK, &data)?;
+            }
+        }
+        PgpCommand::Show { file } => {
+            for key in openpgp::read_keys(&read_file(&file)?)? {
+                for line in key.describe() {
+                    println!("{}", line);
+                }
+            }
+        }
+        PgpCommand::Import { file } => {
+            let mut key_pairs = Vec::new();
+            for key in openpgp::read_keys(&read_file(&file)?)? {
+                key_pairs.extend(key.to_key_pairs()?);
+            }
+            if key_pairs.is_empty() {
+                return Err(
+                    "the key block has no secret keys, use it with --to-file and --key-file"
+                        .to_string(),
+                );
+            }
+            Storage::update(|storage| {
+                for key_pair in key_pairs.iter() {
+                    storage.add_key_pair(key_pair.clone());
+                }
+            })?;
+            for key_pair in key_pairs.iter() {
+                println!(
+                    "Imported \"{}\", key ID {}",
+                    key_pair.name,
+                    openpgp::key_id_text(PublicKey::from_key_pair(key_pair).key_id())
+                );
+            }
+        }
+        PgpCommand::Encrypt {
+            to,
+            to_file,
+            sign,
+            input,
+            output,
+        } => {
+            let recipient = match (to, to_file) {
+                (Some(to), _) => PublicKey::from_key_pair(find_key_pair(&storage, &to)?),
+                (None, Some(to_file)) => openpgp::read_keys(&read_file(&to_file)?)?
+                    .iter()
+                    .find_map(|key| key.encryption_key())
+                    .map(|key| key.public.clone())
+                    .ok_or("the key block has no key for encryption")?,
+                (None, None) => unreachable!(),
+            };
+            let signer = match sign {
+                Some(sign) => Some(find_key_pair(&storage, &sign)?),
+                None => None,
+            };
+            let file_name = literal_file_name(&input);
+
+            let data = openpgp::encrypt(&read_input(&input)?, &file_name, &recipient, signer)?;
+            write_armored(&output, openpgp::MESSAGE, &data)?;
+        }
+        PgpCommand::Decrypt {
+            key_file,
+            out,
+            input,
+        } => {
+            let key_pairs: Vec<&KeyPair> = storage.key_pairs().collect();
+            let (message, key_pair) = openpgp::decrypt(&read_input(&input)?, &key_pairs)?;
+            eprintln!("Decrypted with \"{}\"", key_pair.name);
+
+            // The plain text goes to standard output, so the report goes to standard error
+            let keys = verification_keys(&storage, &key_file)?;
+            let valid = check_signatures(&message.signatures, &message.literal.data, &keys, true);
+            write_bytes(&out, &message.literal.data)?;
+            return Ok(valid);
+        }
+        PgpCommand::Sign {
+            key,
+            detach,
+            input,
+            output,
+        } => {
+            let key_pair = find_key_pair(&storage, &key)?;
+            let data = read_input(&input)?;
+            if detach {
+                let signature = openpgp::sign_detached(&data, key_pair)?;
+                write_armored(&output, openpgp::SIGNATURE, &signature)?;
+            } else {
+                let file_name = literal_file_name(&input);
+                let message = openpgp::sign_message(&data, &file_name, key_pair)?;
+                write_armored(&output, openpgp::MESSAGE, &message)?;
+            }
+        }
+        PgpCommand::Verify {
+            signature,
+            key_file,
+            input,
+        } => {
+            let keys = verification_keys(&storage, &key_file)?;
+            let input = read_input(&input)?;
+            let valid = match signature {
+                Some(signature) => {
+                    let signature = openpgp::read_signature(&read_file(&signature)?)?;
+                    check_signatures(&[signature], &input, &keys, false)
+                }
+                None => {
+                    let message = openpgp::read_message(&input)?;
+                    if message.signatures.is_empty() {
+                        return Err("the message is not signed".to_string());
+                    }
+                    check_signatures(&message.signatures, &message.literal.data, &keys, false)
+                }
+            };
+            return Ok(valid);
+        }
+    }
+    Ok(true)
+}
+
 fn main() -> crossterm::Result<()> {
+    let cli = Cli::parse();
+    let keyring_given = cli.keyring.is_some();
+    if let Err(error) = config::init(Overrides {
+        config: cli.config,
+        data_dir: cli.data_dir,
+        keyring: cli.keyring,
+    }) {
+        eprintln!("error: {}", error);
+        std::process::exit(2);
+    }
+
+    match cli.command {
+        Some(Command::Storage {
+            command: StorageCommand::Migrate { check, file },
+        }) => std::process::exit(migrate(check, file, keyring_given)),
+        Some(Command::Token {
+            command: TokenCommand::Verify { key, token },
+        }) => std::process::exit(verify_token(key, &token)),
+        Some(Command::Token {
+            command:
+                TokenCommand::Sign {
+                    key,
+                    alg,
+                    subject,
+                    issuer,
+                    expires_in,
+                    claims,
+                },
+        }) => std::process::exit(sign_token(
+            &key, &alg, &subject, &issuer, expires_in, claims,
+        )),
+        Some(Command::Cert { command }) => std::process::exit(cert(command)),
+        Some(Command::Pgp { command }) => std::process::exit(pgp(command)),
+        Some(Command::Encrypt {
+            to,
+            encoding,
+            input,
+        }) => std::process::exit(encrypt_text(&to, &encoding, &input)),
+        Some(Command::Decrypt {
+            key,
+            encoding,
+            input,
+        }) => std::process::exit(decrypt_text(key, &encoding, &input)),
+        None => {}
+    }
+
     let mut stdout = io::stdout();
     run(&mut stdout)
 }
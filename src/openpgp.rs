@@ -0,0 +1,1241 @@
+use std::io::Read;
+
+use aes::cipher::{AsyncStreamCipher, KeyIvInit};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{TimeZone, Utc};
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use num_bigint_dig::{BigInt, BigUint, ModInverse};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha1::{Digest, Sha1};
+
+use crate::crt;
+use crate::key_storage::{self, GenerationMethod, KeyPair};
+use crate::sign::{self, HashAlgorithm};
+
+// The subset of OpenPGP (RFC 4880, RFC 9580) GnuPG needs to exchange messages with us:
+// v4 RSA keys, PKESK v3 with SEIPD v1 (AES in CFB mode and a SHA-1 MDC) and v4 signatures
+
+// Packet tags (RFC 9580 5)
+const TAG_PUBLIC_KEY_ENCRYPTED_SESSION_KEY: u8 = 1;
+const TAG_SIGNATURE: u8 = 2;
+const TAG_ONE_PASS_SIGNATURE: u8 = 4;
+const TAG_SECRET_KEY: u8 = 5;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_SECRET_SUBKEY: u8 = 7;
+const TAG_COMPRESSED_DATA: u8 = 8;
+const TAG_SYMMETRICALLY_ENCRYPTED_DATA: u8 = 9;
+const TAG_MARKER: u8 = 10;
+const TAG_LITERAL_DATA: u8 = 11;
+const TAG_TRUST: u8 = 12;
+const TAG_USER_ID: u8 = 13;
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+const TAG_ENCRYPTED_DATA: u8 = 18;
+const TAG_MODIFICATION_DETECTION_CODE: u8 = 19;
+
+// Public key algorithms, 2 and 3 are the deprecated encrypt-only and sign-only RSA
+const RSA: u8 = 1;
+const RSA_ENCRYPT_ONLY: u8 = 2;
+const RSA_SIGN_ONLY: u8 = 3;
+
+const AES128: u8 = 7;
+const AES192: u8 = 8;
+const AES256: u8 = 9;
+
+const SHA256: u8 = 8;
+const SHA384: u8 = 9;
+const SHA512: u8 = 10;
+
+const UNCOMPRESSED: u8 = 0;
+const ZIP: u8 = 1;
+const ZLIB: u8 = 2;
+
+const BINARY_DOCUMENT: u8 = 0x00;
+const TEXT_DOCUMENT: u8 = 0x01;
+const POSITIVE_CERTIFICATION: u8 = 0x13;
+const SUBKEY_BINDING: u8 = 0x18;
+
+// Signature subpacket types
+const SUBPACKET_CREATION_TIME: u8 = 2;
+const SUBPACKET_PREFERRED_SYMMETRIC: u8 = 11;
+const SUBPACKET_ISSUER: u8 = 16;
+const SUBPACKET_PREFERRED_HASH: u8 = 21;
+const SUBPACKET_PREFERRED_COMPRESSION: u8 = 22;
+const SUBPACKET_KEY_FLAGS: u8 = 27;
+const SUBPACKET_FEATURES: u8 = 30;
+const SUBPACKET_ISSUER_FINGERPRINT: u8 = 33;
+
+// Certify, sign, encrypt communications and encrypt storage: our keys do everything
+const KEY_FLAGS_ALL: u8 = 0x0f;
+const KEY_FLAGS_ENCRYPT: u8 = 0x0c;
+// Feature flag for SEIPD v1
+const FEATURE_MDC: u8 = 0x01;
+
+const KEY_VERSION: u8 = 4;
+const SIGNATURE_VERSION: u8 = 4;
+const PKESK_VERSION: u8 = 3;
+const SEIPD_VERSION: u8 = 1;
+
+const BLOCK_SIZE: usize = 16;
+const SESSION_KEY_LENGTH: usize = 32;
+const MDC_LENGTH: usize = 22;
+// EME-PKCS1-v1_5 needs at least eight bytes of padding (RFC 8017 7.2.1)
+const MINIMUM_PADDING: usize = 8;
+// DigestInfo of SHA-256 and the PKCS#1 v1.5 padding fill 62 bytes
+const MINIMUM_MODULUS_LENGTH: usize = 62;
+
+pub const MESSAGE: &str = "PGP MESSAGE";
+pub const PUBLIC_KEY_BLOCK: &str = "PGP PUBLIC KEY BLOCK";
+pub const PRIVATE_KEY_BLOCK: &str = "PGP PRIVATE KEY BLOCK";
+pub const SIGNATURE: &str = "PGP SIGNATURE";
+const ARMOR_LINE_LENGTH: usize = 64;
+
+const CRC24_INIT: u32 = 0xb704ce;
+const CRC24_POLY: u32 = 0x1864cfb;
+
+pub struct Packet {
+    pub tag: u8,
+    pub body: Vec<u8>,
+}
+
+// Public part of a v4 RSA key. The creation time is hashed into the fingerprint, so a
+// stored key pair keeps its OpenPGP identity through `created_at`
+#[derive(Clone, PartialEq, Debug)]
+pub struct PublicKey {
+    pub created: u32,
+    pub modulus: BigUint,
+    pub e: BigUint,
+}
+
+#[derive(Clone)]
+pub struct SecretKey {
+    pub d: BigUint,
+    pub p: BigUint,
+    pub q: BigUint,
+}
+
+// A primary key or subkey of a key block, the flags come from its self-signature
+#[derive(Clone)]
+pub struct Key {
+    pub public: PublicKey,
+    pub secret: Option<SecretKey>,
+    pub flags: Option<u8>,
+}
+
+// A primary key with its user IDs and subkeys, only those with a valid self-signature
+pub struct TransferableKey {
+    pub primary: Key,
+    pub user_ids: Vec<String>,
+    pub subkeys: Vec<Key>,
+}
+
+pub struct Signature {
+    pub signature_type: u8,
+    pub hash: u8,
+    pub created: Option<u32>,
+    pub issuer: Option<u64>,
+    pub key_flags: Option<u8>,
+    // Version up to the end of the hashed subpackets, what the trailer hashes
+    hashed: Vec<u8>,
+    pub hash_prefix: [u8; 2],
+    pub value: BigUint,
+}
+
+pub struct Literal {
+    pub file_name: String,
+    pub date: u32,
+    pub data: Vec<u8>,
+}
+
+pub struct Message {
+    pub literal: Literal,
+    pub signatures: Vec<Signature>,
+}
+
+fn now() -> u32 {
+    Utc::now().timestamp().clamp(0, u32::MAX as i64) as u32
+}
+
+pub fn time_text(time: u32) -> String {
+    Utc.timestamp_opt(time as i64, 0)
+        .unwrap()
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string()
+}
+
+pub fn key_id_text(key_id: u64) -> String {
+    format!("{:016X}", key_id)
+}
+
+fn check_key_size(modulus: &BigUint) -> Result<(), String> {
+    if modulus_length(modulus) < MINIMUM_MODULUS_LENGTH {
+        return Err(format!(
+            "the key is too small for OpenPGP, it needs at least {} bits",
+            MINIMUM_MODULUS_LENGTH * 8
+        ));
+    }
+    Ok(())
+}
+
+fn modulus_length(modulus: &BigUint) -> usize {
+    modulus.bits().div_ceil(8)
+}
+
+// I2OSP: the value as exactly `length` big-endian bytes, None when it does not fit
+fn to_fixed_length(value: &BigUint, length: usize) -> Option<Vec<u8>> {
+    let bytes = value.to_bytes_be();
+    if bytes.len() > length {
+        return None;
+    }
+    let mut output = vec![0u8; length - bytes.len()];
+    output.extend(bytes);
+    Some(output)
+}
+
+// The decoders take the input by reference and advance it past what they read
+fn take<'a>(input: &mut &'a [u8], length: usize) -> Result<&'a [u8], String> {
+    if input.len() < length {
+        return Err("truncated OpenPGP data".to_string());
+    }
+    let (value, rest) = input.split_at(length);
+    *input = rest;
+    Ok(value)
+}
+
+fn read_u8(input: &mut &[u8]) -> Result<u8, String> {
+    Ok(take(input, 1)?[0])
+}
+
+fn read_u16(input: &mut &[u8]) -> Result<u16, String> {
+    let bytes = take(input, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(input: &mut &[u8]) -> Result<u32, String> {
+    let bytes = take(input, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u64(input: &mut &[u8]) -> Result<u64, String> {
+    Ok(u64::from_be_bytes(take(input, 8)?.try_into().unwrap()))
+}
+
+// MPI (RFC 9580 3.2): length in bits as two bytes, then the big-endian value
+fn encode_mpi(output: &mut Vec<u8>, value: &BigUint) {
+    output.extend_from_slice(&(value.bits() as u16).to_be_bytes());
+    if value.bits() > 0 {
+        output.extend(value.to_bytes_be());
+    }
+}
+
+fn read_mpi(input: &mut &[u8]) -> Result<BigUint, String> {
+    let bits = read_u16(input)? as usize;
+    Ok(BigUint::from_bytes_be(take(input, bits.div_ceil(8))?))
+}
+
+// New format header (RFC 9580 4.2.1) with a one, two or five byte length
+pub fn encode_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xc0 | tag];
+    let length = body.len();
+    if length < 192 {
+        packet.push(length as u8);
+    } else if length < 8384 {
+        packet.push((((length - 192) >> 8) + 192) as u8);
+        packet.push(((length - 192) & 0xff) as u8);
+    } else {
+        packet.push(0xff);
+        packet.extend_from_slice(&(length as u32).to_be_bytes());
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+// Returns the length and whether it is a partial body length
+fn read_new_format_length(input: &mut &[u8]) -> Result<(usize, bool), String> {
+    let first = read_u8(input)? as usize;
+    Ok(match first {
+        0..=191 => (first, false),
+        192..=223 => (((first - 192) << 8) + read_u8(input)? as usize + 192, false),
+        224..=254 => (1 << (first & 0x1f), true),
+        _ => (read_u32(input)? as usize, false),
+    })
+}
+
+// Reads new and old format packets, GnuPG still writes the old format for some of them
+pub fn parse_packets(mut input: &[u8]) -> Result<Vec<Packet>, String> {
+    let mut packets = Vec::new();
+    while !input.is_empty() {
+        let header = read_u8(&mut input)?;
+        if header & 0x80 == 0 {
+            return Err("not OpenPGP data, a packet header is invalid".to_string());
+        }
+
+        let (tag, body) = if header & 0x40 != 0 {
+            let mut body = Vec::new();
+            loop {
+                let (length, partial) = read_new_format_length(&mut input)?;
+                body.extend_from_slice(take(&mut input, length)?);
+                if !partial {
+                    break;
+                }
+            }
+            (header & 0x3f, body)
+        } else {
+            let length = match header & 0x03 {
+                0 => read_u8(&mut input)? as usize,
+                1 => read_u16(&mut input)? as usize,
+                2 => read_u32(&mut input)? as usize,
+                // Indeterminate length: the packet runs to the end of the data
+                _ => input.len(),
+            };
+            ((header >> 2) & 0x0f, take(&mut input, length)?.to_vec())
+        };
+        packets.push(Packet { tag, body });
+    }
+    Ok(packets)
+}
+
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xffffff
+}
+
+// ASCII armor (RFC 9580 6.2) with the CRC-24 checksum line GnuPG writes
+pub fn armor(label: &str, data: &[u8]) -> String {
+    let encoded = STANDARD.encode(data);
+    let mut text = format!("-----BEGIN {}-----\n\n", label);
+
+    for line in encoded.as_bytes().chunks(ARMOR_LINE_LENGTH) {
+        text.push_str(std::str::from_utf8(line).unwrap());
+        text.push('\n');
+    }
+    let crc = crc24(data).to_be_bytes();
+    text.push_str(&format!("={}\n", STANDARD.encode(&crc[1..])));
+    text.push_str(&format!("-----END {}-----\n", label));
+    text
+}
+
+// Armored input is decoded and its checksum checked, anything else is taken as binary
+pub fn dearmor(input: &[u8]) -> Result<Vec<u8>, String> {
+    let text = match std::str::from_utf8(input) {
+        Ok(text) if text.trim_start().starts_with("-----BEGIN PGP") => text,
+        _ => return Ok(input.to_vec()),
+    };
+    let (label, data) = read_armor(text)?;
+    if label == "PGP SIGNED MESSAGE" {
+        return Err("cleartext signed messages are not supported, use gpg --sign".to_string());
+    }
+    Ok(data)
+}
+
+// The label and the data of armored text with any label
+pub fn read_armor(text: &str) -> Result<(String, Vec<u8>), String> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty());
+
+    let label = lines
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or("invalid armor header line")?
+        .to_string();
+    if label == "PGP SIGNED MESSAGE" {
+        return Ok((label, Vec::new()));
+    }
+
+    // Armor headers like "Comment:" end with an empty line
+    let mut lines = lines.skip_while(|line| !line.is_empty()).skip(1);
+    let mut encoded = String::new();
+    let mut checksum = None;
+    for line in lines.by_ref() {
+        if line.starts_with("-----END ") {
+            break;
+        } else if let Some(crc) = line.strip_prefix('=') {
+            checksum = Some(crc.to_string());
+        } else {
+            encoded.push_str(line);
+        }
+    }
+
+    let data = STANDARD
+        .decode(encoded)
+        .map_err(|error| format!("invalid base64 in the armor: {}", error))?;
+    if let Some(checksum) = checksum {
+        let crc = crc24(&data).to_be_bytes();
+        if STANDARD.encode(&crc[1..]) != checksum {
+            return Err("the armor checksum does not match".to_string());
+        }
+    }
+    Ok((label, data))
+}
+
+impl PublicKey {
+    pub fn from_key_pair(key_pair: &KeyPair) -> Self {
+        PublicKey {
+            created: key_pair.created_at.clamp(0, u32::MAX as i64) as u32,
+            modulus: key_pair.modulus.clone(),
+            e: key_pair.e.clone(),
+        }
+    }
+
+    fn body(&self) -> Vec<u8> {
+        let mut body = vec![KEY_VERSION];
+        body.extend_from_slice(&self.created.to_be_bytes());
+        body.push(RSA);
+        encode_mpi(&mut body, &self.modulus);
+        encode_mpi(&mut body, &self.e);
+        body
+    }
+
+    // How keys enter fingerprints and signature hashes: 0x99 and a two byte length
+    fn hash_input(&self) -> Vec<u8> {
+        let body = self.body();
+        let mut input = vec![0x99];
+        input.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        input.extend(body);
+        input
+    }
+
+    // v4 fingerprint (RFC 9580 5.5.4.2): SHA-1 of the framed public key packet body
+    pub fn fingerprint(&self) -> Vec<u8> {
+        Sha1::digest(self.hash_input()).to_vec()
+    }
+
+    pub fn fingerprint_text(&self) -> String {
+        self.fingerprint()
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect()
+    }
+
+    // The low 64 bits of the fingerprint
+    pub fn key_id(&self) -> u64 {
+        let fingerprint = self.fingerprint();
+        u64::from_be_bytes(fingerprint[12..].try_into().unwrap())
+    }
+}
+
+fn parse_key(mut input: &[u8], secret: bool) -> Result<Key, String> {
+    let version = read_u8(&mut input)?;
+    if version != KEY_VERSION {
+        return Err(format!("version {} keys are not supported", version));
+    }
+    let created = read_u32(&mut input)?;
+    let algorithm = read_u8(&mut input)?;
+    if ![RSA, RSA_ENCRYPT_ONLY, RSA_SIGN_ONLY].contains(&algorithm) {
+        return Err(format!(
+            "public key algorithm {} is not supported, only RSA",
+            algorithm
+        ));
+    }
+    let public = PublicKey {
+        created,
+        modulus: read_mpi(&mut input)?,
+        e: read_mpi(&mut input)?,
+    };
+
+    let secret = if secret {
+        let start = input;
+        if read_u8(&mut input)? != 0 {
+            return Err(
+                "the secret key is protected with a passphrase, export it without one".to_string(),
+            );
+        }
+        let d = read_mpi(&mut input)?;
+        let p = read_mpi(&mut input)?;
+        let q = read_mpi(&mut input)?;
+        read_mpi(&mut input)?;
+
+        // Two byte sum of the MPI bytes
+        let mpis = &start[1..start.len() - input.len()];
+        let checksum = mpis
+            .iter()
+            .fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16));
+        if read_u16(&mut input)? != checksum {
+            return Err("the secret key checksum does not match".to_string());
+        }
+        Some(SecretKey { d, p, q })
+    } else {
+        None
+    };
+
+    Ok(Key {
+        public,
+        secret,
+        flags: None,
+    })
+}
+
+impl Key {
+    // Name and creation time come from the OpenPGP key, so the key ID stays the same
+    pub fn to_key_pair(&self, name: String) -> Result<KeyPair, String> {
+        let secret = self
+            .secret
+            .as_ref()
+            .ok_or("the key block has no secret key")?;
+        key_storage::check_private_key(
+            &self.public.modulus,
+            &self.public.e,
+            &secret.d,
+            &[secret.p.clone(), secret.q.clone()],
+        )?;
+
+        let mut key_pair = KeyPair::new(
+            name,
+            secret.p.clone(),
+            secret.q.clone(),
+            self.public.modulus.clone(),
+            self.public.e.clone(),
+            BigInt::from(secret.d.clone()),
+        );
+        key_pair.generation_method = GenerationMethod::Imported;
+        key_pair.created_at = self.public.created as i64;
+        Ok(key_pair)
+    }
+
+    fn can_encrypt(&self) -> bool {
+        self.flags
+            .is_none_or(|flags| flags & KEY_FLAGS_ENCRYPT != 0)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} RSA {} bits, created {}{}",
+            self.public.fingerprint_text(),
+            self.public.modulus.bits(),
+            time_text(self.public.created),
+            if self.secret.is_some() {
+                ", with secret key"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+impl TransferableKey {
+    pub fn user_id(&self) -> &str {
+        self.user_ids.first().map(String::as_str).unwrap_or("")
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        std::iter::once(&self.primary).chain(self.subkeys.iter())
+    }
+
+    pub fn key_by_id(&self, key_id: u64) -> Option<&Key> {
+        self.keys().find(|key| key.public.key_id() == key_id)
+    }
+
+    // The first subkey for encryption, else the primary key when its flags allow it
+    pub fn encryption_key(&self) -> Option<&Key> {
+        self.subkeys
+            .iter()
+            .find(|key| {
+                key.flags
+                    .is_some_and(|flags| flags & KEY_FLAGS_ENCRYPT != 0)
+            })
+            .or(Some(&self.primary).filter(|key| key.can_encrypt()))
+    }
+
+    // Key pairs for the secret keys, named after the first user ID
+    pub fn to_key_pairs(&self) -> Result<Vec<KeyPair>, String> {
+        let mut key_pairs = Vec::new();
+        for (index, key) in self.keys().enumerate() {
+            if key.secret.is_some() {
+                let name = match index {
+                    0 => self.user_id().to_string(),
+                    _ => format!("{} subkey {}", self.user_id(), index),
+                };
+                key_pairs.push(key.to_key_pair(name)?);
+            }
+        }
+        Ok(key_pairs)
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![format!("Key: {}", self.primary.describe())];
+        for user_id in self.user_ids.iter() {
+            lines.push(format!("User ID: {}", user_id));
+        }
+        for subkey in self.subkeys.iter() {
+            lines.push(format!("Subkey: {}", subkey.describe()));
+        }
+        lines
+    }
+}
+
+fn user_id_hash_input(user_id: &str) -> Vec<u8> {
+    let mut input = vec![0xb4];
+    input.extend_from_slice(&(user_id.len() as u32).to_be_bytes());
+    input.extend_from_slice(user_id.as_bytes());
+    input
+}
+
+enum Component {
+    None,
+    UserId(String),
+    Subkey(Box<Key>),
+}
+
+// Reads public and secret key blocks. User IDs and subkeys count only with a valid
+// self-signature, which also gives the key flags
+pub fn read_keys(input: &[u8]) -> Result<Vec<TransferableKey>, String> {
+    let packets = parse_packets(&dearmor(input)?)?;
+    let mut keys: Vec<TransferableKey> = Vec::new();
+    let mut component = Component::None;
+
+    for packet in packets {
+        match packet.tag {
+            TAG_PUBLIC_KEY | TAG_SECRET_KEY => {
+                keys.push(TransferableKey {
+                    primary: parse_key(&packet.body, packet.tag == TAG_SECRET_KEY)?,
+                    user_ids: Vec::new(),
+                    subkeys: Vec::new(),
+                });
+                component = Component::None;
+            }
+            TAG_USER_ID => {
+                component = Component::UserId(String::from_utf8_lossy(&packet.body).into_owned());
+            }
+            // Subkeys of other algorithms, like ECDH, are skipped
+            TAG_PUBLIC_SUBKEY | TAG_SECRET_SUBKEY => {
+                component = match parse_key(&packet.body, packet.tag == TAG_SECRET_SUBKEY) {
+                    Ok(key) => Component::Subkey(Box::new(key)),
+                    Err(_) => Component::None,
+                };
+            }
+            TAG_SIGNATURE => {
+                let key = keys.last_mut().ok_or("a signature comes before any key")?;
+                let signature = match parse_signature(&packet.body) {
+                    Ok(signature) => signature,
+                    Err(_) => continue,
+                };
+                let primary = &key.primary.public;
+                match &component {
+                    Component::UserId(user_id)
+                        if (0x10..=0x13).contains(&signature.signature_type) =>
+                    {
+                        let mut data = primary.hash_input();
+                        data.extend(user_id_hash_input(user_id));
+                        if signature.verify_hashed(data, primary).is_ok()
+                            && !key.user_ids.contains(user_id)
+                        {
+                            if key.user_ids.is_empty() {
+                                key.primary.flags = signature.key_flags;
+                            }
+                            key.user_ids.push(user_id.clone());
+                        }
+                    }
+                    Component::Subkey(subkey) if signature.signature_type == SUBKEY_BINDING => {
+                        let mut data = primary.hash_input();
+                        data.extend(subkey.public.hash_input());
+                        if signature.verify_hashed(data, primary).is_ok() {
+                            let mut subkey = (**subkey).clone();
+                            subkey.flags = signature.key_flags;
+                            key.subkeys.push(subkey);
+                            component = Component::None;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // Trust packets only come from keyrings and say nothing about the key
+            TAG_TRUST => {}
+            _ => component = Component::None,
+        }
+    }
+
+    if keys.is_empty() {
+        return Err("no OpenPGP key found".to_string());
+    }
+    Ok(keys)
+}
+
+fn subpacket(kind: u8, data: &[u8]) -> Vec<u8> {
+    let mut subpacket = vec![(data.len() + 1) as u8, kind];
+    subpacket.extend_from_slice(data);
+    subpacket
+}
+
+fn parse_signature(mut input: &[u8]) -> Result<Signature, String> {
+    let start = input;
+    let version = read_u8(&mut input)?;
+    if version != SIGNATURE_VERSION {
+        return Err(format!("version {} signatures are not supported", version));
+    }
+    let signature_type = read_u8(&mut input)?;
+    if ![RSA, RSA_SIGN_ONLY].contains(&read_u8(&mut input)?) {
+        return Err("only RSA signatures are supported".to_string());
+    }
+    let hash = read_u8(&mut input)?;
+    let hashed_length = read_u16(&mut input)? as usize;
+    let hashed_subpackets = take(&mut input, hashed_length)?;
+    let hashed = start[..start.len() - input.len()].to_vec();
+    let unhashed_length = read_u16(&mut input)? as usize;
+    let unhashed_subpackets = take(&mut input, unhashed_length)?;
+    let hash_prefix = take(&mut input, 2)?;
+    let value = read_mpi(&mut input)?;
+
+    let mut signature = Signature {
+        signature_type,
+        hash,
+        created: None,
+        issuer: None,
+        key_flags: None,
+        hashed,
+        hash_prefix: [hash_prefix[0], hash_prefix[1]],
+        value,
+    };
+    // The issuer may be in the unhashed area, everything else counts only when hashed
+    for (subpackets, is_hashed) in [(hashed_subpackets, true), (unhashed_subpackets, false)] {
+        let mut subpackets = subpackets;
+        while !subpackets.is_empty() {
+            let (length, _) = read_new_format_length(&mut subpackets)?;
+            let data = take(&mut subpackets, length)?;
+            let (kind, data) = data.split_first().ok_or("empty signature subpacket")?;
+            match (kind & 0x7f, is_hashed) {
+                (SUBPACKET_CREATION_TIME, true) if data.len() == 4 => {
+                    signature.created = Some(u32::from_be_bytes(data.try_into().unwrap()));
+                }
+                (SUBPACKET_KEY_FLAGS, true) if !data.is_empty() => {
+                    signature.key_flags = Some(data[0]);
+                }
+                (SUBPACKET_ISSUER, _) if data.len() == 8 => {
+                    signature.issuer = Some(u64::from_be_bytes(data.try_into().unwrap()));
+                }
+                (SUBPACKET_ISSUER_FINGERPRINT, _) if data.len() == 21 => {
+                    signature.issuer = Some(u64::from_be_bytes(data[13..].try_into().unwrap()));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(signature)
+}
+
+fn hash_algorithm(hash: u8) -> Result<HashAlgorithm, String> {
+    match hash {
+        SHA256 => Ok(HashAlgorithm::Sha256),
+        SHA384 => Ok(HashAlgorithm::Sha384),
+        SHA512 => Ok(HashAlgorithm::Sha512),
+        _ => Err(format!(
+            "hash algorithm {} is not supported, only SHA-256, SHA-384 and SHA-512",
+            hash
+        )),
+    }
+}
+
+// Canonical text (RFC 9580 5.2.1.2): every line ends with CR LF
+fn canonical_text(data: &[u8]) -> Vec<u8> {
+    let mut text = Vec::with_capacity(data.len());
+    for (index, byte) in data.iter().enumerate() {
+        if *byte == b'\n' && (index == 0 || data[index - 1] != b'\r') {
+            text.push(b'\r');
+        }
+        text.push(*byte);
+    }
+    text
+}
+
+impl Signature {
+    pub fn algorithm_name(&self) -> String {
+        match hash_algorithm(self.hash) {
+            Ok(HashAlgorithm::Sha256) => "RSA with SHA-256".to_string(),
+            Ok(HashAlgorithm::Sha384) => "RSA with SHA-384".to_string(),
+            Ok(HashAlgorithm::Sha512) => "RSA with SHA-512".to_string(),
+            Err(_) => format!("RSA with hash algorithm {}", self.hash),
+        }
+    }
+
+    // Checks the signature over `data` followed by the v4 trailer
+    fn verify_hashed(&self, mut data: Vec<u8>, key: &PublicKey) -> Result<(), String> {
+        let hash = hash_algorithm(self.hash)?;
+        data.extend_from_slice(&self.hashed);
+        data.extend_from_slice(&[SIGNATURE_VERSION, 0xff]);
+        data.extend_from_slice(&(self.hashed.len() as u32).to_be_bytes());
+
+        if hash.digest(&data)[..2] != self.hash_prefix {
+            return Err("the signed data was changed".to_string());
+        }
+        let valid = to_fixed_length(&self.value, modulus_length(&key.modulus))
+            .is_some_and(|value| sign::verify_pkcs1_v15(&data, &value, hash, &key.modulus, &key.e));
+        if !valid {
+            return Err("the signature is not valid for this key".to_string());
+        }
+        Ok(())
+    }
+
+    // Signatures of binary and text documents
+    pub fn verify(&self, data: &[u8], key: &PublicKey) -> Result<(), String> {
+        match self.signature_type {
+            BINARY_DOCUMENT => self.verify_hashed(data.to_vec(), key),
+            TEXT_DOCUMENT => self.verify_hashed(canonical_text(data), key),
+            other => Err(format!(
+                "signature type 0x{:02x} is not a document signature",
+                other
+            )),
+        }
+    }
+}
+
+// A v4 signature packet by `key_pair` with SHA-256 over `data` and the trailer
+fn create_signature(
+    mut data: Vec<u8>,
+    signature_type: u8,
+    created: u32,
+    extra_subpackets: &[u8],
+    key_pair: &KeyPair,
+) -> Result<Vec<u8>, String> {
+    let public = PublicKey::from_key_pair(key_pair);
+    let mut fingerprint = vec![KEY_VERSION];
+    fingerprint.extend(public.fingerprint());
+
+    let mut subpackets = subpacket(SUBPACKET_ISSUER_FINGERPRINT, &fingerprint);
+    subpackets.extend(subpacket(SUBPACKET_CREATION_TIME, &created.to_be_bytes()));
+    subpackets.extend_from_slice(extra_subpackets);
+
+    let mut hashed = vec![SIGNATURE_VERSION, signature_type, RSA, SHA256];
+    hashed.extend_from_slice(&(subpackets.len() as u16).to_be_bytes());
+    hashed.extend(subpackets);
+    data.extend_from_slice(&hashed);
+    data.extend_from_slice(&[SIGNATURE_VERSION, 0xff]);
+    data.extend_from_slice(&(hashed.len() as u32).to_be_bytes());
+
+    let unhashed = subpacket(SUBPACKET_ISSUER, &public.key_id().to_be_bytes());
+    let mut body = hashed;
+    body.extend_from_slice(&(unhashed.len() as u16).to_be_bytes());
+    body.extend(unhashed);
+    body.extend_from_slice(&HashAlgorithm::Sha256.digest(&data)[..2]);
+    let value = sign::sign_pkcs1_v15(&data, HashAlgorithm::Sha256, key_pair)?;
+    encode_mpi(&mut body, &BigUint::from_bytes_be(&value));
+    Ok(encode_packet(TAG_SIGNATURE, &body))
+}
+
+// Positive certification of the user ID with our preferences. It is dated to the key's
+// creation, so exporting the same key twice gives the same bytes
+fn certification(key_pair: &KeyPair, user_id: &str) -> Result<Vec<u8>, String> {
+    let public = PublicKey::from_key_pair(key_pair);
+    let mut data = public.hash_input();
+    data.extend(user_id_hash_input(user_id));
+
+    let mut subpackets = subpacket(SUBPACKET_KEY_FLAGS, &[KEY_FLAGS_ALL]);
+    subpackets.extend(subpacket(
+        SUBPACKET_PREFERRED_SYMMETRIC,
+        &[AES256, AES192, AES128],
+    ));
+    subpackets.extend(subpacket(
+        SUBPACKET_PREFERRED_HASH,
+        &[SHA512, SHA384, SHA256],
+    ));
+    subpackets.extend(subpacket(
+        SUBPACKET_PREFERRED_COMPRESSION,
+        &[ZLIB, ZIP, UNCOMPRESSED],
+    ));
+    subpackets.extend(subpacket(SUBPACKET_FEATURES, &[FEATURE_MDC]));
+
+    let mut packets = encode_packet(TAG_USER_ID, user_id.as_bytes());
+    packets.extend(create_signature(
+        data,
+        POSITIVE_CERTIFICATION,
+        public.created,
+        &subpackets,
+        key_pair,
+    )?);
+    Ok(packets)
+}
+
+// Public key packet, user ID and self-signature, what `gpg --import` expects
+pub fn export_public_key(key_pair: &KeyPair, user_id: &str) -> Result<Vec<u8>, String> {
+    check_key_size(&key_pair.modulus)?;
+    let mut packets = encode_packet(TAG_PUBLIC_KEY, &PublicKey::from_key_pair(key_pair).body());
+    packets.extend(certification(key_pair, user_id)?);
+    Ok(packets)
+}
+
+// The secret key packet is unprotected: d, p, q and u = p^-1 mod q with p < q
+pub fn export_secret_key(key_pair: &KeyPair, user_id: &str) -> Result<Vec<u8>, String> {
+    check_key_size(&key_pair.modulus)?;
+    if !key_pair.other_primes.is_empty() {
+        return Err("OpenPGP has no multi-prime keys".to_string());
+    }
+    let (p, q) = if key_pair.p < key_pair.q {
+        (&key_pair.p, &key_pair.q)
+    } else {
+        (&key_pair.q, &key_pair.p)
+    };
+    let u = p
+        .mod_inverse(q)
+        .and_then(|inverse| inverse.to_biguint())
+        .ok_or("the primes of the key are not coprime")?;
+
+    let mut mpis = Vec::new();
+    encode_mpi(&mut mpis, key_pair.d.to_biguint().as_ref().unwrap());
+    encode_mpi(&mut mpis, p);
+    encode_mpi(&mut mpis, q);
+    encode_mpi(&mut mpis, &u);
+    let checksum = mpis
+        .iter()
+        .fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16));
+
+    let mut body = PublicKey::from_key_pair(key_pair).body();
+    body.push(0);
+    body.extend(mpis);
+    body.extend_from_slice(&checksum.to_be_bytes());
+
+    let mut packets = encode_packet(TAG_SECRET_KEY, &body);
+    packets.extend(certification(key_pair, user_id)?);
+    Ok(packets)
+}
+
+fn literal_packet(data: &[u8], file_name: &str) -> Vec<u8> {
+    let name = &file_name.as_bytes()[..file_name.len().min(255)];
+    let mut body = vec![b'b', name.len() as u8];
+    body.extend_from_slice(name);
+    body.extend_from_slice(&now().to_be_bytes());
+    body.extend_from_slice(data);
+    encode_packet(TAG_LITERAL_DATA, &body)
+}
+
+// Detached signature of binary data
+pub fn sign_detached(data: &[u8], key_pair: &KeyPair) -> Result<Vec<u8>, String> {
+    check_key_size(&key_pair.modulus)?;
+    create_signature(data.to_vec(), BINARY_DOCUMENT, now(), &[], key_pair)
+}
+
+// One-pass signature, literal data and signature, like `gpg --sign --compress-level 0`
+pub fn sign_message(data: &[u8], file_name: &str, key_pair: &KeyPair) -> Result<Vec<u8>, String> {
+    let signature = sign_detached(data, key_pair)?;
+    let mut one_pass = vec![3, BINARY_DOCUMENT, SHA256, RSA];
+    one_pass.extend_from_slice(&PublicKey::from_key_pair(key_pair).key_id().to_be_bytes());
+    // 1: this is the last signature, the one next to the data
+    one_pass.push(1);
+
+    let mut packets = encode_packet(TAG_ONE_PASS_SIGNATURE, &one_pass);
+    packets.extend(literal_packet(data, file_name));
+    packets.extend(signature);
+    Ok(packets)
+}
+
+fn cfb_encrypt(algorithm: u8, key: &[u8], data: &mut [u8]) {
+    let iv = [0u8; BLOCK_SIZE];
+    match algorithm {
+        AES128 => cfb_mode::Encryptor::<aes::Aes128>::new_from_slices(key, &iv)
+            .unwrap()
+            .encrypt(data),
+        AES192 => cfb_mode::Encryptor::<aes::Aes192>::new_from_slices(key, &iv)
+            .unwrap()
+            .encrypt(data),
+        _ => cfb_mode::Encryptor::<aes::Aes256>::new_from_slices(key, &iv)
+            .unwrap()
+            .encrypt(data),
+    }
+}
+
+fn cfb_decrypt(algorithm: u8, key: &[u8], data: &mut [u8]) {
+    let iv = [0u8; BLOCK_SIZE];
+    match algorithm {
+        AES128 => cfb_mode::Decryptor::<aes::Aes128>::new_from_slices(key, &iv)
+            .unwrap()
+            .decrypt(data),
+        AES192 => cfb_mode::Decryptor::<aes::Aes192>::new_from_slices(key, &iv)
+            .unwrap()
+            .decrypt(data),
+        _ => cfb_mode::Decryptor::<aes::Aes256>::new_from_slices(key, &iv)
+            .unwrap()
+            .decrypt(data),
+    }
+}
+
+fn key_length(algorithm: u8) -> Option<usize> {
+    match algorithm {
+        AES128 => Some(16),
+        AES192 => Some(24),
+        AES256 => Some(32),
+        _ => None,
+    }
+}
+
+fn session_key_checksum(key: &[u8]) -> u16 {
+    key.iter()
+        .fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16))
+}
+
+// PKESK v3: the session key in EME-PKCS1-v1_5 (RFC 8017 7.2.1) encrypted to the recipient
+fn session_key_packet(algorithm: u8, session_key: &[u8], recipient: &PublicKey) -> Vec<u8> {
+    let mut message = vec![algorithm];
+    message.extend_from_slice(session_key);
+    message.extend_from_slice(&session_key_checksum(session_key).to_be_bytes());
+
+    let length = modulus_length(&recipient.modulus);
+    let mut padding = vec![0u8; length - message.len() - 3];
+    for byte in padding.iter_mut() {
+        while *byte == 0 {
+            *byte = (OsRng.next_u32() & 0xff) as u8;
+        }
+    }
+    let mut encoded = vec![0x00, 0x02];
+    encoded.extend(padding);
+    encoded.push(0x00);
+    encoded.extend(message);
+
+    let ciphertext = BigUint::from_bytes_be(&encoded).modpow(&recipient.e, &recipient.modulus);
+    let mut body = vec![PKESK_VERSION];
+    body.extend_from_slice(&recipient.key_id().to_be_bytes());
+    body.push(RSA);
+    encode_mpi(&mut body, &ciphertext);
+    encode_packet(TAG_PUBLIC_KEY_ENCRYPTED_SESSION_KEY, &body)
+}
+
+// PKESK and SEIPD v1 with AES-256, optionally signed by `signer` inside the encryption
+pub fn encrypt(
+    data: &[u8],
+    file_name: &str,
+    recipient: &PublicKey,
+    signer: Option<&KeyPair>,
+) -> Result<Vec<u8>, String> {
+    // Algorithm byte, key and checksum need to fit with the minimum padding
+    if modulus_length(&recipient.modulus) < SESSION_KEY_LENGTH + 3 + MINIMUM_PADDING + 3 {
+        return Err("the recipient's key is too small for OpenPGP".to_string());
+    }
+    let inner = match signer {
+        Some(signer) => sign_message(data, file_name, signer)?,
+        None => literal_packet(data, file_name),
+    };
+
+    let mut session_key = [0u8; SESSION_KEY_LENGTH];
+    OsRng.fill_bytes(&mut session_key);
+
+    // Random block with its last two bytes repeated, the data and the MDC packet over all
+    let mut plaintext = vec![0u8; BLOCK_SIZE];
+    OsRng.fill_bytes(&mut plaintext);
+    plaintext.extend_from_within(BLOCK_SIZE - 2..);
+    plaintext.extend(inner);
+    plaintext.extend_from_slice(&[0xc0 | TAG_MODIFICATION_DETECTION_CODE, 20]);
+    let mdc = Sha1::digest(&plaintext);
+    plaintext.extend_from_slice(&mdc);
+    cfb_encrypt(AES256, &session_key, &mut plaintext);
+
+    let mut body = vec![SEIPD_VERSION];
+    body.extend(plaintext);
+    let mut packets = session_key_packet(AES256, &session_key, recipient);
+    packets.extend(encode_packet(TAG_ENCRYPTED_DATA, &body));
+    Ok(packets)
+}
+
+// Returns the symmetric algorithm and the session key
+fn decrypt_session_key(mut input: &[u8], key_pair: &KeyPair) -> Result<(u8, Vec<u8>), String> {
+    let ciphertext = read_mpi(&mut input)?;
+    if ciphertext >= key_pair.modulus {
+        return Err("the encrypted session key is out of range".to_string());
+    }
+    let length = modulus_length(&key_pair.modulus);
+    let encoded =
+        to_fixed_length(&crt::private_key_operation(&ciphertext, key_pair), length).unwrap();
+
+    let separator = encoded
+        .iter()
+        .skip(2)
+        .position(|byte| *byte == 0)
+        .map(|position| position + 2);
+    let message = match separator {
+        Some(separator) if encoded[..2] == [0x00, 0x02] && separator >= 2 + MINIMUM_PADDING => {
+            &encoded[separator + 1..]
+        }
+        _ => return Err("the session key does not decrypt with this key".to_string()),
+    };
+
+    let (algorithm, rest) = message.split_first().ok_or("the session key is empty")?;
+    let length = key_length(*algorithm).ok_or(format!(
+        "symmetric algorithm {} is not supported, only AES",
+        algorithm
+    ))?;
+    if rest.len() != length + 2
+        || session_key_checksum(&rest[..length]).to_be_bytes() != rest[length..]
+    {
+        return Err("the session key does not decrypt with this key".to_string());
+    }
+    Ok((*algorithm, rest[..length].to_vec()))
+}
+
+fn decompress(mut input: &[u8]) -> Result<Vec<u8>, String> {
+    let algorithm = read_u8(&mut input)?;
+    let mut output = Vec::new();
+    let result = match algorithm {
+        UNCOMPRESSED => {
+            output.extend_from_slice(input);
+            Ok(0)
+        }
+        ZIP => DeflateDecoder::new(input).read_to_end(&mut output),
+        ZLIB => ZlibDecoder::new(input).read_to_end(&mut output),
+        _ => {
+            return Err(format!(
+                "compression algorithm {} is not supported, only ZIP and ZLIB",
+                algorithm
+            ))
+        }
+    };
+    result.map_err(|error| format!("cannot decompress the message: {}", error))?;
+    Ok(output)
+}
+
+// Literal data with the signatures around it, compressed packets are opened
+fn read_packets(packets: Vec<Packet>, message: &mut Option<Message>) -> Result<(), String> {
+    for packet in packets {
+        match packet.tag {
+            TAG_COMPRESSED_DATA => {
+                read_packets(parse_packets(&decompress(&packet.body)?)?, message)?;
+            }
+            TAG_LITERAL_DATA => {
+                let mut input = packet.body.as_slice();
+                read_u8(&mut input)?;
+                let name_length = read_u8(&mut input)? as usize;
+                let file_name =
+                    String::from_utf8_lossy(take(&mut input, name_length)?).into_owned();
+                let date = read_u32(&mut input)?;
+                if message.is_some() {
+                    return Err("the message has more than one literal data packet".to_string());
+                }
+                *message = Some(Message {
+                    literal: Literal {
+                        file_name,
+                        date,
+                        data: input.to_vec(),
+                    },
+                    signatures: Vec::new(),
+                });
+            }
+            TAG_SIGNATURE => {
+                let signature = parse_signature(&packet.body)?;
+                if let Some(message) = message {
+                    message.signatures.push(signature);
+                }
+            }
+            TAG_ONE_PASS_SIGNATURE | TAG_MARKER => {}
+            TAG_PUBLIC_KEY_ENCRYPTED_SESSION_KEY | TAG_ENCRYPTED_DATA => {
+                return Err("the message is encrypted, decrypt it first".to_string());
+            }
+            other => return Err(format!("unexpected packet {} in a message", other)),
+        }
+    }
+    Ok(())
+}
+
+fn unpack(packets: Vec<Packet>) -> Result<Message, String> {
+    let mut message = None;
+    read_packets(packets, &mut message)?;
+    message.ok_or("the message has no literal data".to_string())
+}
+
+// A signed or plain message that is not encrypted
+pub fn read_message(input: &[u8]) -> Result<Message, String> {
+    unpack(parse_packets(&dearmor(input)?)?)
+}
+
+// The first signature packet of a detached signature
+pub fn read_signature(input: &[u8]) -> Result<Signature, String> {
+    parse_packets(&dearmor(input)?)?
+        .into_iter()
+        .find(|packet| packet.tag == TAG_SIGNATURE)
+        .ok_or("no signature packet found".to_string())
+        .and_then(|packet| parse_signature(&packet.body))
+}
+
+// Uses the key pair the message is encrypted for, the key IDs are those of `created_at`.
+// A key ID of zero hides the recipient, then every key pair is tried
+pub fn decrypt<'a>(
+    input: &[u8],
+    key_pairs: &[&'a KeyPair],
+) -> Result<(Message, &'a KeyPair), String> {
+    let packets = parse_packets(&dearmor(input)?)?;
+    let mut recipients = Vec::new();
+    let mut session_key = None;
+    let mut last_error = None;
+
+    for packet in packets.iter() {
+        if packet.tag != TAG_PUBLIC_KEY_ENCRYPTED_SESSION_KEY || session_key.is_some() {
+            continue;
+        }
+        let mut input = packet.body.as_slice();
+        if read_u8(&mut input)? != PKESK_VERSION {
+            continue;
+        }
+        let key_id = read_u64(&mut input)?;
+        if ![RSA, RSA_ENCRYPT_ONLY].contains(&read_u8(&mut input)?) {
+            continue;
+        }
+        recipients.push(key_id);
+
+        for key_pair in key_pairs.iter() {
+            let ours = PublicKey::from_key_pair(key_pair).key_id();
+            if key_id != ours && key_id != 0 {
+                continue;
+            }
+            match decrypt_session_key(input, key_pair) {
+                Ok(key) => {
+                    session_key = Some((key, *key_pair));
+                    break;
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+    }
+
+    let ((algorithm, key), key_pair) = match session_key {
+        Some(found) => found,
+        None => {
+            return Err(last_error.unwrap_or(if recipients.is_empty() {
+                "the message is not encrypted to an RSA key".to_string()
+            } else {
+                format!(
+                    "the message is encrypted for {}, no key pair matches",
+                    recipients
+                        .iter()
+                        .map(|key_id| key_id_text(*key_id))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }))
+        }
+    };
+
+    let body = match packets.iter().find(|packet| {
+        packet.tag == TAG_ENCRYPTED_DATA || packet.tag == TAG_SYMMETRICALLY_ENCRYPTED_DATA
+    }) {
+        Some(packet) if packet.tag == TAG_ENCRYPTED_DATA => &packet.body,
+        Some(_) => return Err("the message has no integrity protection, it is refused".to_string()),
+        None => return Err("the message has no encrypted data".to_string()),
+    };
+    if body.first() != Some(&SEIPD_VERSION) {
+        return Err("only version 1 encrypted data packets are supported".to_string());
+    }
+
+    let mut plaintext = body[1..].to_vec();
+    if plaintext.len() < BLOCK_SIZE + 2 + MDC_LENGTH {
+        return Err("the encrypted data is too short".to_string());
+    }
+    cfb_decrypt(algorithm, &key, &mut plaintext);
+    if plaintext[BLOCK_SIZE - 2..BLOCK_SIZE] != plaintext[BLOCK_SIZE..BLOCK_SIZE + 2] {
+        return Err("the session key does not decrypt the data".to_string());
+    }
+    let (protected, mdc) = plaintext.split_at(plaintext.len() - 20);
+    if protected[protected.len() - 2..] != [0xc0 | TAG_MODIFICATION_DETECTION_CODE, 20]
+        || Sha1::digest(protected).as_slice() != mdc
+    {
+        return Err("the modification detection code does not match".to_string());
+    }
+
+    let inner = &protected[BLOCK_SIZE + 2..protected.len() - 2];
+    Ok((unpack(parse_packets(inner)?)?, key_pair))
+}
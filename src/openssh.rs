@@ -0,0 +1,305 @@
+use aes::cipher::{KeyIvInit, StreamCipher};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use num_bigint_dig::{BigInt, BigUint};
+use rand::RngCore;
+
+use crate::key_storage::{self, GenerationMethod, KeyPair};
+use crate::pkcs1;
+
+// Key type name of RSA keys in the SSH wire format (RFC 4253 6.6)
+pub const KEY_TYPE: &str = "ssh-rsa";
+
+// PROTOCOL.key of OpenSSH: "openssh-key-v1" and a zero byte start the decoded PEM
+const AUTH_MAGIC: &[u8] = b"openssh-key-v1\0";
+const PEM_LABEL: &str = "OPENSSH PRIVATE KEY";
+const PEM_LINE_LENGTH: usize = 70;
+
+// What ssh-keygen writes for a key with a passphrase: AES-256 in CTR mode with key and
+// IV from bcrypt_pbkdf over a random 16 byte salt
+const CIPHER: &str = "aes256-ctr";
+const KDF: &str = "bcrypt";
+const KDF_ROUNDS: u32 = 16;
+const SALT_LENGTH: usize = 16;
+const KEY_LENGTH: usize = 32;
+const IV_LENGTH: usize = 16;
+const CIPHER_BLOCK_SIZE: usize = 16;
+// Without a cipher the private section is padded to 8 bytes
+const UNENCRYPTED_BLOCK_SIZE: usize = 8;
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+// RFC 4251 5: uint32 length followed by the bytes
+pub fn encode_string(output: &mut Vec<u8>, value: &[u8]) {
+    output.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    output.extend_from_slice(value);
+}
+
+// RFC 4251 5: two's complement big-endian string, so a leading zero byte is added when
+// the top bit is set. Zero is the empty string
+pub fn encode_mpint(output: &mut Vec<u8>, value: &BigUint) {
+    let mut bytes = value.to_bytes_be();
+    if bytes == [0] {
+        bytes.clear();
+    } else if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    encode_string(output, &bytes);
+}
+
+// The decoders take the input by reference and advance it past what they read
+pub fn decode_u32(input: &mut &[u8]) -> Result<u32, String> {
+    if input.len() < 4 {
+        return Err("truncated SSH data".to_string());
+    }
+    let (value, rest) = input.split_at(4);
+    *input = rest;
+    Ok(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+}
+
+pub fn decode_string<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], String> {
+    let length = decode_u32(input)? as usize;
+    if input.len() < length {
+        return Err("truncated SSH data".to_string());
+    }
+    let (value, rest) = input.split_at(length);
+    *input = rest;
+    Ok(value)
+}
+
+// Only non-negative values, RSA keys have no others
+pub fn decode_mpint(input: &mut &[u8]) -> Result<BigUint, String> {
+    let bytes = decode_string(input)?;
+    if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+        return Err("negative mpint in an RSA key".to_string());
+    }
+    Ok(BigUint::from_bytes_be(bytes))
+}
+
+// string "ssh-rsa", mpint e, mpint n: the public key blob OpenSSH fingerprints and puts
+// base64 encoded in authorized_keys lines
+pub fn public_key_blob(modulus: &BigUint, e: &BigUint) -> Vec<u8> {
+    let mut blob = Vec::new();
+    encode_string(&mut blob, KEY_TYPE.as_bytes());
+    encode_mpint(&mut blob, e);
+    encode_mpint(&mut blob, modulus);
+    blob
+}
+
+// Returns the modulus and the public exponent
+pub fn public_key_from_blob(blob: &[u8]) -> Result<(BigUint, BigUint), String> {
+    let mut input = blob;
+    let key_type = decode_string(&mut input)?;
+    if key_type != KEY_TYPE.as_bytes() {
+        return Err(format!(
+            "unsupported key type {}",
+            String::from_utf8_lossy(key_type)
+        ));
+    }
+    let e = decode_mpint(&mut input)?;
+    let modulus = decode_mpint(&mut input)?;
+    Ok((modulus, e))
+}
+
+// "ssh-rsa AAAA... comment", one line of authorized_keys or a .pub file
+pub fn public_key_to_line(modulus: &BigUint, e: &BigUint, comment: &str) -> String {
+    let line = format!(
+        "{} {}",
+        KEY_TYPE,
+        STANDARD.encode(public_key_blob(modulus, e))
+    );
+    if comment.is_empty() {
+        line
+    } else {
+        format!("{} {}", line, comment)
+    }
+}
+
+// Returns the modulus, the public exponent and the comment of the first key line,
+// skipping blank lines and # comments
+pub fn public_key_from_line(text: &str) -> Result<(BigUint, BigUint, String), String> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or("no public key line found")?;
+
+    let mut fields = line.splitn(3, char::is_whitespace);
+    let key_type = fields.next().unwrap_or_default();
+    if key_type != KEY_TYPE {
+        return Err(format!("unsupported key type {}", key_type));
+    }
+    let blob = STANDARD
+        .decode(fields.next().ok_or("missing key data")?)
+        .map_err(|error| format!("invalid base64 in public key: {}", error))?;
+    let comment = fields.next().unwrap_or_default().trim().to_string();
+
+    let (modulus, e) = public_key_from_blob(&blob)?;
+    Ok((modulus, e, comment))
+}
+
+// openssh-key-v1 private key in PEM. With a passphrase the private section is encrypted
+// the same way ssh-keygen does it. OpenSSH only knows two-prime RSA keys
+pub fn private_key_to_pem(key_pair: &KeyPair, passphrase: Option<&str>) -> Result<String, String> {
+    if !key_pair.other_primes.is_empty() {
+        return Err("OpenSSH does not support multi-prime keys".to_string());
+    }
+
+    let mut rng = rand::thread_rng();
+    // Two equal random numbers let the reader check the passphrase was right
+    let check = rng.next_u32();
+
+    let mut private_section = Vec::new();
+    private_section.extend_from_slice(&check.to_be_bytes());
+    private_section.extend_from_slice(&check.to_be_bytes());
+    encode_string(&mut private_section, KEY_TYPE.as_bytes());
+    encode_mpint(&mut private_section, &key_pair.modulus);
+    encode_mpint(&mut private_section, &key_pair.e);
+    encode_mpint(&mut private_section, &key_pair.d.to_biguint().unwrap());
+    let qinv = key_pair
+        .qinv()
+        .ok_or("the primes of the key are not coprime")?;
+    encode_mpint(&mut private_section, &qinv);
+    encode_mpint(&mut private_section, &key_pair.p);
+    encode_mpint(&mut private_section, &key_pair.q);
+    encode_string(&mut private_section, key_pair.comment.as_bytes());
+
+    let block_size = if passphrase.is_some() {
+        CIPHER_BLOCK_SIZE
+    } else {
+        UNENCRYPTED_BLOCK_SIZE
+    };
+    // Padding is 1, 2, 3, ... up to the block size
+    let mut padding: u8 = 1;
+    while !private_section.len().is_multiple_of(block_size) {
+        private_section.push(padding);
+        padding += 1;
+    }
+
+    let mut kdf_options = Vec::new();
+    let (cipher, kdf) = match passphrase {
+        Some(passphrase) => {
+            let mut salt = [0u8; SALT_LENGTH];
+            rng.fill_bytes(&mut salt);
+            encode_string(&mut kdf_options, &salt);
+            kdf_options.extend_from_slice(&KDF_ROUNDS.to_be_bytes());
+
+            let mut cipher = key_stream(passphrase, &salt, KDF_ROUNDS)?;
+            cipher.apply_keystream(&mut private_section);
+            (CIPHER, KDF)
+        }
+        None => ("none", "none"),
+    };
+
+    let mut output = AUTH_MAGIC.to_vec();
+    encode_string(&mut output, cipher.as_bytes());
+    encode_string(&mut output, kdf.as_bytes());
+    encode_string(&mut output, &kdf_options);
+    output.extend_from_slice(&1u32.to_be_bytes());
+    encode_string(
+        &mut output,
+        &public_key_blob(&key_pair.modulus, &key_pair.e),
+    );
+    encode_string(&mut output, &private_section);
+
+    let encoded = STANDARD.encode(output);
+    let mut pem = format!("-----BEGIN {}-----\n", PEM_LABEL);
+    for line in encoded.as_bytes().chunks(PEM_LINE_LENGTH) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", PEM_LABEL));
+    Ok(pem)
+}
+
+// Whether reading the private key needs a passphrase
+pub fn private_key_is_encrypted(pem: &str) -> Result<bool, String> {
+    let data = private_key_data(pem)?;
+    let mut input = &data[AUTH_MAGIC.len()..];
+    Ok(decode_string(&mut input)? != b"none")
+}
+
+// Reads an openssh-key-v1 private key. The comment becomes the comment of the key pair
+pub fn private_key_from_pem(
+    pem: &str,
+    name: String,
+    passphrase: Option<&str>,
+) -> Result<KeyPair, String> {
+    let data = private_key_data(pem)?;
+    let mut input = &data[AUTH_MAGIC.len()..];
+
+    let cipher = decode_string(&mut input)?;
+    let kdf = decode_string(&mut input)?;
+    let mut kdf_options = decode_string(&mut input)?;
+    if decode_u32(&mut input)? != 1 {
+        return Err("only files with a single key are supported".to_string());
+    }
+    let public_key = public_key_from_blob(decode_string(&mut input)?)?;
+    let mut private_section = decode_string(&mut input)?.to_vec();
+
+    match (cipher, kdf) {
+        (b"none", b"none") => {}
+        (cipher, kdf) if cipher == CIPHER.as_bytes() && kdf == KDF.as_bytes() => {
+            let passphrase = passphrase.ok_or("the key is encrypted, a passphrase is needed")?;
+            let salt = decode_string(&mut kdf_options)?;
+            let rounds = decode_u32(&mut kdf_options)?;
+            let mut cipher = key_stream(passphrase, salt, rounds)?;
+            cipher.apply_keystream(&mut private_section);
+        }
+        (cipher, kdf) => {
+            return Err(format!(
+                "unsupported encryption {} with {}",
+                String::from_utf8_lossy(cipher),
+                String::from_utf8_lossy(kdf)
+            ))
+        }
+    }
+
+    let mut input = private_section.as_slice();
+    if decode_u32(&mut input)? != decode_u32(&mut input)? {
+        return Err("wrong passphrase".to_string());
+    }
+    let key_type = decode_string(&mut input)?;
+    if key_type != KEY_TYPE.as_bytes() {
+        return Err(format!(
+            "unsupported key type {}",
+            String::from_utf8_lossy(key_type)
+        ));
+    }
+    let modulus = decode_mpint(&mut input)?;
+    let e = decode_mpint(&mut input)?;
+    let d = decode_mpint(&mut input)?;
+    let _qinv = decode_mpint(&mut input)?;
+    let p = decode_mpint(&mut input)?;
+    let q = decode_mpint(&mut input)?;
+    let comment = String::from_utf8_lossy(decode_string(&mut input)?).into_owned();
+
+    if public_key != (modulus.clone(), e.clone()) {
+        return Err("the public and the private key do not match".to_string());
+    }
+    key_storage::check_private_key(&modulus, &e, &d, &[p.clone(), q.clone()])?;
+
+    let mut key_pair = KeyPair::new(name, p, q, modulus, e, BigInt::from(d));
+    key_pair.generation_method = GenerationMethod::Imported;
+    key_pair.comment = comment;
+    Ok(key_pair)
+}
+
+fn private_key_data(pem: &str) -> Result<Vec<u8>, String> {
+    let (label, data) = pkcs1::from_pem(pem)?;
+    if label != PEM_LABEL {
+        return Err(format!("unsupported PEM type: {}", label));
+    }
+    if !data.starts_with(AUTH_MAGIC) {
+        return Err("not an openssh-key-v1 private key".to_string());
+    }
+    Ok(data)
+}
+
+fn key_stream(passphrase: &str, salt: &[u8], rounds: u32) -> Result<Aes256Ctr, String> {
+    let mut key_and_iv = [0u8; KEY_LENGTH + IV_LENGTH];
+    bcrypt_pbkdf::bcrypt_pbkdf(passphrase.as_bytes(), salt, rounds, &mut key_and_iv)
+        .map_err(|error| format!("bcrypt_pbkdf failed: {}", error))?;
+    let (key, iv) = key_and_iv.split_at(KEY_LENGTH);
+    Ok(Aes256Ctr::new(key.into(), iv.into()))
+}
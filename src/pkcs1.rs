@@ -0,0 +1,159 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use num_bigint_dig::BigUint;
+
+use crate::der;
+use crate::key_storage::KeyPair;
+
+// RFC 8017 A.1.2: version 0 is a two-prime key, version 1 carries otherPrimeInfos
+const VERSION_TWO_PRIME: u32 = 0;
+const VERSION_MULTI: u32 = 1;
+
+const PEM_LINE_LENGTH: usize = 64;
+
+// DER of the rsaEncryption OID 1.2.840.113549.1.1.1
+const RSA_ENCRYPTION_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+// RSAPublicKey ::= SEQUENCE { modulus, publicExponent }
+pub fn public_key_to_der(modulus: &BigUint, e: &BigUint) -> Vec<u8> {
+    der::encode_sequence(&[der::encode_integer(modulus), der::encode_integer(e)])
+}
+
+// Returns the modulus and the public exponent
+pub fn public_key_from_der(input: &[u8]) -> Result<(BigUint, BigUint), String> {
+    let (sequence, rest) = der::decode_sequence(input)?;
+    if !rest.is_empty() {
+        return Err("unexpected data after RSAPublicKey".to_string());
+    }
+    let (modulus, sequence) = der::decode_integer(sequence)?;
+    let (e, _) = der::decode_integer(sequence)?;
+    Ok((modulus, e))
+}
+
+// SubjectPublicKeyInfo ::= SEQUENCE { algorithm AlgorithmIdentifier, subjectPublicKey BIT STRING },
+// the "PUBLIC KEY" PEM OpenSSL writes by default. The bit string holds an RSAPublicKey
+pub fn public_key_to_subject_public_key_info(modulus: &BigUint, e: &BigUint) -> Vec<u8> {
+    der::encode_sequence(&[
+        der::encode_sequence(&[
+            der::encode_tlv(der::TAG_OBJECT_IDENTIFIER, &RSA_ENCRYPTION_OID),
+            der::encode_tlv(der::TAG_NULL, &[]),
+        ]),
+        der::encode_bit_string(&public_key_to_der(modulus, e)),
+    ])
+}
+
+pub fn public_key_from_subject_public_key_info(input: &[u8]) -> Result<(BigUint, BigUint), String> {
+    let (sequence, _) = der::decode_sequence(input)?;
+    let (algorithm, sequence) = der::decode_sequence(sequence)?;
+    let (oid, _) = der::decode_expected(algorithm, der::TAG_OBJECT_IDENTIFIER)?;
+    if oid != RSA_ENCRYPTION_OID {
+        return Err("not an RSA public key".to_string());
+    }
+
+    let (bit_string, _) = der::decode_expected(sequence, der::TAG_BIT_STRING)?;
+    // The first byte counts the unused bits of the last byte, always 0 here
+    match bit_string.split_first() {
+        Some((0, public_key)) => public_key_from_der(public_key),
+        _ => Err("malformed subjectPublicKey".to_string()),
+    }
+}
+
+// RSAPrivateKey ::= SEQUENCE { version, modulus, publicExponent, privateExponent, prime1,
+//     prime2, exponent1, exponent2, coefficient, otherPrimeInfos OtherPrimeInfos OPTIONAL }
+// OtherPrimeInfo ::= SEQUENCE { prime, exponent, coefficient }
+pub fn private_key_to_der(key_pair: &KeyPair) -> Result<Vec<u8>, String> {
+    let version = if key_pair.other_primes.is_empty() {
+        VERSION_TWO_PRIME
+    } else {
+        VERSION_MULTI
+    };
+
+    let mut elements = vec![
+        der::encode_integer(&BigUint::from(version)),
+        der::encode_integer(&key_pair.modulus),
+        der::encode_integer(&key_pair.e),
+        der::encode_integer(&key_pair.d.to_biguint().unwrap()),
+        der::encode_integer(&key_pair.p),
+        der::encode_integer(&key_pair.q),
+        der::encode_integer(&key_pair.dp()),
+        der::encode_integer(&key_pair.dq()),
+        der::encode_integer(
+            &key_pair
+                .qinv()
+                .ok_or("the primes of the key are not coprime")?,
+        ),
+    ];
+
+    if !key_pair.other_primes.is_empty() {
+        let other_prime_infos: Vec<Vec<u8>> = key_pair
+            .other_primes
+            .iter()
+            .map(|info| {
+                der::encode_sequence(&[
+                    der::encode_integer(&info.prime),
+                    der::encode_integer(&info.exponent),
+                    der::encode_integer(&info.coefficient),
+                ])
+            })
+            .collect();
+        elements.push(der::encode_sequence(&other_prime_infos));
+    }
+
+    Ok(der::encode_sequence(&elements))
+}
+
+pub fn public_key_to_pem(modulus: &BigUint, e: &BigUint) -> String {
+    to_pem("RSA PUBLIC KEY", &public_key_to_der(modulus, e))
+}
+
+// Accepts both "RSA PUBLIC KEY" (PKCS#1) and "PUBLIC KEY" (SubjectPublicKeyInfo) PEM
+pub fn public_key_from_pem(pem: &str) -> Result<(BigUint, BigUint), String> {
+    let (label, der) = from_pem(pem)?;
+    match label.as_str() {
+        "RSA PUBLIC KEY" => public_key_from_der(&der),
+        "PUBLIC KEY" => public_key_from_subject_public_key_info(&der),
+        _ => Err(format!("unsupported PEM type: {}", label)),
+    }
+}
+
+pub fn private_key_to_pem(key_pair: &KeyPair) -> Result<String, String> {
+    Ok(to_pem("RSA PRIVATE KEY", &private_key_to_der(key_pair)?))
+}
+
+pub fn to_pem(label: &str, der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+
+    for line in encoded.as_bytes().chunks(PEM_LINE_LENGTH) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+// Returns the label and the decoded DER of the first PEM block in `pem`
+pub fn from_pem(pem: &str) -> Result<(String, Vec<u8>), String> {
+    let mut lines = pem.lines().map(str::trim);
+
+    let label = lines
+        .find_map(|line| {
+            line.strip_prefix("-----BEGIN ")
+                .and_then(|line| line.strip_suffix("-----"))
+        })
+        .ok_or("no PEM block found")?
+        .to_string();
+
+    let end = format!("-----END {}-----", label);
+    let mut encoded = String::new();
+    for line in lines.by_ref() {
+        if line == end {
+            let der = STANDARD
+                .decode(encoded)
+                .map_err(|error| format!("invalid base64 in PEM: {}", error))?;
+            return Ok((label, der));
+        }
+        encoded.push_str(line);
+    }
+    Err(format!("missing \"{}\"", end))
+}
@@ -0,0 +1,318 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use num_bigint_dig::prime::probably_prime;
+use num_bigint_dig::{BigUint, RandBigInt};
+use num_traits::{One, ToPrimitive, Zero};
+use rand::Rng;
+
+// Candidates are trial-divided by every prime below this bound before Miller-Rabin
+const SMALL_PRIMES_BOUND: u32 = 2000;
+
+// Odd numbers checked after a random starting point before a new one is drawn
+const SEARCH_WINDOW: u32 = 1 << 16;
+
+const MILLER_RABIN_ROUNDS: usize = 40;
+
+// The small primes `RandPrime::gen_prime` sieves with, their product fits in a u64
+const GEN_PRIME_SMALL_PRIMES: [u64; 15] = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+const GEN_PRIME_SMALL_PRIMES_PRODUCT: u64 = 16_294_579_238_595_022_365;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchProgress {
+    pub workers: usize,
+    pub candidates: u64,
+    // Candidates thrown away because a small prime divides them
+    pub sieve_hits: u64,
+    pub miller_rabin_rounds: u64,
+    pub primes_found: usize,
+    pub primes_needed: usize,
+}
+
+#[derive(Default)]
+struct Counters {
+    candidates: AtomicU64,
+    sieve_hits: AtomicU64,
+    miller_rabin_rounds: AtomicU64,
+}
+
+// Searches for primes on several threads at once. The counters add up over every
+// search made with the same instance
+pub struct PrimeSearch {
+    workers: usize,
+    small_primes: Vec<u32>,
+    counters: Counters,
+}
+
+impl PrimeSearch {
+    pub fn new(workers: usize) -> Self {
+        PrimeSearch {
+            workers: workers.max(1),
+            small_primes: small_primes(SMALL_PRIMES_BOUND),
+            counters: Counters::default(),
+        }
+    }
+
+    // One worker for every core
+    pub fn on_all_cores() -> Self {
+        PrimeSearch::new(thread::available_parallelism().map_or(1, |count| count.get()))
+    }
+
+    pub fn progress(&self, primes_found: usize, primes_needed: usize) -> SearchProgress {
+        SearchProgress {
+            workers: self.workers,
+            candidates: self.counters.candidates.load(Ordering::Relaxed),
+            sieve_hits: self.counters.sieve_hits.load(Ordering::Relaxed),
+            miller_rabin_rounds: self.counters.miller_rabin_rounds.load(Ordering::Relaxed),
+            primes_found,
+            primes_needed,
+        }
+    }
+
+    // Finds one prime of every size in `sizes`, returns None once `cancel` is set.
+    // The progress is sent to `progress` while searching, a closed receiver is ignored
+    pub fn find_primes(
+        &self,
+        sizes: &[usize],
+        cancel: &AtomicBool,
+        progress: &Sender<SearchProgress>,
+    ) -> Option<Vec<BigUint>> {
+        let mut primes: Vec<BigUint> = Vec::with_capacity(sizes.len());
+
+        for &size in sizes.iter() {
+            primes.push(self.find_prime(size, cancel, primes.len(), sizes.len(), progress)?);
+            let _ = progress.send(self.progress(primes.len(), sizes.len()));
+        }
+        Some(primes)
+    }
+
+    fn find_prime(
+        &self,
+        bits: usize,
+        cancel: &AtomicBool,
+        primes_found: usize,
+        primes_needed: usize,
+        progress: &Sender<SearchProgress>,
+    ) -> Option<BigUint> {
+        let stop = AtomicBool::new(false);
+
+        // Scoped threads are always joined before returning, so cancelling never
+        // leaves a worker running in the background
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            for _ in 0..self.workers {
+                let sender = sender.clone();
+                let stop = &stop;
+                scope.spawn(move || {
+                    let should_stop =
+                        || stop.load(Ordering::Relaxed) || cancel.load(Ordering::Relaxed);
+                    if let Some(prime) = self.search(bits, &should_stop) {
+                        let _ = sender.send(prime);
+                    }
+                });
+            }
+            drop(sender);
+
+            let prime = loop {
+                if cancel.load(Ordering::Relaxed) {
+                    break None;
+                }
+                match receiver.recv_timeout(PROGRESS_INTERVAL) {
+                    Ok(prime) => break Some(prime),
+                    Err(RecvTimeoutError::Timeout) => {
+                        let _ = progress.send(self.progress(primes_found, primes_needed));
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break None,
+                }
+            };
+            stop.store(true, Ordering::Relaxed);
+            prime
+        })
+    }
+
+    // Incremental search: the residues of a random odd start modulo the small primes
+    // are computed once, then every following odd number is sieved with u32 arithmetic
+    fn search<F>(&self, bits: usize, should_stop: &F) -> Option<BigUint>
+    where
+        F: Fn() -> bool,
+    {
+        let mut rng = rand::thread_rng();
+
+        while !should_stop() {
+            let start = random_odd(&mut rng, bits);
+            let residues: Vec<u32> = self
+                .small_primes
+                .iter()
+                .map(|&prime| (&start % BigUint::from(prime)).to_u32().unwrap())
+                .collect();
+
+            for delta in (0..SEARCH_WINDOW).step_by(2) {
+                if should_stop() {
+                    return None;
+                }
+                self.counters.candidates.fetch_add(1, Ordering::Relaxed);
+
+                let divisible = self
+                    .small_primes
+                    .iter()
+                    .zip(residues.iter())
+                    .any(|(&prime, &residue)| (residue + delta) % prime == 0);
+                if divisible {
+                    self.counters.sieve_hits.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+
+                let candidate = &start + BigUint::from(delta);
+                if candidate.bits() != bits {
+                    break;
+                }
+                let passed = miller_rabin(&candidate, MILLER_RABIN_ROUNDS, &mut rng, || {
+                    self.counters
+                        .miller_rabin_rounds
+                        .fetch_add(1, Ordering::Relaxed);
+                    !should_stop()
+                });
+                if passed {
+                    return Some(candidate);
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn is_probable_prime<R: Rng>(candidate: &BigUint, rounds: usize, rng: &mut R) -> bool {
+    miller_rabin(candidate, rounds, rng, || true)
+}
+
+// Miller-Rabin with random bases. `before_round` runs ahead of every round and stops
+// the test (reporting a composite) when it returns false
+pub fn miller_rabin<R, F>(
+    candidate: &BigUint,
+    rounds: usize,
+    rng: &mut R,
+    mut before_round: F,
+) -> bool
+where
+    R: Rng,
+    F: FnMut() -> bool,
+{
+    let one = BigUint::one();
+    let two = BigUint::from(2u32);
+    let three = BigUint::from(3u32);
+
+    if candidate <= &three {
+        return candidate > &one;
+    }
+    if (candidate % &two).is_zero() {
+        return false;
+    }
+
+    let candidate_minus_one = candidate - &one;
+
+    // candidate - 1 = 2^s * d with an odd d
+    let mut d = candidate_minus_one.clone();
+    let mut s = 0;
+    while (&d % &two).is_zero() {
+        d >>= 1;
+        s += 1;
+    }
+
+    'rounds: for _ in 0..rounds {
+        if !before_round() {
+            return false;
+        }
+
+        let base = rng.gen_biguint_range(&two, &candidate_minus_one);
+        let mut x = base.modpow(&d, candidate);
+        if x == one || x == candidate_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = (&x * &x) % candidate;
+            if x == candidate_minus_one {
+                continue 'rounds;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Draws the same candidates from `rng` as `RandPrime::gen_prime` of num-bigint-dig, so
+// seeded keys stay the same, but returns None once `cancel` is set. The flag is checked
+// before every candidate
+pub fn gen_prime<R: Rng + ?Sized>(
+    rng: &mut R,
+    bits: usize,
+    cancel: &AtomicBool,
+) -> Option<BigUint> {
+    assert!(bits >= 2, "prime size must be at least 2-bit");
+
+    let top_bits = match bits % 8 {
+        0 => 8,
+        top_bits => top_bits,
+    };
+    let mut bytes = vec![0u8; bits.div_ceil(8)];
+
+    while !cancel.load(Ordering::Relaxed) {
+        rng.fill_bytes(&mut bytes);
+        bytes[0] &= ((1u32 << top_bits) - 1) as u8;
+        if top_bits >= 2 {
+            bytes[0] |= 3u8 << (top_bits - 2);
+        } else {
+            bytes[0] |= 1;
+            if bytes.len() > 1 {
+                bytes[1] |= 0x80;
+            }
+        }
+        let last = bytes.len() - 1;
+        bytes[last] |= 1;
+
+        let mut candidate = BigUint::from_bytes_be(&bytes);
+        let residue = (&candidate % BigUint::from(GEN_PRIME_SMALL_PRIMES_PRODUCT))
+            .to_u64()
+            .unwrap();
+        let delta = (0..1u64 << 20).step_by(2).find(|delta| {
+            let m = residue + delta;
+            GEN_PRIME_SMALL_PRIMES
+                .iter()
+                .all(|&prime| !m.is_multiple_of(prime) || (bits <= 6 && m == prime))
+        });
+        if let Some(delta) = delta {
+            candidate += BigUint::from(delta);
+        }
+
+        if candidate.bits() == bits && probably_prime(&candidate, 20) {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+// Random odd number with the two top bits set, so the product of two such numbers
+// always has exactly twice as many bits. Products of more of them can be one bit short
+fn random_odd<R: Rng>(rng: &mut R, bits: usize) -> BigUint {
+    let top_bits = BigUint::from(3u32) << (bits - 2);
+    rng.gen_biguint(bits) | top_bits | BigUint::one()
+}
+
+// Sieve of Eratosthenes, skipping 2 because only odd candidates are tested
+fn small_primes(bound: u32) -> Vec<u32> {
+    let mut is_prime = vec![true; bound as usize];
+    let mut primes = Vec::new();
+
+    for number in (3..bound).step_by(2) {
+        if is_prime[number as usize] {
+            primes.push(number);
+            for multiple in (number * number..bound).step_by(number as usize) {
+                is_prime[multiple as usize] = false;
+            }
+        }
+    }
+    primes
+}
@@ -0,0 +1,289 @@
+use num_bigint_dig::{BigInt, BigUint};
+use num_traits::Num;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::blinding;
+use crate::crt;
+use crate::exponentiation::Exponentiation;
+use crate::key_storage::KeyPair;
+
+// Textbook hash-then-sign: the signature is SHA-256(message)^d mod n, hex encoded
+pub fn sign(message: &str, modulus: &BigUint, e: &BigUint, d: &BigInt) -> String {
+    let digest = BigUint::from_bytes_be(&Sha256::digest(message.as_bytes()));
+
+    blinding::private_key_operation(&(digest % modulus), modulus, e, d, Exponentiation::Builtin)
+        .to_str_radix(16)
+}
+
+pub fn verify(message: &str, signature: &str, modulus: &BigUint, e: &BigUint) -> bool {
+    let signature = match BigUint::from_str_radix(signature.trim(), 16) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    if &signature >= modulus {
+        return false;
+    }
+
+    let digest = BigUint::from_bytes_be(&Sha256::digest(message.as_bytes()));
+    signature.modpow(e, modulus) == digest % modulus
+}
+
+// Hash functions for the RFC 8017 signature schemes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            HashAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+            HashAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Sha384 => "SHA-384",
+            HashAlgorithm::Sha512 => "SHA-512",
+        }
+    }
+
+    pub fn output_length(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 => 64,
+        }
+    }
+
+    // DER of DigestInfo without the digest itself (RFC 8017 9.2, note 1)
+    fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            HashAlgorithm::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x01, 0x05, 0x00, 0x04, 0x20,
+            ],
+            HashAlgorithm::Sha384 => &[
+                0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x02, 0x05, 0x00, 0x04, 0x30,
+            ],
+            HashAlgorithm::Sha512 => &[
+                0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x03, 0x05, 0x00, 0x04, 0x40,
+            ],
+        }
+    }
+}
+
+// RSASSA-PKCS1-v1_5 (RFC 8017 8.2), the signature is k bytes long where k is the length
+// of the modulus in bytes
+pub fn sign_pkcs1_v15(
+    data: &[u8],
+    hash: HashAlgorithm,
+    key_pair: &KeyPair,
+) -> Result<Vec<u8>, String> {
+    // At least 8 bytes of 0xff padding (RFC 8017 9.2, step 3)
+    let needed = hash.digest_info_prefix().len() + hash.output_length() + 11;
+    check_key_size(
+        &key_pair.modulus,
+        8 * (needed - 1) + 1,
+        "RSASSA-PKCS1-v1_5",
+        hash,
+    )?;
+    let length = modulus_length(&key_pair.modulus);
+    let encoded = emsa_pkcs1_v15_encode(data, hash, length);
+    let signature = crt::private_key_operation(&BigUint::from_bytes_be(&encoded), key_pair);
+    Ok(to_fixed_length(&signature, length))
+}
+
+pub fn verify_pkcs1_v15(
+    data: &[u8],
+    signature: &[u8],
+    hash: HashAlgorithm,
+    modulus: &BigUint,
+    e: &BigUint,
+) -> bool {
+    let length = modulus_length(modulus);
+    let encoded = match public_key_operation(signature, modulus, e) {
+        Some(message) => to_fixed_length(&message, length),
+        None => return false,
+    };
+    encoded == emsa_pkcs1_v15_encode(data, hash, length)
+}
+
+// EM = 0x00 || 0x01 || 0xff...0xff || 0x00 || DigestInfo
+fn emsa_pkcs1_v15_encode(data: &[u8], hash: HashAlgorithm, length: usize) -> Vec<u8> {
+    let mut digest_info = hash.digest_info_prefix().to_vec();
+    digest_info.extend(hash.digest(data));
+
+    let padding_length = length.saturating_sub(digest_info.len() + 3);
+    let mut encoded = vec![0x00, 0x01];
+    encoded.extend(std::iter::repeat_n(0xff, padding_length));
+    encoded.push(0x00);
+    encoded.extend(digest_info);
+    encoded
+}
+
+// RSASSA-PSS (RFC 8017 8.1) with MGF1 over the same hash and a salt as long as the hash,
+// the parameters JWS uses for PS256
+pub fn sign_pss(data: &[u8], hash: HashAlgorithm, key_pair: &KeyPair) -> Result<Vec<u8>, String> {
+    // emLen >= hLen + sLen + 2 (RFC 8017 9.1.1, step 3) with emBits one below the key size
+    let needed = 2 * hash.output_length() + 2;
+    check_key_size(&key_pair.modulus, 8 * (needed - 1) + 2, "RSASSA-PSS", hash)?;
+    let encoded_bits = key_pair.modulus.bits() - 1;
+
+    let mut salt = vec![0u8; hash.output_length()];
+    OsRng.fill_bytes(&mut salt);
+
+    let encoded = emsa_pss_encode(data, hash, &salt, encoded_bits);
+    let signature = crt::private_key_operation(&BigUint::from_bytes_be(&encoded), key_pair);
+    Ok(to_fixed_length(
+        &signature,
+        modulus_length(&key_pair.modulus),
+    ))
+}
+
+pub fn verify_pss(
+    data: &[u8],
+    signature: &[u8],
+    hash: HashAlgorithm,
+    modulus: &BigUint,
+    e: &BigUint,
+) -> bool {
+    let encoded_bits = modulus.bits() - 1;
+    let encoded_length = encoded_bits.div_ceil(8);
+    let message = match public_key_operation(signature, modulus, e) {
+        Some(message) if message.bits() <= encoded_length * 8 => message,
+        _ => return false,
+    };
+    emsa_pss_verify(
+        data,
+        &to_fixed_length(&message, encoded_length),
+        hash,
+        encoded_bits,
+    )
+}
+
+// EM = maskedDB || H || 0xbc with H = Hash(0x00 * 8 || Hash(M) || salt) and
+// DB = 0x00...0x00 || 0x01 || salt masked with MGF1(H)
+fn emsa_pss_encode(data: &[u8], hash: HashAlgorithm, salt: &[u8], encoded_bits: usize) -> Vec<u8> {
+    let encoded_length = encoded_bits.div_ceil(8);
+    let hash_length = hash.output_length();
+
+    let mut prefixed = vec![0u8; 8];
+    prefixed.extend(hash.digest(data));
+    prefixed.extend_from_slice(salt);
+    let h = hash.digest(&prefixed);
+
+    let mut db = vec![0u8; encoded_length - salt.len() - hash_length - 2];
+    db.push(0x01);
+    db.extend_from_slice(salt);
+    for (byte, mask) in db
+        .iter_mut()
+        .zip(mgf1(&h, encoded_length - hash_length - 1, hash))
+    {
+        *byte ^= mask;
+    }
+    // Bits above emBits are cleared so EM is smaller than the modulus
+    db[0] &= 0xff >> (8 * encoded_length - encoded_bits);
+
+    let mut encoded = db;
+    encoded.extend(h);
+    encoded.push(0xbc);
+    encoded
+}
+
+fn emsa_pss_verify(data: &[u8], encoded: &[u8], hash: HashAlgorithm, encoded_bits: usize) -> bool {
+    let encoded_length = encoded.len();
+    let hash_length = hash.output_length();
+    let salt_length = hash_length;
+    if encoded_length < hash_length + salt_length + 2 || encoded[encoded_length - 1] != 0xbc {
+        return false;
+    }
+
+    let (masked_db, h) = encoded[..encoded_length - 1].split_at(encoded_length - hash_length - 1);
+    let top_bits = 0xff >> (8 * encoded_length - encoded_bits);
+    if masked_db[0] & !top_bits != 0 {
+        return false;
+    }
+
+    let mut db: Vec<u8> = masked_db
+        .iter()
+        .zip(mgf1(h, masked_db.len(), hash))
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    db[0] &= top_bits;
+
+    let separator = db.len() - salt_length - 1;
+    if db[..separator].iter().any(|&byte| byte != 0) || db[separator] != 0x01 {
+        return false;
+    }
+
+    let mut prefixed = vec![0u8; 8];
+    prefixed.extend(hash.digest(data));
+    prefixed.extend_from_slice(&db[separator + 1..]);
+    hash.digest(&prefixed) == h
+}
+
+// RFC 8017 B.2.1: Hash(seed || counter) for counter = 0, 1, ... cut to `length` bytes
+pub(crate) fn mgf1(seed: &[u8], length: usize, hash: HashAlgorithm) -> Vec<u8> {
+    let mut mask = Vec::with_capacity(length + hash.output_length());
+    let mut counter: u32 = 0;
+    while mask.len() < length {
+        let mut input = seed.to_vec();
+        input.extend_from_slice(&counter.to_be_bytes());
+        mask.extend(hash.digest(&input));
+        counter += 1;
+    }
+    mask.truncate(length);
+    mask
+}
+
+// s^e mod n, None when the signature has the wrong length or is not smaller than n
+fn public_key_operation(signature: &[u8], modulus: &BigUint, e: &BigUint) -> Option<BigUint> {
+    if signature.len() != modulus_length(modulus) {
+        return None;
+    }
+    let signature = BigUint::from_bytes_be(signature);
+    if &signature >= modulus {
+        return None;
+    }
+    Some(signature.modpow(e, modulus))
+}
+
+// Checked before encoding, the encoded message would not fit into a smaller key
+fn check_key_size(
+    modulus: &BigUint,
+    minimum_bits: usize,
+    scheme: &str,
+    hash: HashAlgorithm,
+) -> Result<(), String> {
+    if modulus.bits() < minimum_bits {
+        return Err(format!(
+            "the key is too small for {} with {}, it needs at least {} bits",
+            scheme,
+            hash.name(),
+            minimum_bits
+        ));
+    }
+    Ok(())
+}
+
+fn modulus_length(modulus: &BigUint) -> usize {
+    modulus.bits().div_ceil(8)
+}
+
+// I2OSP: big-endian with leading zero bytes up to `length`
+fn to_fixed_length(value: &BigUint, length: usize) -> Vec<u8> {
+    let bytes = value.to_bytes_be();
+    let mut output = vec![0u8; length.saturating_sub(bytes.len())];
+    output.extend(bytes);
+    output
+}
@@ -0,0 +1,104 @@
+use serde_json::{Map, Value};
+
+// Version written by this build. Bump it together with a new entry in MIGRATIONS
+// whenever the storage JSON changes in a way `serde` cannot read on its own
+pub const SCHEMA_VERSION: u64 = 1;
+
+// Files written before the version field existed
+pub const UNVERSIONED: u64 = 0;
+
+// Upgrades a storage document from `from` to `from + 1`
+pub struct Migration {
+    pub from: u64,
+    pub description: &'static str,
+    apply: fn(&mut Map<String, Value>) -> Result<(), String>,
+}
+
+const MIGRATIONS: [Migration; 1] = [Migration {
+    from: UNVERSIONED,
+    description: "add the schema version and fill in key metadata and contacts of older files",
+    apply: unversioned_to_1,
+}];
+
+pub fn schema_version(document: &Value) -> Result<u64, String> {
+    let object = document
+        .as_object()
+        .ok_or("the storage is not a JSON object")?;
+    match object.get("version") {
+        None => Ok(UNVERSIONED),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("invalid schema version {}", version)),
+    }
+}
+
+// Migrations that bring `document` to SCHEMA_VERSION, in the order they apply
+pub fn pending_migrations(document: &Value) -> Result<Vec<&'static Migration>, String> {
+    let version = schema_version(document)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "the storage has schema version {} but this build only knows up to {}, update the tool",
+            version, SCHEMA_VERSION
+        ));
+    }
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|migration| migration.from >= version)
+        .collect())
+}
+
+// Runs every pending migration on `document` in place and returns the ones that ran
+pub fn migrate(document: &mut Value) -> Result<Vec<&'static Migration>, String> {
+    let migrations = pending_migrations(document)?;
+    let object = document.as_object_mut().unwrap();
+    for migration in migrations.iter() {
+        (migration.apply)(object).map_err(|error| {
+            format!(
+                "migration from version {} failed: {}",
+                migration.from, error
+            )
+        })?;
+        object.insert("version".to_string(), Value::from(migration.from + 1));
+    }
+    Ok(migrations)
+}
+
+// Unversioned files come from every release before the version field: the first ones
+// only had `key_pairs` with name, p, q, modulus, e and d, later ones added
+// `other_primes`, `generation_method`, `contacts` and `created_at`/`comment`/`tags`
+fn unversioned_to_1(storage: &mut Map<String, Value>) -> Result<(), String> {
+    let key_pairs = storage
+        .entry("key_pairs")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or("key_pairs is not an object")?;
+
+    for (id, key_pair) in key_pairs.iter_mut() {
+        let key_pair = key_pair
+            .as_object_mut()
+            .ok_or_else(|| format!("key pair {} is not an object", id))?;
+        for field in ["name", "p", "q", "modulus", "e", "d"] {
+            if !key_pair.contains_key(field) {
+                return Err(format!("key pair {} has no {}", id, field));
+            }
+        }
+        key_pair
+            .entry("other_primes")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        key_pair
+            .entry("generation_method")
+            .or_insert_with(|| Value::from("Unknown"));
+        key_pair
+            .entry("created_at")
+            .or_insert_with(|| Value::from(0));
+        key_pair.entry("comment").or_insert_with(|| Value::from(""));
+        key_pair
+            .entry("tags")
+            .or_insert_with(|| Value::Array(Vec::new()));
+    }
+
+    storage
+        .entry("contacts")
+        .or_insert_with(|| Value::Object(Map::new()));
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::time::Instant;
+
+use num_bigint_dig::{BigUint, RandBigInt};
+
+use crate::exponentiation::{self, Exponentiation};
+use crate::key_storage::KeyPair;
+
+pub struct TimingStats {
+    pub samples: usize,
+    // All times are in nanoseconds
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl TimingStats {
+    pub fn from_samples(samples: &[f64]) -> Self {
+        let count = samples.len().max(1) as f64;
+        let mean = samples.iter().sum::<f64>() / count;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count;
+
+        TimingStats {
+            samples: samples.len(),
+            mean,
+            std_dev: variance.sqrt(),
+            min: samples.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: samples.iter().cloned().fold(0.0, f64::max),
+        }
+    }
+}
+
+pub struct VariantReport {
+    pub exponentiation: Exponentiation,
+    pub decryption: TimingStats,
+    // Mean time of the step processing every bit of d, most significant bit first
+    pub step_times: Vec<f64>,
+    // A bit is guessed to be 1 when its step took longer than the average step
+    pub guessed_bits: Vec<bool>,
+    // Pearson correlation between the step times and the real bits of d
+    pub correlation: f64,
+    // Share of bits guessed right, 0.5 is no better than a coin toss
+    pub accuracy: f64,
+}
+
+pub struct TimingReport {
+    pub bits: Vec<bool>,
+    pub variants: Vec<VariantReport>,
+}
+
+// Decrypts `samples` random ciphertexts with the leaky square-and-multiply and with the
+// Montgomery ladder, timing every exponent bit separately
+pub fn run_timing_experiment(key_pair: &KeyPair, samples: usize) -> TimingReport {
+    let d = key_pair.d.to_biguint().unwrap();
+    let bits = exponentiation::bits_msb_first(&d, d.bits());
+
+    let mut rng = rand::thread_rng();
+    let ciphertexts: Vec<BigUint> = (0..samples)
+        .map(|_| rng.gen_biguint_below(&key_pair.modulus))
+        .collect();
+
+    let variants = [
+        Exponentiation::SquareAndMultiply,
+        Exponentiation::MontgomeryLadder,
+    ]
+    .iter()
+    .map(|&exponentiation| measure_variant(exponentiation, &ciphertexts, key_pair, &d, &bits))
+    .collect();
+
+    TimingReport { bits, variants }
+}
+
+fn measure_variant(
+    exponentiation: Exponentiation,
+    ciphertexts: &[BigUint],
+    key_pair: &KeyPair,
+    d: &BigUint,
+    bits: &[bool],
+) -> VariantReport {
+    let mut decryption_times: Vec<f64> = Vec::new();
+    let mut step_sums: Vec<f64> = vec![0.0; bits.len()];
+
+    for ciphertext in ciphertexts.iter() {
+        let mut timestamps: Vec<Instant> = Vec::with_capacity(key_pair.modulus.bits() + 1);
+        timestamps.push(Instant::now());
+
+        let on_step = || timestamps.push(Instant::now());
+        match exponentiation {
+            Exponentiation::MontgomeryLadder => {
+                exponentiation::montgomery_ladder_traced(ciphertext, d, &key_pair.modulus, on_step)
+            }
+            _ => exponentiation::square_and_multiply_traced(
+                ciphertext,
+                d,
+                &key_pair.modulus,
+                on_step,
+            ),
+        };
+
+        let step_times: Vec<f64> = timestamps
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).as_nanos() as f64)
+            .collect();
+        decryption_times.push(step_times.iter().sum());
+
+        // The ladder also runs over the leading zeros of d, only the bits of d are compared
+        let skip = step_times.len() - bits.len();
+        for (sum, time) in step_sums.iter_mut().zip(step_times.iter().skip(skip)) {
+            *sum += time;
+        }
+    }
+
+    let step_times: Vec<f64> = step_sums
+        .iter()
+        .map(|sum| sum / ciphertexts.len().max(1) as f64)
+        .collect();
+    let threshold = TimingStats::from_samples(&step_times).mean;
+    let guessed_bits: Vec<bool> = step_times.iter().map(|time| *time > threshold).collect();
+    let right_guesses = guessed_bits
+        .iter()
+        .zip(bits.iter())
+        .filter(|(guess, bit)| guess == bit)
+        .count();
+
+    VariantReport {
+        exponentiation,
+        decryption: TimingStats::from_samples(&decryption_times),
+        correlation: correlation(&step_times, bits),
+        accuracy: right_guesses as f64 / bits.len().max(1) as f64,
+        step_times,
+        guessed_bits,
+    }
+}
+
+fn correlation(times: &[f64], bits: &[bool]) -> f64 {
+    let bits: Vec<f64> = bits
+        .iter()
+        .map(|&bit| if bit { 1.0 } else { 0.0 })
+        .collect();
+    let times_stats = TimingStats::from_samples(times);
+    let bits_stats = TimingStats::from_samples(&bits);
+
+    if times_stats.std_dev == 0.0 || bits_stats.std_dev == 0.0 {
+        return 0.0;
+    }
+
+    let covariance = times
+        .iter()
+        .zip(bits.iter())
+        .map(|(time, bit)| (time - times_stats.mean) * (bit - bits_stats.mean))
+        .sum::<f64>()
+        / times.len() as f64;
+    covariance / (times_stats.std_dev * bits_stats.std_dev)
+}
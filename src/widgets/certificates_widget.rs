@@ -0,0 +1,682 @@
+use std::io::Write;
+
+use crossterm::cursor;
+use crossterm::event::KeyCode;
+use crossterm::style;
+use crossterm::terminal;
+
+use crate::key_storage::{KeyPair, Storage};
+use crate::widgets::utils::read_char;
+use crate::x509::{self, Name, Validity};
+
+const CONTROLS: &str = r#"'x' - Create a self-signed certificate
+'r' - Create a certificate signing request
+'s' - Sign a request with a CA key
+'v' - View a certificate or request file"#;
+
+// One line of a form, toggles switch between "yes" and "no"
+struct Field {
+    label: &'static str,
+    value: String,
+    toggle: bool,
+}
+
+impl Field {
+    fn text(label: &'static str, value: &str) -> Field {
+        Field {
+            label,
+            value: value.to_string(),
+            toggle: false,
+        }
+    }
+
+    fn toggle(label: &'static str, value: bool) -> Field {
+        Field {
+            label,
+            value: if value { "yes" } else { "no" }.to_string(),
+            toggle: true,
+        }
+    }
+
+    fn is_set(&self) -> bool {
+        self.value == "yes"
+    }
+}
+
+pub fn run<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let mut index = 0;
+    let count = CONTROLS.split('\n').count();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("CERTIFICATES"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("X.509 certificates and PKCS#10 requests for the keys of the keyring."),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for (i, line) in CONTROLS.split('\n').enumerate() {
+            if i == index {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                    style::SetAttribute(style::Attribute::Bold),
+                )?;
+            } else {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::SetBackgroundColor(style::Color::Reset),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            crossterm::queue!(
+                w,
+                style::Print(line),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Use arrow keys to navigate, Enter to select or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        let choice = match read_char()? {
+            KeyCode::Up => {
+                index = (index + count - 1) % count;
+                continue;
+            }
+            KeyCode::Down => {
+                index = (index + 1) % count;
+                continue;
+            }
+            KeyCode::Enter => index,
+            KeyCode::Char('x') => 0,
+            KeyCode::Char('r') => 1,
+            KeyCode::Char('s') => 2,
+            KeyCode::Char('v') => 3,
+            KeyCode::Esc => return Ok(()),
+            _ => continue,
+        };
+        match choice {
+            0 => run_create(w, true)?,
+            1 => run_create(w, false)?,
+            2 => run_sign(w)?,
+            _ => run_view(w)?,
+        }
+    }
+}
+
+// Self-signed certificate or, without `certificate`, a signing request for a stored key
+fn run_create<W>(w: &mut W, certificate: bool) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_message(w, &error),
+    };
+    let prompt = if certificate {
+        "Choose the key pair to certify:"
+    } else {
+        "Choose the key pair to request a certificate for:"
+    };
+    let key_pair = match run_choose_key_pair(w, &storage, prompt, None)? {
+        Some(key_pair) => key_pair,
+        None => return Ok(()),
+    };
+
+    let extension = if certificate { "crt" } else { "csr" };
+    let mut fields = vec![
+        Field::text("Subject", &format!("CN={}", key_pair.name)),
+        Field::text("Alternative names", ""),
+        Field::toggle("Certificate authority", false),
+        Field::text("Path length", ""),
+        Field::text("Key usage", ""),
+        Field::text("Valid for days", "365"),
+        Field::toggle("Write DER instead of PEM", false),
+        Field::text(
+            "Output file",
+            &format!("{}.{}.pem", key_pair.name, extension),
+        ),
+    ];
+    if !certificate {
+        // Requests have no validity, the issuer decides it
+        fields.remove(5);
+    }
+
+    let title = if certificate {
+        "Self-signed certificate"
+    } else {
+        "Certificate signing request"
+    };
+    loop {
+        if !run_form(w, title, &mut fields)? {
+            return Ok(());
+        }
+        let value = |label: &str| {
+            fields
+                .iter()
+                .find(|field| field.label == label)
+                .map_or("", |field| field.value.as_str())
+        };
+        let is_set = |label: &str| {
+            fields
+                .iter()
+                .any(|field| field.label == label && field.is_set())
+        };
+
+        let result = (|| -> Result<(Vec<u8>, Vec<String>), String> {
+            let subject = Name::parse(value("Subject"))?;
+            let path_length = match value("Path length").trim() {
+                "" => None,
+                length => Some(
+                    length
+                        .parse()
+                        .map_err(|_| format!("\"{}\" is not a path length", length))?,
+                ),
+            };
+            let extensions = x509::extensions_from_text(
+                value("Alternative names"),
+                is_set("Certificate authority"),
+                path_length,
+                value("Key usage"),
+            )?;
+
+            if certificate {
+                let days: i64 = value("Valid for days")
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|days| *days > 0)
+                    .ok_or("the validity must be a positive number of days")?;
+                let der = x509::self_signed_certificate(
+                    &subject,
+                    &extensions,
+                    Validity::days_from_now(days),
+                    key_pair,
+                )?;
+                let lines = x509::parse_certificate(&der)?.describe();
+                Ok((der, lines))
+            } else {
+                let der = x509::certificate_request(&subject, &extensions, key_pair)?;
+                let lines = x509::parse_certificate_request(&der)?.describe();
+                Ok((der, lines))
+            }
+        })();
+
+        match result {
+            Ok((der, lines)) => {
+                let pem = if certificate {
+                    x509::certificate_to_pem(&der)
+                } else {
+                    x509::certificate_request_to_pem(&der)
+                };
+                return run_write_and_show(
+                    w,
+                    value("Output file"),
+                    if is_set("Write DER instead of PEM") {
+                        der
+                    } else {
+                        pem.into_bytes()
+                    },
+                    lines,
+                );
+            }
+            Err(error) => run_print_message(w, &error)?,
+        }
+    }
+}
+
+fn run_sign<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let request_path = match run_enter_text(w, "Certificate request file", "request.csr.pem")? {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let request =
+        match read_file(&request_path).and_then(|input| x509::read_certificate_request(&input)) {
+            Ok(request) => request,
+            Err(error) => return run_print_message(w, &error),
+        };
+    let issuer_path = match run_enter_text(w, "CA certificate file", "ca.crt.pem")? {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let issuer = match read_file(&issuer_path).and_then(|input| x509::read_certificate(&input)) {
+        Ok(issuer) => issuer,
+        Err(error) => return run_print_message(w, &error),
+    };
+
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_message(w, &error),
+    };
+    let prompt = format!("Choose the key pair of \"{}\":", issuer.subject.text());
+    let issuer_key = match run_choose_key_pair(w, &storage, &prompt, Some(&issuer))? {
+        Some(key_pair) => key_pair,
+        None => return Ok(()),
+    };
+
+    let default_path = format!(
+        "{}.crt.pem",
+        request.subject.common_name().unwrap_or("certificate")
+    );
+    let mut fields = vec![
+        Field::text("Valid for days", "365"),
+        Field::toggle("Write DER instead of PEM", false),
+        Field::text("Output file", &default_path),
+    ];
+    loop {
+        if !run_form(
+            w,
+            &format!("Certificate for {}", request.subject.text()),
+            &mut fields,
+        )? {
+            return Ok(());
+        }
+
+        let result = fields[0]
+            .value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|days| *days > 0)
+            .ok_or_else(|| "the validity must be a positive number of days".to_string())
+            .and_then(|days| {
+                x509::sign_request(&request, &issuer, issuer_key, Validity::days_from_now(days))
+            });
+        match result {
+            Ok(der) => {
+                let mut lines = x509::parse_certificate(&der).unwrap().describe();
+                lines.push(format!("Signed by {}", issuer.subject.text()));
+                let bytes = if fields[1].is_set() {
+                    der
+                } else {
+                    x509::certificate_to_pem(&der).into_bytes()
+                };
+                return run_write_and_show(w, &fields[2].value.clone(), bytes, lines);
+            }
+            Err(error) => run_print_message(w, &error)?,
+        }
+    }
+}
+
+fn run_view<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let path = match run_enter_text(w, "Certificate or request file", "certificate.crt.pem")? {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let input = match read_file(&path) {
+        Ok(input) => input,
+        Err(error) => return run_print_message(w, &error),
+    };
+
+    let mut lines = vec![path.clone(), String::new()];
+    match x509::read_certificate(&input) {
+        Ok(certificate) => lines.extend(certificate.describe()),
+        Err(_) => match x509::read_certificate_request(&input) {
+            Ok(request) => lines.extend(request.describe()),
+            Err(error) => {
+                return run_print_message(
+                    w,
+                    &format!("Neither a certificate nor a request: {}", error),
+                )
+            }
+        },
+    }
+    run_print_lines(w, &lines)
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|error| format!("Cannot read {}: {}", path, error))
+}
+
+fn run_write_and_show<W>(
+    w: &mut W,
+    path: &str,
+    bytes: Vec<u8>,
+    mut lines: Vec<String>,
+) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    match std::fs::write(path, bytes) {
+        Ok(()) => lines.insert(0, format!("Written to {}", path)),
+        Err(error) => lines.insert(0, format!("Cannot write {}: {}", path, error)),
+    }
+    lines.insert(1, String::new());
+    run_print_lines(w, &lines)
+}
+
+// With `issuer`, the key pair that belongs to the certificate is selected first
+fn run_choose_key_pair<'a, W>(
+    w: &mut W,
+    storage: &'a Storage,
+    prompt: &str,
+    issuer: Option<&x509::Certificate>,
+) -> crossterm::Result<Option<&'a KeyPair>>
+where
+    W: Write,
+{
+    let key_pairs: Vec<&KeyPair> = storage.key_pairs().collect();
+    if key_pairs.is_empty() {
+        run_print_message(w, "No key pairs found. Please create a new key pair.")?;
+        return Ok(None);
+    }
+    let mut index = issuer
+        .and_then(|issuer| {
+            key_pairs
+                .iter()
+                .position(|key_pair| key_pair.modulus == issuer.modulus)
+        })
+        .unwrap_or(0);
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("CERTIFICATES"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(prompt),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+        )?;
+
+        for (id, key_pair) in key_pairs.iter().enumerate() {
+            if id == index {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                    style::SetAttribute(style::Attribute::Bold),
+                )?;
+            } else {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::SetBackgroundColor(style::Color::Reset),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            let matches = issuer.is_some_and(|issuer| issuer.modulus == key_pair.modulus);
+            crossterm::queue!(
+                w,
+                style::Print(format!(
+                    "{}: {} ({} bits){}",
+                    id,
+                    key_pair.name,
+                    key_pair.bit_length(),
+                    if matches {
+                        " - matches the certificate"
+                    } else {
+                        ""
+                    }
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to select or ESC to go back."),
+        )?;
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => index = (index + key_pairs.len() - 1) % key_pairs.len(),
+            KeyCode::Down => index = (index + 1) % key_pairs.len(),
+            KeyCode::Enter => return Ok(Some(key_pairs[index])),
+            KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+// Edits the fields in place, returns false when the user cancelled with ESC
+fn run_form<W>(w: &mut W, title: &str, fields: &mut [Field]) -> crossterm::Result<bool>
+where
+    W: Write,
+{
+    let mut index = 0;
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("CERTIFICATES"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(title),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for (i, field) in fields.iter().enumerate() {
+            if i == index {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                    style::SetAttribute(style::Attribute::Bold),
+                )?;
+            } else {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::SetBackgroundColor(style::Color::Reset),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            crossterm::queue!(
+                w,
+                style::Print(format!("{}: {}", field.label, field.value)),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(
+                "Alternative names: DNS:example.org, IP:127.0.0.1, email:a@example.org, URI:..."
+            ),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(
+                "Key usage: digitalSignature, nonRepudiation, keyEncipherment, dataEncipherment,"
+            ),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("  keyAgreement, keyCertSign, cRLSign. Empty picks the usual ones."),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Type to edit the selected line, Space toggles yes/no."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Use arrow keys to navigate, Enter to create or ESC to go back."),
+        )?;
+        w.flush()?;
+
+        let field = &mut fields[index];
+        match read_char()? {
+            KeyCode::Up => index = (index + fields.len() - 1) % fields.len(),
+            KeyCode::Down | KeyCode::Tab => index = (index + 1) % fields.len(),
+            KeyCode::Enter => return Ok(true),
+            KeyCode::Esc => return Ok(false),
+            KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right if field.toggle => {
+                field.value = if field.is_set() { "no" } else { "yes" }.to_string();
+            }
+            KeyCode::Backspace if !field.toggle => {
+                field.value.pop();
+            }
+            KeyCode::Char(c) if !field.toggle => field.value.push(c),
+            _ => {}
+        }
+    }
+}
+
+fn run_enter_text<W>(
+    w: &mut W,
+    prompt: &str,
+    default_value: &str,
+) -> crossterm::Result<Option<String>>
+where
+    W: Write,
+{
+    let mut tmp_input_line: String = String::new();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("CERTIFICATES"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "{} ({}): {}",
+                prompt, default_value, tmp_input_line
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to confirm or ESC to cancel."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Enter => {
+                if tmp_input_line.is_empty() {
+                    return Ok(Some(default_value.to_string()));
+                }
+                return Ok(Some(tmp_input_line));
+            }
+            KeyCode::Backspace => {
+                tmp_input_line.pop();
+            }
+            KeyCode::Esc => {
+                return Ok(None);
+            }
+            KeyCode::Char(c) => {
+                tmp_input_line.push(c);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Scrollable list of lines
+fn run_print_lines<W>(w: &mut W, lines: &[String]) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let mut scroll: usize = 0;
+
+    loop {
+        let (_, rows) = terminal::size()?;
+        let visible = (rows as usize).saturating_sub(7).max(1);
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("CERTIFICATES"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for line in lines.iter().skip(scroll).take(visible) {
+            crossterm::queue!(
+                w,
+                style::Print(line),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Arrow keys scroll, ESC goes back."),
+        )?;
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => scroll = scroll.saturating_sub(1),
+            KeyCode::Down if scroll + visible < lines.len() => scroll += 1,
+            KeyCode::Esc | KeyCode::Enter => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn run_print_message<W>(w: &mut W, message: &str) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    crossterm::queue!(
+        w,
+        style::ResetColor,
+        terminal::Clear(crossterm::terminal::ClearType::All),
+        cursor::Hide,
+        cursor::MoveTo(2, 2),
+        style::Print("CERTIFICATES"),
+        cursor::MoveToNextLine(2),
+        cursor::MoveRight(2),
+        style::Print(message),
+        cursor::MoveToNextLine(2),
+        cursor::MoveRight(2),
+        style::Print("Press any key to go back."),
+    )?;
+    w.flush()?;
+    read_char()?;
+    Ok(())
+}
@@ -4,11 +4,15 @@ use crossterm::cursor;
 use crossterm::event::KeyCode;
 use crossterm::style;
 use crossterm::terminal;
+use num_bigint_dig::BigUint;
 
-use crate::decrypt::decrypt;
+use crate::decrypt::decrypt_crt;
+use crate::encoding::{self, Encoding};
 use crate::encrypt::encrypt;
-use crate::key_storage::Storage;
-use crate::widgets::utils::{read_char, EncryptOrDecrypt};
+use crate::envelope;
+use crate::key_storage::{Contact, KeyPair, Storage};
+use crate::sign::{sign, verify};
+use crate::widgets::utils::{read_char, run_print_error, EncryptOrDecrypt};
 
 #[derive(PartialEq)]
 pub enum EncryptWidgetState {
@@ -17,6 +21,28 @@ pub enum EncryptWidgetState {
     End,
 }
 
+// Encrypting and verifying only need a public key, so contacts are offered as well
+enum KeyEntry<'a> {
+    KeyPair(&'a KeyPair),
+    Contact(&'a Contact),
+}
+
+impl KeyEntry<'_> {
+    fn label(&self) -> String {
+        match self {
+            KeyEntry::KeyPair(key_pair) => key_pair.name.clone(),
+            KeyEntry::Contact(contact) => format!("{} (contact)", contact.name),
+        }
+    }
+
+    fn public_key(&self) -> (&BigUint, &BigUint) {
+        match self {
+            KeyEntry::KeyPair(key_pair) => (&key_pair.modulus, &key_pair.e),
+            KeyEntry::Contact(contact) => (&contact.modulus, &contact.e),
+        }
+    }
+}
+
 #[derive(PartialEq)]
 pub struct TextResult {
     text: Option<String>,
@@ -28,7 +54,17 @@ where
     W: Write,
 {
     let mut index: usize = 0;
-    let storage = Storage::load_storage();
+    // Entries marked with Space, a message for several recipients when encrypting
+    let mut marked: Vec<usize> = Vec::new();
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_error(w, procedure.title(), &error),
+    };
+
+    let mut entries: Vec<KeyEntry> = storage.key_pairs().map(KeyEntry::KeyPair).collect();
+    if procedure == EncryptOrDecrypt::Encrypt || procedure == EncryptOrDecrypt::Verify {
+        entries.extend(storage.contacts().map(KeyEntry::Contact));
+    }
 
     loop {
         crossterm::queue!(
@@ -38,19 +74,18 @@ where
             cursor::Hide,
             cursor::MoveTo(2, 2),
             cursor::DisableBlinking,
-            if procedure == EncryptOrDecrypt::Encrypt {
-                style::Print("ENCRYPT")
-            } else {
-                style::Print("DECRYPT")
-            },
+            style::Print(procedure.title()),
             cursor::MoveToNextLine(2),
             cursor::MoveRight(2),
-            style::Print("Choose a key pair to encrypt/decrypt your message:"),
+            style::Print(format!(
+                "Choose a key pair to {} your message:",
+                procedure.verb()
+            )),
             cursor::MoveToNextLine(1),
             cursor::MoveRight(2),
         )?;
 
-        if storage.get_key_pairs().len() == 0 {
+        if entries.is_empty() {
             crossterm::queue!(
                 w,
                 style::Print("No key pairs found. Please create a new key pair."),
@@ -58,8 +93,7 @@ where
                 cursor::MoveRight(2),
             )?;
         } else {
-            for id in 0..storage.get_key_pairs().len() {
-                let key_pair = storage.get_key_pair_by_id(id).unwrap();
+            for (id, entry) in entries.iter().enumerate() {
                 if id == index {
                     crossterm::queue!(
                         w,
@@ -76,9 +110,14 @@ where
                     )?;
                 }
 
+                let mark = match procedure {
+                    EncryptOrDecrypt::Encrypt if marked.contains(&id) => "[x] ",
+                    EncryptOrDecrypt::Encrypt => "[ ] ",
+                    _ => "",
+                };
                 crossterm::queue!(
                     w,
-                    style::Print(format!("{}: {}", id, key_pair.name)),
+                    style::Print(format!("{}{}: {}", mark, id, entry.label())),
                     cursor::MoveToNextLine(1),
                     cursor::MoveRight(2)
                 )?;
@@ -97,62 +136,141 @@ where
             cursor::MoveRight(2),
             style::Print("Press Enter button to select key or ESC to go back."),
         )?;
+        if procedure == EncryptOrDecrypt::Encrypt {
+            crossterm::queue!(
+                w,
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print(
+                    "Press Space to mark several recipients, Enter encrypts to all marked keys."
+                ),
+            )?;
+        } else if procedure == EncryptOrDecrypt::Decrypt {
+            crossterm::queue!(
+                w,
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print(
+                    "Messages for several recipients are decrypted with any key pair that is one of them."
+                ),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print(
+                    "Hex, base64, base64url, base32, base58, decimal and armor are recognized, Tab starts a new line."
+                ),
+            )?;
+        }
 
         w.flush()?;
 
         match read_char()? {
             KeyCode::Up => {
-                if storage.get_key_pairs().len() == 0 {
+                if entries.is_empty() {
                     continue;
                 } else if index > 0 {
                     index -= 1;
                 } else {
-                    index = storage.get_key_pairs().len() - 1;
+                    index = entries.len() - 1;
                 }
             }
             KeyCode::Down => {
-                if storage.get_key_pairs().len() == 0 {
+                if entries.is_empty() {
                     continue;
-                } else if index < storage.get_key_pairs().len() - 1 {
+                } else if index < entries.len() - 1 {
                     index += 1;
                 } else {
                     index = 0;
                 }
             }
+            KeyCode::Char(' ') if procedure == EncryptOrDecrypt::Encrypt => {
+                if entries.is_empty() {
+                    continue;
+                } else if let Some(position) = marked.iter().position(|id| *id == index) {
+                    marked.remove(position);
+                } else {
+                    marked.push(index);
+                }
+            }
             KeyCode::Enter => {
-                if storage.get_key_pairs().len() == 0 {
+                if entries.is_empty() {
                     continue;
                 } else {
-                    let entered_message = run_enter_message(w, &procedure)?;
+                    let entered_message = run_enter_message(w, &procedure, "Enter your message: ")?;
 
                     match entered_message.state {
                         EncryptWidgetState::Back => {
                             continue;
                         }
                         EncryptWidgetState::Continue => {
-                            let key_pair = storage.get_key_pair_by_id(index).unwrap();
-
-                            let result = match procedure {
-                                EncryptOrDecrypt::Encrypt => encrypt(
-                                    &entered_message.text.unwrap(),
-                                    &key_pair.modulus,
-                                    &key_pair.e,
-                                ),
-                                EncryptOrDecrypt::Decrypt => decrypt(
-                                    &entered_message.text.unwrap(),
-                                    &key_pair.modulus,
-                                    &key_pair.d,
-                                )
-                                .unwrap(),
+                            let entry = &entries[index];
+                            let (modulus, e) = entry.public_key();
+                            let message = entered_message.text.unwrap();
+
+                            if procedure == EncryptOrDecrypt::Encrypt {
+                                let print_result =
+                                    match encrypt_to(&message, &marked, &entries, index) {
+                                        Ok(ciphertext) => run_print_ciphertext(w, &ciphertext)?,
+                                        Err(error) => run_print_result(
+                                            w,
+                                            &format!("Could not encrypt: {}", error),
+                                        )?,
+                                    };
+                                if print_result == EncryptWidgetState::End {
+                                    break;
+                                }
+                                continue;
+                            }
+
+                            let result = match (&procedure, entry) {
+                                (EncryptOrDecrypt::Decrypt, KeyEntry::KeyPair(key_pair)) => {
+                                    match encoding::decode_auto(&message) {
+                                        Ok((data, _)) if envelope::is_envelope(&data) => {
+                                            open_envelope(&data, &storage)
+                                        }
+                                        Ok((data, _)) => decrypt_crt(&data, key_pair)
+                                            .unwrap_or_else(|error| {
+                                                format!("Could not decrypt: {}", error)
+                                            }),
+                                        Err(error) => format!("Could not decrypt: {}", error),
+                                    }
+                                }
+                                (EncryptOrDecrypt::Sign, KeyEntry::KeyPair(key_pair)) => {
+                                    sign(&message, modulus, e, &key_pair.d)
+                                }
+                                // Encryption is handled above, contacts are only listed for
+                                // encryption and verification
+                                (
+                                    EncryptOrDecrypt::Encrypt
+                                    | EncryptOrDecrypt::Decrypt
+                                    | EncryptOrDecrypt::Sign,
+                                    _,
+                                ) => {
+                                    continue;
+                                }
+                                (EncryptOrDecrypt::Verify, _) => {
+                                    let entered_signature =
+                                        run_enter_message(w, &procedure, "Enter the signature: ")?;
+                                    if entered_signature.state == EncryptWidgetState::Back {
+                                        continue;
+                                    }
+
+                                    if verify(
+                                        &message,
+                                        &entered_signature.text.unwrap(),
+                                        modulus,
+                                        e,
+                                    ) {
+                                        "Signature is valid.".to_string()
+                                    } else {
+                                        "Signature is NOT valid.".to_string()
+                                    }
+                                }
                             };
 
                             let print_result = run_print_result(w, &result)?;
 
-                            match print_result {
-                                EncryptWidgetState::End => {
-                                    break;
-                                }
-                                _ => {}
+                            if print_result == EncryptWidgetState::End {
+                                break;
                             }
                         }
                         EncryptWidgetState::End => {
@@ -170,9 +288,41 @@ where
     Ok(())
 }
 
+// Several marked entries get a message for several recipients, otherwise the marked or the
+// highlighted entry is used
+fn encrypt_to(
+    message: &str,
+    marked: &[usize],
+    entries: &[KeyEntry],
+    index: usize,
+) -> Result<Vec<u8>, String> {
+    if marked.len() > 1 {
+        let recipients: Vec<(&BigUint, &BigUint)> =
+            marked.iter().map(|id| entries[*id].public_key()).collect();
+        return envelope::seal(message.as_bytes(), &recipients);
+    }
+    let (modulus, e) = entries[*marked.first().unwrap_or(&index)].public_key();
+    encrypt(message, modulus, e)
+}
+
+// Every key pair of the storage is tried, not only the chosen one
+fn open_envelope(data: &[u8], storage: &Storage) -> String {
+    let key_pairs: Vec<&KeyPair> = storage.key_pairs().collect();
+    let opened = envelope::parse(data).and_then(|envelope| envelope.open(&key_pairs));
+    match opened {
+        Ok((message, key_pair)) => format!(
+            "{} (decrypted with {})",
+            String::from_utf8_lossy(&message),
+            key_pair.name
+        ),
+        Err(error) => format!("Could not decrypt: {}", error),
+    }
+}
+
 pub fn run_enter_message<W>(
     w: &mut W,
     procedure: &EncryptOrDecrypt,
+    prompt: &str,
 ) -> crossterm::Result<TextResult>
 where
     W: Write,
@@ -185,14 +335,10 @@ where
             style::ResetColor,
             terminal::Clear(crossterm::terminal::ClearType::All),
             cursor::MoveTo(2, 2),
-            if *procedure == EncryptOrDecrypt::Encrypt {
-                style::Print("ENCRYPT")
-            } else {
-                style::Print("DECRYPT")
-            },
+            style::Print(procedure.title()),
             cursor::MoveToNextLine(2),
             cursor::MoveRight(2),
-            style::Print(r#"Enter your message: "#),
+            style::Print(prompt),
             cursor::MoveToNextLine(1),
             cursor::MoveRight(2)
         )?;
@@ -210,12 +356,11 @@ where
             w,
             cursor::MoveToNextLine(2),
             cursor::MoveRight(2),
-            if *procedure == EncryptOrDecrypt::Encrypt {
-                style::Print("Press Enter button to encrypt your message.")
-            } else {
-                style::Print("Press Enter button to decrypt your message.")
-            },
-            if tmp_input_line.len() > 0 {
+            style::Print(format!(
+                "Press Enter button to {} your message.",
+                procedure.verb()
+            )),
+            if !tmp_input_line.is_empty() {
                 cursor::MoveTo(2, tmp_input_line.lines().count() as u16 + 5)
             } else {
                 cursor::MoveTo(2, 5)
@@ -226,7 +371,7 @@ where
 
         match read_char()? {
             KeyCode::Enter => {
-                if tmp_input_line.len() == 0 {
+                if tmp_input_line.is_empty() {
                     continue;
                 } else {
                     return Ok(TextResult {
@@ -236,7 +381,7 @@ where
                 }
             }
             KeyCode::Tab => {
-                if tmp_input_line.len() == 0 {
+                if tmp_input_line.is_empty() {
                     continue;
                 } else {
                     tmp_input_line.push('\n');
@@ -248,10 +393,8 @@ where
                     state: EncryptWidgetState::Back,
                 });
             }
-            KeyCode::Backspace => {
-                if tmp_input_line.len() > 0 {
-                    tmp_input_line.pop();
-                }
+            KeyCode::Backspace if !tmp_input_line.is_empty() => {
+                tmp_input_line.pop();
             }
             KeyCode::Char(c) => {
                 tmp_input_line.push(c);
@@ -295,3 +438,49 @@ where
         };
     }
 }
+
+// Tab shows the ciphertext in the next encoding
+pub fn run_print_ciphertext<W>(
+    w: &mut W,
+    ciphertext: &[u8],
+) -> crossterm::Result<EncryptWidgetState>
+where
+    W: Write,
+{
+    let mut encoding = Encoding::Hex;
+    crossterm::queue!(w, cursor::Hide, cursor::DisableBlinking)?;
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::MoveTo(2, 2),
+            style::Print("RESULT"),
+            cursor::MoveToNextLine(2),
+            style::Print(format!("Your result ({}):", encoding.name())),
+            cursor::MoveToNextLine(1),
+        )?;
+        // Armor has several lines, which need a new line in raw mode
+        for line in encoding.encode(ciphertext).lines() {
+            crossterm::queue!(w, style::Print(line), cursor::MoveToNextLine(1))?;
+        }
+        crossterm::queue!(
+            w,
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Tab to show the ciphertext in another encoding."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter button to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Tab => encoding = encoding.next(),
+            KeyCode::Enter => return Ok(EncryptWidgetState::End),
+            KeyCode::Esc => return Ok(EncryptWidgetState::End),
+            _ => {}
+        };
+    }
+}
@@ -1,21 +1,35 @@
 use std::io::Write;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
 use crossterm::cursor;
 use crossterm::event::{Event, KeyCode};
 use crossterm::style;
 use crossterm::terminal;
+use num_bigint_dig::BigUint;
 
+use crate::fips186::{self, PrimeKind};
 use crate::key_generator;
-use crate::key_storage::{KeyPair, Storage};
-use crate::widgets::utils::read_char;
+use crate::key_storage::{self, KeyPair, Storage};
+use crate::prime_search::SearchProgress;
+use crate::widgets::key_details_widget;
+use crate::widgets::utils::{read_char, run_print_error};
+
+const SEED_PROMPT: &str = "Seed for a reproducible key (optional): ";
 
 pub fn run<W>(w: &mut W) -> crossterm::Result<()>
 where
     W: Write,
 {
     let mut tmp_input_line: String = String::new();
+    let mut seed_input_line: String = String::new();
+    let mut seed_field_active: bool = false;
     let mut key_size: i64 = -1;
+    let mut weak_rng: bool = false;
+    let mut prime_count: usize = 2;
+    // None is the student generator, Some(kind) follows FIPS 186-5 A.1
+    let mut fips_kind: Option<PrimeKind> = None;
 
     loop {
         crossterm::queue!(
@@ -38,18 +52,74 @@ where
             crossterm::queue!(
                 w,
                 style::Print(format!(r#"Enter key size (1024): {}"#, &tmp_input_line)),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print(format!(r#"{}{}"#, SEED_PROMPT, &seed_input_line)),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::SetForegroundColor(style::Color::Red),
+                style::Print(
+                    "WARNING: anyone who knows the seed can regenerate the private key. Use seeded keys for exercises only!"
+                ),
+                style::ResetColor,
                 cursor::MoveToNextLine(2),
                 cursor::MoveRight(2),
                 style::Print("Press Enter to generate key or ESC to cancel."),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print("Press Up/Down to switch between key size and seed."),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print(format!(
+                    "Press Tab to toggle weak RNG (DEMO ONLY, keys may share primes): {}",
+                    if weak_rng { "on" } else { "off" }
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print(format!(
+                    "Press Left/Right to change the number of primes (2-{}): {}",
+                    key_generator::MAX_PRIMES,
+                    prime_count
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+                style::Print(format!(
+                    "Press F2 to switch the generation mode: {}",
+                    match fips_kind {
+                        None => "student",
+                        Some(PrimeKind::Probable) => "FIPS 186-5 probable primes",
+                        Some(PrimeKind::Provable) => "FIPS 186-5 provable primes",
+                    }
+                )),
+            )?;
+            if fips_kind.is_some() {
+                crossterm::queue!(
+                    w,
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(
+                        "FIPS 186-5 mode always uses two primes and e = 65537, the seed and weak RNG are ignored."
+                    ),
+                )?;
+            }
+        }
+        if seed_field_active {
+            crossterm::queue!(
+                w,
+                cursor::MoveTo(
+                    format!(r#"{}{}"#, SEED_PROMPT, &seed_input_line).len() as u16 + 2,
+                    5,
+                )
+            )?;
+        } else {
+            crossterm::queue!(
+                w,
+                cursor::MoveTo(
+                    format!(r#"Enter key size (1024): {}"#, &tmp_input_line).len() as u16 + 2,
+                    4,
+                )
             )?;
         }
-        crossterm::queue!(
-            w,
-            cursor::MoveTo(
-                format!(r#"Enter key size (1024): {}"#, &tmp_input_line).len() as u16 + 2,
-                4,
-            )
-        )?;
 
         w.flush()?;
 
@@ -74,43 +144,178 @@ where
                     }
                 }
                 KeyCode::Backspace => {
-                    tmp_input_line.pop();
+                    if seed_field_active {
+                        seed_input_line.pop();
+                    } else {
+                        tmp_input_line.pop();
+                    }
+                }
+                KeyCode::Up | KeyCode::Down => {
+                    seed_field_active = !seed_field_active;
+                }
+                KeyCode::Tab => {
+                    weak_rng = !weak_rng;
+                }
+                KeyCode::F(2) => {
+                    fips_kind = match fips_kind {
+                        None => Some(PrimeKind::Probable),
+                        Some(PrimeKind::Probable) => Some(PrimeKind::Provable),
+                        Some(PrimeKind::Provable) => None,
+                    };
+                }
+                KeyCode::Left if prime_count > 2 => {
+                    prime_count -= 1;
+                }
+                KeyCode::Right if prime_count < key_generator::MAX_PRIMES => {
+                    prime_count += 1;
                 }
                 KeyCode::Esc => {
                     return Ok(());
                 }
                 KeyCode::Char(c) => {
-                    tmp_input_line.push(c);
+                    if seed_field_active {
+                        seed_input_line.push(c);
+                    } else {
+                        tmp_input_line.push(c);
+                    }
                 }
                 _ => {}
             }
         }
     }
 
-    crossterm::queue!(
-        w,
-        style::ResetColor,
-        terminal::Clear(crossterm::terminal::ClearType::All),
-        cursor::Hide,
-        cursor::MoveTo(2, 2),
-        style::Print(format!(r#"Selected key size: {}"#, key_size)),
-        cursor::MoveToNextLine(2),
-        cursor::MoveRight(2),
-        style::Print("Generating key pair"),
-    )?;
-
-    let thread_join_handle =
-        std::thread::spawn(move || key_generator::generate_key_pair(key_size as usize));
+    let seed_is_empty = seed_input_line.is_empty();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (progress_sender, progress_receiver) = mpsc::channel::<SearchProgress>();
+
+    let thread_cancel = Arc::clone(&cancel);
+    let thread_join_handle = std::thread::spawn(move || {
+        if let Some(kind) = fips_kind {
+            let e = BigUint::from(key_generator::EXPONENT);
+            // A.1 asks to start over whenever a step fails
+            while !thread_cancel.load(Ordering::Relaxed) {
+                if let Ok(key_pair) =
+                    fips186::generate_key_pair(key_size as usize * 2, &e, kind, &thread_cancel)
+                {
+                    return Some(key_pair);
+                }
+            }
+            None
+        } else if !seed_input_line.is_empty() {
+            let mut rng = key_generator::seeded_rng(&seed_input_line);
+            key_generator::generate_multi_prime_key_pair_with_rng(
+                key_size as usize * 2,
+                prime_count,
+                &mut rng,
+                &thread_cancel,
+            )
+        } else if weak_rng {
+            key_generator::generate_weak_key_pair(key_size as usize, &thread_cancel)
+        } else {
+            // Same modulus size as a two-prime key, split between `prime_count` primes
+            key_generator::generate_key_pair_parallel(
+                key_size as usize * 2,
+                prime_count,
+                &thread_cancel,
+                &progress_sender,
+            )
+        }
+    });
+
+    let started = Instant::now();
+    let mut progress: Option<SearchProgress> = None;
 
     while !thread_join_handle.is_finished() {
-        crossterm::queue!(w, crossterm::style::Print("."),)?;
+        if let Some(latest) = progress_receiver.try_iter().last() {
+            progress = Some(latest);
+        }
+
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print(format!(
+                r#"Selected key size: {}, primes: {}"#,
+                key_size, prime_count
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "Generating key pair... {:.1} s",
+                started.elapsed().as_secs_f64()
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        match progress {
+            Some(progress) => {
+                crossterm::queue!(
+                    w,
+                    style::Print(format!("Worker threads:      {}", progress.workers)),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!(
+                        "Primes found:        {}/{}",
+                        progress.primes_found, progress.primes_needed
+                    )),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!("Candidates tried:    {}", progress.candidates)),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!("Sieve hits:          {}", progress.sieve_hits)),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!(
+                        "Miller-Rabin rounds: {}",
+                        progress.miller_rabin_rounds
+                    )),
+                    cursor::MoveToNextLine(2),
+                    cursor::MoveRight(2),
+                )?;
+            }
+            None if fips_kind.is_some() || !seed_is_empty || weak_rng => {
+                crossterm::queue!(
+                    w,
+                    style::Print("No live statistics for this generation mode."),
+                    cursor::MoveToNextLine(2),
+                    cursor::MoveRight(2),
+                )?;
+            }
+            None => {}
+        }
+
+        crossterm::queue!(w, style::Print("Press ESC to cancel."))?;
 
         w.flush()?;
 
-        std::thread::sleep(Duration::from_millis(200));
+        if crossterm::event::poll(Duration::from_millis(200))? {
+            if let Event::Key(key_event) = crossterm::event::read()? {
+                if key_event.code == KeyCode::Esc {
+                    crossterm::queue!(
+                        w,
+                        cursor::MoveToNextLine(2),
+                        cursor::MoveRight(2),
+                        style::Print("Cancelling..."),
+                    )?;
+                    w.flush()?;
+
+                    // Every generation mode checks the flag before the next prime candidate
+                    cancel.store(true, Ordering::Relaxed);
+                    let _ = thread_join_handle.join();
+                    return Ok(());
+                }
+            }
+        }
     }
 
-    let key_pair: KeyPair = thread_join_handle.join().unwrap();
+    let key_pair: KeyPair = match thread_join_handle.join().unwrap() {
+        Some(key_pair) => key_pair,
+        None => return Ok(()),
+    };
 
     loop {
         crossterm::queue!(
@@ -119,36 +324,59 @@ where
             cursor::Hide,
             cursor::MoveTo(2, 2),
             style::Print(format!(r#"Generated key pair with size: {}"#, key_size)),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!(r#"Method: {}"#, key_pair.generation_method.name())),
             cursor::MoveToNextLine(2),
             cursor::MoveRight(2),
             style::Print(format!(
                 r#"p: {}..."#,
-                key_pair.p.to_string()[..key_pair.p.to_string().len() / 8].to_string()
+                &key_pair.p.to_string()[..key_pair.p.to_string().len() / 8]
             )),
             cursor::MoveToNextLine(1),
             cursor::MoveRight(2),
             style::Print(format!(
                 r#"q: {}..."#,
-                key_pair.q.to_string()[..key_pair.q.to_string().len() / 8].to_string()
+                &key_pair.q.to_string()[..key_pair.q.to_string().len() / 8]
             )),
             cursor::MoveToNextLine(1),
             cursor::MoveRight(2),
+        )?;
+
+        for (i, info) in key_pair.other_primes.iter().enumerate() {
+            crossterm::queue!(
+                w,
+                style::Print(format!(
+                    r#"r{}: {}..."#,
+                    i + 3,
+                    &info.prime.to_string()[..info.prime.to_string().len() / 8]
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
             style::Print(format!(
                 r#"n: {}..."#,
-                key_pair.modulus.to_string()[..key_pair.modulus.to_string().len() / 8].to_string()
+                &key_pair.modulus.to_string()[..key_pair.modulus.to_string().len() / 8]
             )),
             cursor::MoveToNextLine(1),
             cursor::MoveRight(2),
-            style::Print(format!(r#"e: {}"#, key_pair.e.to_string())),
+            style::Print(format!(r#"e: {}"#, key_pair.e)),
             cursor::MoveToNextLine(1),
             cursor::MoveRight(2),
             style::Print(format!(
                 r#"d: {}..."#,
-                key_pair.d.to_string()[..key_pair.d.to_string().len() / 8].to_string()
+                &key_pair.d.to_string()[..key_pair.d.to_string().len() / 8]
             )),
             cursor::MoveToNextLine(2),
             cursor::MoveRight(2),
             style::Print("Save key pair? (y/n)"),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press 'v' to view every parameter in full."),
         )?;
 
         w.flush()?;
@@ -161,6 +389,9 @@ where
             KeyCode::Char('n') => {
                 break;
             }
+            KeyCode::Char('v') => {
+                key_details_widget::run_key_pair(w, &key_pair)?;
+            }
             _ => {}
         }
     }
@@ -169,6 +400,40 @@ where
 }
 
 fn run_choose_key_pair_name<W>(w: &mut W, mut key_pair: KeyPair) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_error(w, "KEY GENERATOR", &error),
+    };
+    let default_name = format!("key_{}", storage.get_key_pairs().len());
+
+    key_pair.name = match run_enter_text(w, "Enter key name", &default_name)? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    key_pair.comment = match run_enter_text(w, "Enter a comment", "")? {
+        Some(comment) => comment,
+        None => return Ok(()),
+    };
+    key_pair.tags = match run_enter_text(w, "Enter tags separated by commas", "")? {
+        Some(tags) => key_storage::parse_tags(&tags),
+        None => return Ok(()),
+    };
+
+    match Storage::update(|storage| storage.add_key_pair(key_pair)) {
+        Ok(_) => Ok(()),
+        Err(error) => run_print_error(w, "KEY GENERATOR", &error),
+    }
+}
+
+// Returns `default_value` when Enter is pressed on an empty line and None on ESC
+fn run_enter_text<W>(
+    w: &mut W,
+    prompt: &str,
+    default_value: &str,
+) -> crossterm::Result<Option<String>>
 where
     W: Write,
 {
@@ -180,7 +445,13 @@ where
             terminal::Clear(crossterm::terminal::ClearType::All),
             cursor::Hide,
             cursor::MoveTo(2, 2),
-            style::Print(format!(r#"Enter key name (default): {}"#, tmp_input_line)),
+            style::Print(format!(
+                r#"{} ({}): {}"#,
+                prompt, default_value, tmp_input_line
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to confirm or ESC to cancel saving."),
         )?;
 
         w.flush()?;
@@ -188,15 +459,17 @@ where
         if let Event::Key(key_event) = crossterm::event::read()? {
             match key_event.code {
                 KeyCode::Enter => {
-                    key_pair.name = tmp_input_line.clone();
-                    let mut storage = Storage::load_storage();
-                    storage.add_key_pair(key_pair);
-                    storage.save_storage();
-                    break;
+                    if tmp_input_line.is_empty() {
+                        return Ok(Some(default_value.to_string()));
+                    }
+                    return Ok(Some(tmp_input_line));
                 }
                 KeyCode::Backspace => {
                     tmp_input_line.pop();
                 }
+                KeyCode::Esc => {
+                    return Ok(None);
+                }
                 KeyCode::Char(c) => {
                     tmp_input_line.push(c);
                 }
@@ -204,6 +477,4 @@ where
             }
         }
     }
-
-    Ok(())
 }
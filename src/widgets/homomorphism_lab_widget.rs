@@ -0,0 +1,339 @@
+use std::io::Write;
+
+use crossterm::cursor;
+use crossterm::event::{Event, KeyCode};
+use crossterm::style;
+use crossterm::terminal;
+use num_bigint_dig::BigUint;
+use num_traits::Num;
+
+use crate::homomorphism::{self, BlindingAttack, DecryptionOracle, HomomorphismDemo};
+use crate::key_storage::{KeyPair, Storage};
+use crate::widgets::utils::{read_char, run_print_error};
+
+pub fn run<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let mut index: usize = 0;
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_error(w, "HOMOMORPHISM LAB", &error),
+    };
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            cursor::DisableBlinking,
+            style::Print("HOMOMORPHISM LAB"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Choose a key pair for the experiment:"),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+        )?;
+
+        if storage.get_key_pairs().is_empty() {
+            crossterm::queue!(
+                w,
+                style::Print("No key pairs found. Please create a new key pair."),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        } else {
+            for (id, key_pair) in storage.key_pairs().enumerate() {
+                if id == index {
+                    crossterm::queue!(
+                        w,
+                        style::SetForegroundColor(style::Color::Black),
+                        style::SetBackgroundColor(style::Color::White),
+                        style::SetAttribute(style::Attribute::Bold),
+                    )?;
+                } else {
+                    crossterm::queue!(
+                        w,
+                        style::SetForegroundColor(style::Color::Reset),
+                        style::SetBackgroundColor(style::Color::Reset),
+                        style::SetAttribute(style::Attribute::Reset),
+                    )?;
+                }
+
+                crossterm::queue!(
+                    w,
+                    style::Print(format!("{}: {}", id, key_pair.name)),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2)
+                )?;
+            }
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Use arrow keys to navigate."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter button to select key or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => {
+                if storage.get_key_pairs().is_empty() {
+                    continue;
+                } else if index > 0 {
+                    index -= 1;
+                } else {
+                    index = storage.get_key_pairs().len() - 1;
+                }
+            }
+            KeyCode::Down => {
+                if storage.get_key_pairs().is_empty() {
+                    continue;
+                } else if index < storage.get_key_pairs().len() - 1 {
+                    index += 1;
+                } else {
+                    index = 0;
+                }
+            }
+            KeyCode::Enter => {
+                if storage.get_key_pairs().is_empty() {
+                    continue;
+                }
+                let key_pair = storage.get_key_pair_by_id(index).unwrap();
+
+                let a = match run_enter_number(w, "Enter first number a")? {
+                    Some(a) => a,
+                    None => continue,
+                };
+                let b = match run_enter_number(w, "Enter second number b")? {
+                    Some(b) => b,
+                    None => continue,
+                };
+
+                let demo = homomorphism::demonstrate_homomorphism(&a, &b, key_pair);
+                run_print_demo(w, key_pair, &demo)?;
+            }
+            KeyCode::Esc => {
+                return Ok(());
+            }
+            _ => {}
+        };
+    }
+}
+
+fn run_enter_number<W>(w: &mut W, prompt: &str) -> crossterm::Result<Option<BigUint>>
+where
+    W: Write,
+{
+    let mut tmp_input_line: String = String::new();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::MoveTo(2, 2),
+            style::Print("HOMOMORPHISM LAB"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!("{}: {}", prompt, tmp_input_line)),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to continue or ESC to cancel."),
+        )?;
+
+        w.flush()?;
+
+        if let Event::Key(key_event) = crossterm::event::read()? {
+            match key_event.code {
+                KeyCode::Enter => match BigUint::from_str_radix(&tmp_input_line, 10) {
+                    Ok(number) => return Ok(Some(number)),
+                    Err(_) => tmp_input_line.clear(),
+                },
+                KeyCode::Backspace => {
+                    tmp_input_line.pop();
+                }
+                KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    tmp_input_line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn run_print_demo<W>(
+    w: &mut W,
+    key_pair: &KeyPair,
+    demo: &HomomorphismDemo,
+) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("HOMOMORPHISM LAB"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!("a: {}", demo.a)),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!("b: {}", demo.b)),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!("E(a): {}", shorten(&demo.encrypted_a))),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!("E(b): {}", shorten(&demo.encrypted_b))),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "E(a) * E(b) mod n: {}",
+                shorten(&demo.encrypted_product)
+            )),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!("D(E(a) * E(b)): {}", demo.decrypted_product)),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!("a * b mod n: {}", demo.expected_product)),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            if demo.decrypted_product == demo.expected_product {
+                style::Print("E(a) * E(b) = E(a * b): the ciphertexts were multiplied without the private key.")
+            } else {
+                style::Print("The decrypted product does not match a * b mod n.")
+            },
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press 'c' to run the chosen-ciphertext blinding attack on E(a)."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Char('c') => {
+                let oracle = DecryptionOracle::new(key_pair, demo.encrypted_a.clone());
+                let attack = homomorphism::blinding_attack(
+                    &demo.encrypted_a,
+                    &key_pair.modulus,
+                    &key_pair.e,
+                    &oracle,
+                );
+                run_print_blinding_attack(w, &oracle, attack.as_ref())?;
+            }
+            KeyCode::Enter | KeyCode::Esc => return Ok(()),
+            _ => {}
+        };
+    }
+}
+
+fn run_print_blinding_attack<W>(
+    w: &mut W,
+    oracle: &DecryptionOracle,
+    attack: Option<&BlindingAttack>,
+) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("CHOSEN-CIPHERTEXT BLINDING ATTACK"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        match attack {
+            Some(attack) => {
+                crossterm::queue!(
+                    w,
+                    style::Print(format!("Target ciphertext c: {}", shorten(&attack.target))),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!(
+                        "Oracle on c: {}",
+                        match oracle.decrypt(&attack.target) {
+                            Some(_) => "decrypted",
+                            None => "refused",
+                        }
+                    )),
+                    cursor::MoveToNextLine(2),
+                    cursor::MoveRight(2),
+                    style::Print(format!("Random r: {}", shorten(&attack.r))),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!(
+                        "Blinded ciphertext c * r^e mod n: {}",
+                        shorten(&attack.blinded_ciphertext)
+                    )),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!(
+                        "Oracle on blinded ciphertext (m * r mod n): {}",
+                        shorten(&attack.blinded_plaintext)
+                    )),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2),
+                    style::Print(format!(
+                        "Recovered m = m * r * r^-1 mod n: {}",
+                        attack.recovered
+                    )),
+                    cursor::MoveToNextLine(2),
+                    cursor::MoveRight(2),
+                )?;
+            }
+            None => {
+                crossterm::queue!(
+                    w,
+                    style::Print("The oracle refused every blinded ciphertext."),
+                    cursor::MoveToNextLine(2),
+                    cursor::MoveRight(2),
+                )?;
+            }
+        }
+
+        crossterm::queue!(w, style::Print("Press Enter or ESC to go back."))?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Enter | KeyCode::Esc => return Ok(()),
+            _ => {}
+        };
+    }
+}
+
+fn shorten(number: &BigUint) -> String {
+    let number = number.to_string();
+    if number.len() > 64 {
+        format!("{}...", &number[..64])
+    } else {
+        number
+    }
+}
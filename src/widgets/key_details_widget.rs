@@ -0,0 +1,334 @@
+use std::io::Write;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::cursor;
+use crossterm::event::{Event, KeyCode};
+use crossterm::style;
+use crossterm::terminal;
+use num_bigint_dig::BigUint;
+
+use crate::fingerprint;
+use crate::key_storage::{Contact, KeyPair};
+use crate::widgets::utils::read_char;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ValueFormat {
+    Decimal,
+    Hex,
+    Base64,
+}
+
+impl ValueFormat {
+    const ALL: [ValueFormat; 3] = [ValueFormat::Decimal, ValueFormat::Hex, ValueFormat::Base64];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ValueFormat::Decimal => "dec",
+            ValueFormat::Hex => "hex",
+            ValueFormat::Base64 => "b64",
+        }
+    }
+
+    fn format(&self, value: &BigUint) -> String {
+        match self {
+            ValueFormat::Decimal => value.to_str_radix(10),
+            ValueFormat::Hex => value.to_str_radix(16),
+            ValueFormat::Base64 => STANDARD.encode(value.to_bytes_be()),
+        }
+    }
+}
+
+pub fn run_key_pair<W>(w: &mut W, key_pair: &KeyPair) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let metadata = vec![
+        ("Name", key_pair.name.clone()),
+        ("Created", key_pair.created_at_text()),
+        ("Bit length", key_pair.bit_length().to_string()),
+        ("Primes", key_pair.primes().len().to_string()),
+        ("Method", key_pair.generation_method.name().to_string()),
+        ("Comment", key_pair.comment.clone()),
+        ("Tags", key_pair.tags.join(", ")),
+        ("Fingerprint", key_pair.fingerprint()),
+        ("OpenSSH", key_pair.openssh_fingerprint()),
+    ];
+    let randomart = fingerprint::openssh_randomart(&key_pair.modulus, &key_pair.e);
+
+    let mut values = vec![
+        ("n (modulus)".to_string(), key_pair.modulus.clone()),
+        ("e (public exponent)".to_string(), key_pair.e.clone()),
+        (
+            "d (private exponent)".to_string(),
+            key_pair.d.to_biguint().unwrap(),
+        ),
+        ("p".to_string(), key_pair.p.clone()),
+        ("q".to_string(), key_pair.q.clone()),
+        ("dP = d mod (p - 1)".to_string(), key_pair.dp()),
+        ("dQ = d mod (q - 1)".to_string(), key_pair.dq()),
+    ];
+    if let Some(qinv) = key_pair.qinv() {
+        values.push(("qInv = q^-1 mod p".to_string(), qinv));
+    }
+    for (i, info) in key_pair.other_primes.iter().enumerate() {
+        values.push((format!("r{}", i + 3), info.prime.clone()));
+        values.push((
+            format!("d{} = d mod (r{} - 1)", i + 3, i + 3),
+            info.exponent.clone(),
+        ));
+        values.push((
+            format!("t{} (CRT coefficient)", i + 3),
+            info.coefficient.clone(),
+        ));
+    }
+
+    run(w, &metadata, &randomart, &values)
+}
+
+pub fn run_contact<W>(w: &mut W, contact: &Contact) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let metadata = vec![
+        ("Name", contact.name.clone()),
+        ("Bit length", contact.modulus.bits().to_string()),
+        ("Fingerprint", contact.fingerprint.clone()),
+        ("OpenSSH", contact.openssh_fingerprint()),
+    ];
+    let randomart = fingerprint::openssh_randomart(&contact.modulus, &contact.e);
+    let values = vec![
+        ("n (modulus)".to_string(), contact.modulus.clone()),
+        ("e (public exponent)".to_string(), contact.e.clone()),
+    ];
+
+    run(w, &metadata, &randomart, &values)
+}
+
+fn run<W>(
+    w: &mut W,
+    metadata: &[(&str, String)],
+    randomart: &[String],
+    values: &[(String, BigUint)],
+) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let mut selected: usize = 0;
+    let mut format_index: usize = 0;
+    // Lines scrolled with PageUp/PageDown from where the selected value is shown, for
+    // values longer than the screen and to get back to the metadata
+    let mut scroll: isize = 0;
+    let mut status: String = String::new();
+
+    loop {
+        let (columns, rows) = terminal::size()?;
+        let width = (columns as usize).saturating_sub(4).max(16);
+        // Title, footer and the blank lines around them
+        let visible_rows = (rows as usize).saturating_sub(10).max(1);
+
+        // Every line of the screen with the index of the value it belongs to
+        let mut lines: Vec<(String, Option<usize>)> = Vec::new();
+        for (label, value) in metadata.iter() {
+            lines.push((format!("{:<12} {}", format!("{}:", label), value), None));
+        }
+        // Picture of the OpenSSH fingerprint, quicker to compare than the digits
+        lines.push((String::new(), None));
+        for line in randomart.iter() {
+            lines.push((line.clone(), None));
+        }
+        for (index, (label, value)) in values.iter().enumerate() {
+            lines.push((String::new(), None));
+            lines.push((format!("{}:", label), Some(index)));
+            for format in ValueFormat::ALL.iter() {
+                let prefix = format!("  {}: ", format.name());
+                let text = format.format(value);
+                let chunk_length = width.saturating_sub(prefix.len()).max(8);
+                for (i, chunk) in text.as_bytes().chunks(chunk_length).enumerate() {
+                    let chunk = std::str::from_utf8(chunk).unwrap();
+                    if i == 0 {
+                        lines.push((format!("{}{}", prefix, chunk), Some(index)));
+                    } else {
+                        lines.push((
+                            format!("{:width$}{}", "", chunk, width = prefix.len()),
+                            Some(index),
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Scroll so that the header of the selected value and as much of it as fits is shown
+        let first_line = lines
+            .iter()
+            .position(|(_, index)| *index == Some(selected))
+            .unwrap_or(0);
+        let last_line = lines
+            .iter()
+            .rposition(|(_, index)| *index == Some(selected))
+            .unwrap_or(0);
+        let offset = if last_line < visible_rows {
+            0
+        } else {
+            first_line.min(last_line + 1 - visible_rows)
+        };
+        let last_offset = lines.len().saturating_sub(visible_rows) as isize;
+        scroll = scroll.clamp(-(offset as isize), last_offset - offset as isize);
+        let offset = (offset as isize + scroll) as usize;
+
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("KEY DETAILS"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for (text, index) in lines.iter().skip(offset).take(visible_rows) {
+            if *index == Some(selected) {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                )?;
+            } else {
+                crossterm::queue!(w, style::ResetColor)?;
+            }
+            crossterm::queue!(
+                w,
+                style::Print(text),
+                style::ResetColor,
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            cursor::MoveTo(2, rows.saturating_sub(5)),
+            style::Print(&status),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "Use arrow keys to select a value, PageUp/PageDown to scroll. Tab changes the copy format: {}",
+                ValueFormat::ALL[format_index].name()
+            )),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(
+                "Press 'c' to copy the value to the clipboard (OSC 52), 'w' to write it to a file."
+            ),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up if selected > 0 => {
+                selected -= 1;
+                scroll = 0;
+            }
+            KeyCode::Down if selected + 1 < values.len() => {
+                selected += 1;
+                scroll = 0;
+            }
+            KeyCode::PageDown => {
+                scroll += visible_rows as isize;
+            }
+            KeyCode::PageUp => {
+                scroll -= visible_rows as isize;
+            }
+            KeyCode::Tab => {
+                format_index = (format_index + 1) % ValueFormat::ALL.len();
+            }
+            KeyCode::Char('c') => {
+                let (label, value) = &values[selected];
+                let format = ValueFormat::ALL[format_index];
+                copy_to_clipboard(w, &format.format(value))?;
+                status = format!("Copied {} ({}) to the clipboard.", label, format.name());
+            }
+            KeyCode::Char('w') => {
+                let (label, value) = &values[selected];
+                let format = ValueFormat::ALL[format_index];
+                let default_path = format!(
+                    "{}.{}.txt",
+                    label.split_whitespace().next().unwrap_or("value"),
+                    format.name()
+                );
+                if let Some(path) = run_enter_path(w, &default_path)? {
+                    status = match std::fs::write(&path, format.format(value) + "\n") {
+                        Ok(()) => format!("Wrote {} ({}) to {}.", label, format.name(), path),
+                        Err(error) => format!("Could not write {}: {}", path, error),
+                    };
+                }
+            }
+            KeyCode::Esc | KeyCode::Enter => return Ok(()),
+            _ => {}
+        };
+    }
+}
+
+// OSC 52 asks the terminal to put the base64 encoded text on the system clipboard,
+// which also works over SSH. Terminals that do not support it ignore the sequence
+fn copy_to_clipboard<W>(w: &mut W, text: &str) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    write!(w, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    w.flush()
+}
+
+fn run_enter_path<W>(w: &mut W, default_path: &str) -> crossterm::Result<Option<String>>
+where
+    W: Write,
+{
+    let mut tmp_input_line: String = String::new();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("KEY DETAILS"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "Write to file ({}): {}",
+                default_path, tmp_input_line
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to write or ESC to cancel."),
+        )?;
+
+        w.flush()?;
+
+        if let Event::Key(key_event) = crossterm::event::read()? {
+            match key_event.code {
+                KeyCode::Enter => {
+                    if tmp_input_line.is_empty() {
+                        return Ok(Some(default_path.to_string()));
+                    }
+                    return Ok(Some(tmp_input_line));
+                }
+                KeyCode::Backspace => {
+                    tmp_input_line.pop();
+                }
+                KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char(c) => {
+                    tmp_input_line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
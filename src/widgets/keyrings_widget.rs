@@ -0,0 +1,190 @@
+use std::io::Write;
+
+use crossterm::cursor;
+use crossterm::event::{Event, KeyCode};
+use crossterm::style;
+use crossterm::terminal;
+
+use crate::config;
+use crate::key_storage::Storage;
+use crate::widgets::utils::{read_char, run_print_error};
+
+pub fn run<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let settings = config::keyrings().and_then(|keyrings| {
+        let active = config::keyring()?;
+        Ok((keyrings, active, config::data_dir()?))
+    });
+    let (mut keyrings, mut active, data_dir) = match settings {
+        Ok(settings) => settings,
+        Err(error) => return run_print_error(w, "KEYRINGS", &error),
+    };
+    let mut index: usize = keyrings
+        .iter()
+        .position(|name| *name == active)
+        .unwrap_or(0);
+    let mut status: String = String::new();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            cursor::DisableBlinking,
+            style::Print("KEYRINGS"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!("Data directory: {}", data_dir.display())),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for (i, name) in keyrings.iter().enumerate() {
+            if i == index {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                    style::SetAttribute(style::Attribute::Bold),
+                )?;
+            } else {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::SetBackgroundColor(style::Color::Reset),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            crossterm::queue!(
+                w,
+                style::Print(format!(
+                    "{} {}",
+                    if *name == active { "*" } else { " " },
+                    name
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(&status),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Use arrow keys to navigate, Enter to open the selected keyring."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press 'n' to create a new keyring or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => {
+                if index > 0 {
+                    index -= 1;
+                } else {
+                    index = keyrings.len() - 1;
+                }
+            }
+            KeyCode::Down => {
+                if index < keyrings.len() - 1 {
+                    index += 1;
+                } else {
+                    index = 0;
+                }
+            }
+            KeyCode::Enter => {
+                let name = keyrings[index].clone();
+                status = match config::set_keyring(&name).and_then(|_| Storage::load_storage()) {
+                    Ok(storage) => format!(
+                        "Opened keyring \"{}\" ({} key pairs, {} contacts).",
+                        name,
+                        storage.get_key_pairs().len(),
+                        storage.get_contacts().len()
+                    ),
+                    Err(error) => format!("Error: {}", error),
+                };
+                active = name;
+            }
+            KeyCode::Char('n') => {
+                let name = match run_enter_name(w)? {
+                    Some(name) => name,
+                    None => continue,
+                };
+                match config::set_keyring(&name) {
+                    Ok(()) => {
+                        // Loading creates the empty keyring file
+                        let created = Storage::load_storage();
+                        if let Ok(names) = config::keyrings() {
+                            keyrings = names;
+                        }
+                        index = keyrings.iter().position(|n| *n == name).unwrap_or(0);
+                        status = match created {
+                            Ok(_) => format!("Created and opened keyring \"{}\".", name),
+                            Err(error) => format!("Error: {}", error),
+                        };
+                        active = name;
+                    }
+                    Err(error) => status = error,
+                }
+            }
+            KeyCode::Esc => return Ok(()),
+            _ => {}
+        };
+    }
+}
+
+fn run_enter_name<W>(w: &mut W) -> crossterm::Result<Option<String>>
+where
+    W: Write,
+{
+    let mut tmp_input_line: String = String::new();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("KEYRINGS"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!("New keyring name: {}", tmp_input_line)),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to create or ESC to cancel."),
+        )?;
+
+        w.flush()?;
+
+        if let Event::Key(key_event) = crossterm::event::read()? {
+            match key_event.code {
+                KeyCode::Enter if !tmp_input_line.is_empty() => {
+                    return Ok(Some(tmp_input_line));
+                }
+                KeyCode::Backspace => {
+                    tmp_input_line.pop();
+                }
+                KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char(c) => {
+                    tmp_input_line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -5,13 +5,27 @@ use crossterm::event::KeyCode;
 use crossterm::style;
 use crossterm::terminal;
 
+use crate::config;
 use crate::widgets::utils::{read_char, EncryptOrDecrypt};
-use crate::widgets::{browse_key_storage_widget, encrypt_decrypt_widget, generate_key_widget};
+use crate::widgets::{
+    browse_key_storage_widget, certificates_widget, encrypt_decrypt_widget, generate_key_widget,
+    homomorphism_lab_widget, keyrings_widget, scan_shared_primes_widget, settings_widget,
+    timing_lab_widget, token_widget,
+};
 
 const CONTROLS: &str = r#"'e' - Encrypt message
 'd' - Decrypt message
+'i' - Sign message
+'v' - Verify signature
 'g' - Generate key pairs and save to storage
 'b' - Browse key storage
+'k' - Switch keyring
+'s' - Scan keys for shared primes
+'h' - Homomorphism and malleability lab
+'t' - Timing side-channel lab
+'w' - JSON Web Tokens
+'c' - X.509 certificates
+'o' - Settings
 'q' - quit"#;
 
 pub fn run<W>(w: &mut W) -> crossterm::Result<()>
@@ -31,6 +45,12 @@ where
             style::Print(r#"MAIN MENU"#),
             cursor::MoveToNextLine(2),
             cursor::MoveRight(2),
+            style::Print(match config::keyring() {
+                Ok(keyring) => format!("Keyring: {}", keyring),
+                Err(error) => format!("Keyring: Error: {}", error),
+            }),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
             style::Print("Choose an option:"),
             cursor::MoveToNextLine(1),
             cursor::MoveRight(2),
@@ -85,8 +105,21 @@ where
             KeyCode::Char('d') => {
                 encrypt_decrypt_widget::run_choose_key_pair(w, EncryptOrDecrypt::Decrypt)?
             }
+            KeyCode::Char('i') => {
+                encrypt_decrypt_widget::run_choose_key_pair(w, EncryptOrDecrypt::Sign)?
+            }
+            KeyCode::Char('v') => {
+                encrypt_decrypt_widget::run_choose_key_pair(w, EncryptOrDecrypt::Verify)?
+            }
             KeyCode::Char('g') => generate_key_widget::run(w)?,
             KeyCode::Char('b') => browse_key_storage_widget::run(w)?,
+            KeyCode::Char('k') => keyrings_widget::run(w)?,
+            KeyCode::Char('s') => scan_shared_primes_widget::run(w)?,
+            KeyCode::Char('h') => homomorphism_lab_widget::run(w)?,
+            KeyCode::Char('t') => timing_lab_widget::run(w)?,
+            KeyCode::Char('w') => token_widget::run(w)?,
+            KeyCode::Char('c') => certificates_widget::run(w)?,
+            KeyCode::Char('o') => settings_widget::run(w)?,
             KeyCode::Up => {
                 if index > 0 {
                     index -= 1;
@@ -104,9 +137,18 @@ where
             KeyCode::Enter => match index {
                 0 => encrypt_decrypt_widget::run_choose_key_pair(w, EncryptOrDecrypt::Encrypt)?,
                 1 => encrypt_decrypt_widget::run_choose_key_pair(w, EncryptOrDecrypt::Decrypt)?,
-                2 => generate_key_widget::run(w)?,
-                3 => browse_key_storage_widget::run(w)?,
-                4 => break,
+                2 => encrypt_decrypt_widget::run_choose_key_pair(w, EncryptOrDecrypt::Sign)?,
+                3 => encrypt_decrypt_widget::run_choose_key_pair(w, EncryptOrDecrypt::Verify)?,
+                4 => generate_key_widget::run(w)?,
+                5 => browse_key_storage_widget::run(w)?,
+                6 => keyrings_widget::run(w)?,
+                7 => scan_shared_primes_widget::run(w)?,
+                8 => homomorphism_lab_widget::run(w)?,
+                9 => timing_lab_widget::run(w)?,
+                10 => token_widget::run(w)?,
+                11 => certificates_widget::run(w)?,
+                12 => settings_widget::run(w)?,
+                13 => break,
                 _ => {}
             },
             KeyCode::Esc | KeyCode::Char('q') => break,
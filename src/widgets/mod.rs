@@ -1,5 +1,13 @@
 pub(crate) mod browse_key_storage_widget;
+pub(crate) mod certificates_widget;
 pub(crate) mod encrypt_decrypt_widget;
 pub(crate) mod generate_key_widget;
-pub(crate) mod main_menu_widget;
+pub(crate) mod homomorphism_lab_widget;
+pub(crate) mod key_details_widget;
+pub(crate) mod keyrings_widget;
+pub mod main_menu_widget;
+pub(crate) mod scan_shared_primes_widget;
+pub(crate) mod settings_widget;
+pub(crate) mod timing_lab_widget;
+pub(crate) mod token_widget;
 pub(crate) mod utils;
@@ -0,0 +1,236 @@
+use std::io::Write;
+
+use crossterm::cursor;
+use crossterm::event::{Event, KeyCode};
+use crossterm::style;
+use crossterm::terminal;
+
+use crate::batch_gcd::{self, PublicKey, ScanResult};
+use crate::key_storage::Storage;
+use crate::widgets::utils::{self, read_char};
+
+pub fn run<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            cursor::DisableBlinking,
+            style::Print("SHARED PRIME SCAN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(
+                "Find public keys whose moduli share a prime and recover their private keys."
+            ),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("'s' - Scan key storage"),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(
+                "'f' - Scan a file of public keys (one '[name] modulus [exponent]' per line)"
+            ),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Char('s') => {
+                let storage = match Storage::load_storage() {
+                    Ok(storage) => storage,
+                    Err(error) => {
+                        utils::run_print_error(w, "SHARED PRIME SCAN", &error)?;
+                        continue;
+                    }
+                };
+                let public_keys = batch_gcd::public_keys_from_storage(&storage);
+                run_print_scan_result(w, &public_keys, false)?;
+            }
+            KeyCode::Char('f') => {
+                if let Some(path) = run_enter_path(w)? {
+                    match batch_gcd::load_public_keys_from_file(&path) {
+                        Ok(public_keys) => run_print_scan_result(w, &public_keys, true)?,
+                        Err(err) => run_print_error(w, &err)?,
+                    }
+                }
+            }
+            KeyCode::Esc => {
+                return Ok(());
+            }
+            _ => {}
+        };
+    }
+}
+
+fn run_enter_path<W>(w: &mut W) -> crossterm::Result<Option<String>>
+where
+    W: Write,
+{
+    let mut tmp_input_line: String = String::new();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::MoveTo(2, 2),
+            style::Print("SHARED PRIME SCAN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                r#"Enter path to public keys file: {}"#,
+                tmp_input_line
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to scan or ESC to cancel."),
+        )?;
+
+        w.flush()?;
+
+        if let Event::Key(key_event) = crossterm::event::read()? {
+            match key_event.code {
+                KeyCode::Enter if !tmp_input_line.is_empty() => {
+                    return Ok(Some(tmp_input_line));
+                }
+                KeyCode::Backspace => {
+                    tmp_input_line.pop();
+                }
+                KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char(c) => {
+                    tmp_input_line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn run_print_scan_result<W>(
+    w: &mut W,
+    public_keys: &[PublicKey],
+    can_save: bool,
+) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let scan_results: Vec<ScanResult> = batch_gcd::scan(public_keys);
+    let mut saved = false;
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("SHARED PRIME SCAN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "Scanned {} keys, found {} pairs sharing a factor.",
+                public_keys.len(),
+                scan_results.len()
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for scan_result in scan_results.iter() {
+            let factor = scan_result.factor.to_string();
+            crossterm::queue!(
+                w,
+                style::Print(format!(
+                    "{} <-> {}: {}",
+                    scan_result.first.name,
+                    scan_result.second.name,
+                    if scan_result.recovered.is_some() {
+                        format!("shared prime {}...", &factor[..factor.len() / 8])
+                    } else {
+                        "same modulus, private keys cannot be recovered".to_string()
+                    }
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(w, cursor::MoveToNextLine(1), cursor::MoveRight(2))?;
+
+        if saved {
+            crossterm::queue!(
+                w,
+                style::Print("Recovered private keys saved to storage."),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        } else if can_save && scan_results.iter().any(|r| r.recovered.is_some()) {
+            crossterm::queue!(
+                w,
+                style::Print("Press 's' to save recovered private keys to storage."),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(w, style::Print("Press Enter or ESC to go back."))?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Char('s') if can_save && !saved => {
+                let updated = Storage::update(|storage| {
+                    for (first, second) in scan_results.iter().filter_map(|r| r.recovered.clone()) {
+                        storage.add_key_pair(first);
+                        storage.add_key_pair(second);
+                    }
+                });
+                match updated {
+                    Ok(_) => saved = true,
+                    Err(error) => utils::run_print_error(w, "SHARED PRIME SCAN", &error)?,
+                }
+            }
+            KeyCode::Enter | KeyCode::Esc => return Ok(()),
+            _ => {}
+        };
+    }
+}
+
+fn run_print_error<W>(w: &mut W, error: &str) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("SHARED PRIME SCAN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!("Could not read public keys: {}", error)),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Enter | KeyCode::Esc => return Ok(()),
+            _ => {}
+        };
+    }
+}
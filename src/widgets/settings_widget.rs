@@ -0,0 +1,96 @@
+use std::io::Write;
+
+use crossterm::cursor;
+use crossterm::event::KeyCode;
+use crossterm::style;
+use crossterm::terminal;
+
+use crate::blinding;
+use crate::widgets::utils::read_char;
+
+pub fn run<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let mut index: usize = 0;
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            cursor::DisableBlinking,
+            style::Print("SETTINGS"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        let settings = [format!(
+            "Blinding of private-key operations (decrypt, sign): {}",
+            if blinding::is_enabled() { "on" } else { "off" }
+        )];
+
+        for (i, line) in settings.iter().enumerate() {
+            if i == index {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                    style::SetAttribute(style::Attribute::Bold),
+                )?;
+            } else {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::SetBackgroundColor(style::Color::Reset),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            crossterm::queue!(
+                w,
+                style::Print(line),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Use arrow keys to navigate."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter button to toggle the selected setting or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => {
+                if index > 0 {
+                    index -= 1;
+                } else {
+                    index = settings.len() - 1;
+                }
+            }
+            KeyCode::Down => {
+                if index < settings.len() - 1 {
+                    index += 1;
+                } else {
+                    index = 0;
+                }
+            }
+            KeyCode::Enter if index == 0 => blinding::set_enabled(!blinding::is_enabled()),
+            KeyCode::Esc => {
+                return Ok(());
+            }
+            _ => {}
+        };
+    }
+}
@@ -0,0 +1,298 @@
+use std::io::Write;
+use std::time::Duration;
+
+use crossterm::cursor;
+use crossterm::event::{Event, KeyCode};
+use crossterm::style;
+use crossterm::terminal;
+
+use crate::key_storage::{KeyPair, Storage};
+use crate::timing_lab::{self, TimingReport};
+use crate::widgets::utils::{read_char, run_print_error};
+
+const DEFAULT_SAMPLES: usize = 100;
+
+// Number of leading bits of d shown in the report
+const SHOWN_BITS: usize = 64;
+
+pub fn run<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let mut index: usize = 0;
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_error(w, "TIMING SIDE-CHANNEL LAB", &error),
+    };
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            cursor::DisableBlinking,
+            style::Print("TIMING SIDE-CHANNEL LAB"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Choose a key pair for the experiment:"),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+        )?;
+
+        if storage.get_key_pairs().is_empty() {
+            crossterm::queue!(
+                w,
+                style::Print("No key pairs found. Please create a new key pair."),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        } else {
+            for (id, key_pair) in storage.key_pairs().enumerate() {
+                if id == index {
+                    crossterm::queue!(
+                        w,
+                        style::SetForegroundColor(style::Color::Black),
+                        style::SetBackgroundColor(style::Color::White),
+                        style::SetAttribute(style::Attribute::Bold),
+                    )?;
+                } else {
+                    crossterm::queue!(
+                        w,
+                        style::SetForegroundColor(style::Color::Reset),
+                        style::SetBackgroundColor(style::Color::Reset),
+                        style::SetAttribute(style::Attribute::Reset),
+                    )?;
+                }
+
+                crossterm::queue!(
+                    w,
+                    style::Print(format!("{}: {}", id, key_pair.name)),
+                    cursor::MoveToNextLine(1),
+                    cursor::MoveRight(2)
+                )?;
+            }
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Use arrow keys to navigate."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter button to select key or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => {
+                if storage.get_key_pairs().is_empty() {
+                    continue;
+                } else if index > 0 {
+                    index -= 1;
+                } else {
+                    index = storage.get_key_pairs().len() - 1;
+                }
+            }
+            KeyCode::Down => {
+                if storage.get_key_pairs().is_empty() {
+                    continue;
+                } else if index < storage.get_key_pairs().len() - 1 {
+                    index += 1;
+                } else {
+                    index = 0;
+                }
+            }
+            KeyCode::Enter => {
+                if storage.get_key_pairs().is_empty() {
+                    continue;
+                }
+                let key_pair = storage.get_key_pair_by_id(index).unwrap();
+
+                let samples = match run_enter_samples(w)? {
+                    Some(samples) => samples,
+                    None => continue,
+                };
+
+                let report = run_measure(w, key_pair.clone(), samples)?;
+                run_print_report(w, &report)?;
+            }
+            KeyCode::Esc => {
+                return Ok(());
+            }
+            _ => {}
+        };
+    }
+}
+
+fn run_enter_samples<W>(w: &mut W) -> crossterm::Result<Option<usize>>
+where
+    W: Write,
+{
+    let mut tmp_input_line: String = String::new();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::MoveTo(2, 2),
+            style::Print("TIMING SIDE-CHANNEL LAB"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "Number of ciphertexts to time ({}): {}",
+                DEFAULT_SAMPLES, tmp_input_line
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to start measuring or ESC to cancel."),
+        )?;
+
+        w.flush()?;
+
+        if let Event::Key(key_event) = crossterm::event::read()? {
+            match key_event.code {
+                KeyCode::Enter => {
+                    if tmp_input_line.is_empty() {
+                        return Ok(Some(DEFAULT_SAMPLES));
+                    }
+                    match tmp_input_line.parse::<usize>() {
+                        Ok(samples) if samples > 0 => return Ok(Some(samples)),
+                        _ => tmp_input_line.clear(),
+                    }
+                }
+                KeyCode::Backspace => {
+                    tmp_input_line.pop();
+                }
+                KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    tmp_input_line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn run_measure<W>(w: &mut W, key_pair: KeyPair, samples: usize) -> crossterm::Result<TimingReport>
+where
+    W: Write,
+{
+    crossterm::queue!(
+        w,
+        style::ResetColor,
+        terminal::Clear(crossterm::terminal::ClearType::All),
+        cursor::Hide,
+        cursor::MoveTo(2, 2),
+        style::Print(format!("Timing {} decryptions per variant", samples)),
+        cursor::MoveToNextLine(2),
+        cursor::MoveRight(2),
+        style::Print("Measuring"),
+    )?;
+
+    let thread_join_handle =
+        std::thread::spawn(move || timing_lab::run_timing_experiment(&key_pair, samples));
+
+    while !thread_join_handle.is_finished() {
+        crossterm::queue!(w, crossterm::style::Print("."),)?;
+
+        w.flush()?;
+
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    Ok(thread_join_handle.join().unwrap())
+}
+
+fn run_print_report<W>(w: &mut W, report: &TimingReport) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("TIMING REPORT"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "Bits of d ({} of {}):  {}",
+                SHOWN_BITS.min(report.bits.len()),
+                report.bits.len(),
+                bits_to_string(&report.bits)
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for variant in report.variants.iter() {
+            crossterm::queue!(
+                w,
+                style::SetAttribute(style::Attribute::Bold),
+                style::Print(variant.exponentiation.name()),
+                style::SetAttribute(style::Attribute::Reset),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(4),
+                style::Print(format!(
+                    "Decryption: mean {:.1} us, std dev {:.1} us, min {:.1} us, max {:.1} us ({} samples)",
+                    variant.decryption.mean / 1000.0,
+                    variant.decryption.std_dev / 1000.0,
+                    variant.decryption.min / 1000.0,
+                    variant.decryption.max / 1000.0,
+                    variant.decryption.samples
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(4),
+                style::Print(format!(
+                    "Guessed bits from step times: {}",
+                    bits_to_string(&variant.guessed_bits)
+                )),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(4),
+                style::Print(format!(
+                    "Correlation with d: {:.3}, bits guessed right: {:.1}%",
+                    variant.correlation,
+                    variant.accuracy * 100.0
+                )),
+                cursor::MoveToNextLine(2),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::Print(
+                "A correlation close to 1 means the step times reveal the private exponent."
+            ),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Enter | KeyCode::Esc => return Ok(()),
+            _ => {}
+        };
+    }
+}
+
+fn bits_to_string(bits: &[bool]) -> String {
+    bits.iter()
+        .take(SHOWN_BITS)
+        .map(|&bit| if bit { '1' } else { '0' })
+        .collect()
+}
@@ -0,0 +1,624 @@
+use std::io::Write;
+
+use crossterm::cursor;
+use crossterm::event::{Event, KeyCode};
+use crossterm::style;
+use crossterm::terminal;
+use num_bigint_dig::BigUint;
+use serde_json::Value;
+
+use crate::jwk;
+use crate::jwt::{self, Algorithm, Token};
+use crate::key_storage::{Contact, KeyPair, Storage};
+use crate::widgets::utils::read_char;
+
+const CONTROLS: &str = r#"'v' - Paste and verify a token
+'c' - Create a token"#;
+
+// Width of the wrapped token text, long tokens would otherwise run off the margin
+const WRAP: usize = 64;
+
+// Verifying only needs a public key, so contacts are offered as well
+enum KeyEntry<'a> {
+    KeyPair(&'a KeyPair),
+    Contact(&'a Contact),
+}
+
+impl KeyEntry<'_> {
+    fn label(&self) -> String {
+        match self {
+            KeyEntry::KeyPair(key_pair) => key_pair.name.clone(),
+            KeyEntry::Contact(contact) => format!("{} (contact)", contact.name),
+        }
+    }
+
+    fn public_key(&self) -> (&BigUint, &BigUint) {
+        match self {
+            KeyEntry::KeyPair(key_pair) => (&key_pair.modulus, &key_pair.e),
+            KeyEntry::Contact(contact) => (&contact.modulus, &contact.e),
+        }
+    }
+}
+
+pub fn run<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let mut index = 0;
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("TOKEN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("JSON Web Tokens signed with RS256, RS384, RS512 or PS256."),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for (i, line) in CONTROLS.split('\n').enumerate() {
+            if i == index {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                    style::SetAttribute(style::Attribute::Bold),
+                )?;
+            } else {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::SetBackgroundColor(style::Color::Reset),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            crossterm::queue!(
+                w,
+                style::Print(line),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Use arrow keys to navigate, Enter to select or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up | KeyCode::Down => index = 1 - index,
+            KeyCode::Char('v') => run_verify(w)?,
+            KeyCode::Char('c') => run_create(w)?,
+            KeyCode::Enter if index == 0 => run_verify(w)?,
+            KeyCode::Enter => run_create(w)?,
+            KeyCode::Esc => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn run_verify<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    // Pasted tokens often carry line breaks or spaces from where they were copied
+    let text: String = match run_enter_text(w, "Paste the JWT:", "", true)? {
+        Some(text) => text.chars().filter(|c| !c.is_whitespace()).collect(),
+        None => return Ok(()),
+    };
+    let token = match jwt::decode(&text) {
+        Ok(token) => token,
+        Err(error) => return run_print_message(w, &format!("Cannot decode the token: {}", error)),
+    };
+
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_message(w, &error),
+    };
+    let mut entries: Vec<KeyEntry> = storage.key_pairs().map(KeyEntry::KeyPair).collect();
+    entries.extend(storage.contacts().map(KeyEntry::Contact));
+
+    // Start on the key the "kid" header names, if the keyring has it
+    let mut index = entries
+        .iter()
+        .position(|entry| {
+            let (modulus, e) = entry.public_key();
+            token.kid() == Some(jwk::thumbprint(modulus, e).as_str())
+        })
+        .unwrap_or(0);
+
+    loop {
+        index = match run_choose_key(w, &entries, &token, index)? {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let (modulus, e) = entries[index].public_key();
+        let mut lines = vec![
+            format!("Key: {}", entries[index].label()),
+            match token.verify_signature(modulus, e) {
+                Ok(()) => "Signature: valid".to_string(),
+                Err(error) => format!("Signature: NOT valid, {}", error),
+            },
+            match token.check_time(jwt::now(), jwt::LEEWAY) {
+                Ok(()) => "Time: valid".to_string(),
+                Err(error) => format!("Time: NOT valid, {}", error),
+            },
+            String::new(),
+        ];
+        lines.extend(token_lines(&token));
+
+        if !run_print_lines(
+            w,
+            &lines,
+            "Press 'k' to choose another key or ESC to go back.",
+        )? {
+            return Ok(());
+        }
+    }
+}
+
+fn run_create<W>(w: &mut W) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    let storage = match Storage::load_storage() {
+        Ok(storage) => storage,
+        Err(error) => return run_print_message(w, &error),
+    };
+    let entries: Vec<KeyEntry> = storage.key_pairs().map(KeyEntry::KeyPair).collect();
+    if entries.is_empty() {
+        return run_print_message(w, "No key pairs found. Please create a new key pair.");
+    }
+
+    let mut index = 0;
+    loop {
+        index = match run_choose_key_to_sign(w, &entries, index)? {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let key_pair = match entries[index] {
+            KeyEntry::KeyPair(key_pair) => key_pair,
+            KeyEntry::Contact(_) => continue,
+        };
+
+        let algorithm = match run_choose_algorithm(w)? {
+            Some(algorithm) => algorithm,
+            None => continue,
+        };
+        let subject = match run_enter_text(w, "Subject (\"sub\"):", "", false)? {
+            Some(subject) => subject,
+            None => continue,
+        };
+        let issuer = match run_enter_text(w, "Issuer (\"iss\"), empty for none:", "", false)? {
+            Some(issuer) => issuer,
+            None => continue,
+        };
+        let minutes: i64 = match run_enter_text(w, "Lifetime in minutes:", "60", false)? {
+            Some(minutes) => match minutes.trim().parse() {
+                Ok(minutes) if minutes > 0 => minutes,
+                _ => {
+                    run_print_message(w, "The lifetime must be a positive number of minutes.")?;
+                    continue;
+                }
+            },
+            None => continue,
+        };
+
+        let claims = jwt::claims(subject.trim(), issuer.trim(), jwt::now(), minutes * 60);
+        let text = match jwt::encode(&claims, algorithm, key_pair) {
+            Ok(text) => text,
+            Err(error) => {
+                run_print_message(w, &format!("Could not sign: {}", error))?;
+                continue;
+            }
+        };
+        let mut lines = vec![format!(
+            "{} token signed by {}:",
+            algorithm.name(),
+            key_pair.name
+        )];
+        lines.extend(wrap(&text));
+        lines.push(String::new());
+        lines.extend(token_lines(&jwt::decode(&text).unwrap()));
+
+        run_print_lines(w, &lines, "Press ESC to go back.")?;
+        return Ok(());
+    }
+}
+
+// Header and claims as pretty JSON, with the registered time claims spelled out
+fn token_lines(token: &Token) -> Vec<String> {
+    let mut lines = vec!["Header:".to_string()];
+    lines.extend(token.header_json().lines().map(str::to_string));
+    lines.push("Claims:".to_string());
+    lines.extend(token.claims_json().lines().map(str::to_string));
+    for (name, label) in [
+        ("iat", "Issued at"),
+        ("nbf", "Not before"),
+        ("exp", "Expires"),
+    ] {
+        if let Some(seconds) = token.claims.get(name).and_then(Value::as_i64) {
+            lines.push(format!("{}: {}", label, jwt::time_text(seconds)));
+        }
+    }
+    lines
+}
+
+fn wrap(text: &str) -> Vec<String> {
+    text.as_bytes()
+        .chunks(WRAP)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect()
+}
+
+fn run_choose_key<W>(
+    w: &mut W,
+    entries: &[KeyEntry],
+    token: &Token,
+    mut index: usize,
+) -> crossterm::Result<Option<usize>>
+where
+    W: Write,
+{
+    if entries.is_empty() {
+        run_print_message(
+            w,
+            "No keys found. Please create a key pair or add a contact.",
+        )?;
+        return Ok(None);
+    }
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("TOKEN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!(
+                "Algorithm: {}, kid: {}",
+                token
+                    .header
+                    .get("alg")
+                    .and_then(Value::as_str)
+                    .unwrap_or("none"),
+                token.kid().unwrap_or("none")
+            )),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Choose the key to verify the signature with:"),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+        )?;
+        print_entries(w, entries, index, Some(token))?;
+
+        crossterm::queue!(
+            w,
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to verify or ESC to go back."),
+        )?;
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => index = (index + entries.len() - 1) % entries.len(),
+            KeyCode::Down => index = (index + 1) % entries.len(),
+            KeyCode::Enter => return Ok(Some(index)),
+            KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn run_choose_key_to_sign<W>(
+    w: &mut W,
+    entries: &[KeyEntry],
+    mut index: usize,
+) -> crossterm::Result<Option<usize>>
+where
+    W: Write,
+{
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("TOKEN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Choose the key pair to sign the token with:"),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+        )?;
+        print_entries(w, entries, index, None)?;
+
+        crossterm::queue!(
+            w,
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to select or ESC to go back."),
+        )?;
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => index = (index + entries.len() - 1) % entries.len(),
+            KeyCode::Down => index = (index + 1) % entries.len(),
+            KeyCode::Enter => return Ok(Some(index)),
+            KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+// The key matching the "kid" header of `token` is marked
+fn print_entries<W>(
+    w: &mut W,
+    entries: &[KeyEntry],
+    index: usize,
+    token: Option<&Token>,
+) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    for (id, entry) in entries.iter().enumerate() {
+        if id == index {
+            crossterm::queue!(
+                w,
+                style::SetForegroundColor(style::Color::Black),
+                style::SetBackgroundColor(style::Color::White),
+                style::SetAttribute(style::Attribute::Bold),
+            )?;
+        } else {
+            crossterm::queue!(
+                w,
+                style::SetForegroundColor(style::Color::Reset),
+                style::SetBackgroundColor(style::Color::Reset),
+                style::SetAttribute(style::Attribute::Reset),
+            )?;
+        }
+
+        let (modulus, e) = entry.public_key();
+        let matches_kid =
+            token.is_some_and(|token| token.kid() == Some(jwk::thumbprint(modulus, e).as_str()));
+        crossterm::queue!(
+            w,
+            style::Print(format!(
+                "{}: {}{}",
+                id,
+                entry.label(),
+                if matches_kid { " - matches kid" } else { "" }
+            )),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2)
+        )?;
+    }
+
+    crossterm::queue!(
+        w,
+        style::SetForegroundColor(style::Color::Reset),
+        style::SetBackgroundColor(style::Color::Reset),
+        style::SetAttribute(style::Attribute::Reset),
+    )
+}
+
+fn run_choose_algorithm<W>(w: &mut W) -> crossterm::Result<Option<Algorithm>>
+where
+    W: Write,
+{
+    let mut index = 0;
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("TOKEN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Choose the signature algorithm:"),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+        )?;
+
+        for (i, algorithm) in Algorithm::ALL.iter().enumerate() {
+            if i == index {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Black),
+                    style::SetBackgroundColor(style::Color::White),
+                    style::SetAttribute(style::Attribute::Bold),
+                )?;
+            } else {
+                crossterm::queue!(
+                    w,
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::SetBackgroundColor(style::Color::Reset),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            crossterm::queue!(
+                w,
+                style::Print(algorithm.name()),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            style::SetForegroundColor(style::Color::Reset),
+            style::SetBackgroundColor(style::Color::Reset),
+            style::SetAttribute(style::Attribute::Reset),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("RS uses PKCS#1 v1.5 padding, PS256 uses PSS."),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to select or ESC to go back."),
+        )?;
+        w.flush()?;
+
+        let count = Algorithm::ALL.len();
+        match read_char()? {
+            KeyCode::Up => index = (index + count - 1) % count,
+            KeyCode::Down => index = (index + 1) % count,
+            KeyCode::Enter => return Ok(Some(Algorithm::ALL[index])),
+            KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+// Long input like a pasted token is shown wrapped, `required` refuses empty input
+fn run_enter_text<W>(
+    w: &mut W,
+    prompt: &str,
+    initial: &str,
+    required: bool,
+) -> crossterm::Result<Option<String>>
+where
+    W: Write,
+{
+    let mut tmp_input_line: String = initial.to_string();
+
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("TOKEN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(prompt),
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+        )?;
+
+        for line in wrap(&tmp_input_line) {
+            crossterm::queue!(
+                w,
+                style::Print(line),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print("Press Enter to continue or ESC to go back."),
+        )?;
+        w.flush()?;
+
+        if let Event::Key(key_event) = crossterm::event::read()? {
+            match key_event.code {
+                KeyCode::Enter if !required || !tmp_input_line.is_empty() => {
+                    return Ok(Some(tmp_input_line));
+                }
+                KeyCode::Backspace => {
+                    tmp_input_line.pop();
+                }
+                KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char(c) => {
+                    tmp_input_line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Scrollable list of lines, returns true when the user asked for another key with 'k'
+fn run_print_lines<W>(w: &mut W, lines: &[String], hint: &str) -> crossterm::Result<bool>
+where
+    W: Write,
+{
+    let mut scroll: usize = 0;
+
+    loop {
+        let (_, rows) = terminal::size()?;
+        let visible = (rows as usize).saturating_sub(7).max(1);
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print("TOKEN"),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+        )?;
+
+        for line in lines.iter().skip(scroll).take(visible) {
+            crossterm::queue!(
+                w,
+                style::Print(line),
+                cursor::MoveToNextLine(1),
+                cursor::MoveRight(2),
+            )?;
+        }
+
+        crossterm::queue!(
+            w,
+            cursor::MoveToNextLine(1),
+            cursor::MoveRight(2),
+            style::Print(format!("{} Arrow keys scroll.", hint)),
+        )?;
+        w.flush()?;
+
+        match read_char()? {
+            KeyCode::Up => scroll = scroll.saturating_sub(1),
+            KeyCode::Down if scroll + visible < lines.len() => scroll += 1,
+            KeyCode::Char('k') => return Ok(true),
+            KeyCode::Esc | KeyCode::Enter => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+fn run_print_message<W>(w: &mut W, message: &str) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    crossterm::queue!(
+        w,
+        style::ResetColor,
+        terminal::Clear(crossterm::terminal::ClearType::All),
+        cursor::Hide,
+        cursor::MoveTo(2, 2),
+        style::Print("TOKEN"),
+        cursor::MoveToNextLine(2),
+        cursor::MoveRight(2),
+        style::Print(message),
+        cursor::MoveToNextLine(2),
+        cursor::MoveRight(2),
+        style::Print("Press any key to go back."),
+    )?;
+    w.flush()?;
+    read_char()?;
+    Ok(())
+}
@@ -1,9 +1,34 @@
+use std::io::Write;
+
 use crossterm::event::{read, Event, KeyCode};
+use crossterm::{cursor, style, terminal};
 
 #[derive(PartialEq)]
 pub enum EncryptOrDecrypt {
     Encrypt,
     Decrypt,
+    Sign,
+    Verify,
+}
+
+impl EncryptOrDecrypt {
+    pub fn title(&self) -> &'static str {
+        match self {
+            EncryptOrDecrypt::Encrypt => "ENCRYPT",
+            EncryptOrDecrypt::Decrypt => "DECRYPT",
+            EncryptOrDecrypt::Sign => "SIGN",
+            EncryptOrDecrypt::Verify => "VERIFY",
+        }
+    }
+
+    pub fn verb(&self) -> &'static str {
+        match self {
+            EncryptOrDecrypt::Encrypt => "encrypt",
+            EncryptOrDecrypt::Decrypt => "decrypt",
+            EncryptOrDecrypt::Sign => "sign",
+            EncryptOrDecrypt::Verify => "verify",
+        }
+    }
 }
 
 pub fn read_char() -> crossterm::Result<KeyCode> {
@@ -13,3 +38,32 @@ pub fn read_char() -> crossterm::Result<KeyCode> {
         }
     }
 }
+
+// Shown instead of a widget when the key storage cannot be loaded or saved
+pub fn run_print_error<W>(w: &mut W, title: &str, error: &str) -> crossterm::Result<()>
+where
+    W: Write,
+{
+    loop {
+        crossterm::queue!(
+            w,
+            style::ResetColor,
+            terminal::Clear(crossterm::terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(2, 2),
+            style::Print(title),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print(format!("Error: {}", error)),
+            cursor::MoveToNextLine(2),
+            cursor::MoveRight(2),
+            style::Print("Press Enter or ESC to go back."),
+        )?;
+
+        w.flush()?;
+
+        if let KeyCode::Enter | KeyCode::Esc = read_char()? {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,1036 @@
+use std::net::IpAddr;
+
+use chrono::{Datelike, TimeZone, Utc};
+use num_bigint_dig::BigUint;
+use num_traits::ToPrimitive;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::der;
+use crate::key_storage::KeyPair;
+use crate::pkcs1;
+use crate::sign::{self, HashAlgorithm};
+
+// Certificates and CSRs are written as RFC 5280 v3 certificates and RFC 2986 requests, signed
+// with sha256WithRSAEncryption
+
+const VERSION_3: u32 = 2;
+const CSR_VERSION_1: u32 = 0;
+
+const SHA256_WITH_RSA: &str = "1.2.840.113549.1.1.11";
+const SHA384_WITH_RSA: &str = "1.2.840.113549.1.1.12";
+const SHA512_WITH_RSA: &str = "1.2.840.113549.1.1.13";
+
+const EXTENSION_REQUEST: &str = "1.2.840.113549.1.9.14";
+const SUBJECT_KEY_IDENTIFIER: &str = "2.5.29.14";
+const KEY_USAGE: &str = "2.5.29.15";
+const SUBJECT_ALT_NAME: &str = "2.5.29.17";
+const BASIC_CONSTRAINTS: &str = "2.5.29.19";
+const AUTHORITY_KEY_IDENTIFIER: &str = "2.5.29.35";
+
+// Short names of the attributes a subject can be written with, as OpenSSL prints them
+const ATTRIBUTES: [(&str, &str); 7] = [
+    ("CN", "2.5.4.3"),
+    ("C", "2.5.4.6"),
+    ("L", "2.5.4.7"),
+    ("ST", "2.5.4.8"),
+    ("O", "2.5.4.10"),
+    ("OU", "2.5.4.11"),
+    ("emailAddress", "1.2.840.113549.1.9.1"),
+];
+
+// UTCTime covers 1950 to 2049, later dates need GeneralizedTime (RFC 5280 4.1.2.5)
+const LAST_UTC_TIME_YEAR: i32 = 2049;
+
+// Distinguished name as a list of attributes, one per RDN, in the order they are written
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Name {
+    pub attributes: Vec<(String, String)>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GeneralName {
+    Dns(String),
+    Email(String),
+    Uri(String),
+    Ip(IpAddr),
+    // Any other choice, only kept to show its tag number
+    Other(u8),
+}
+
+// Bits of the KeyUsage BIT STRING (RFC 5280 4.2.1.3)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KeyUsage {
+    DigitalSignature,
+    NonRepudiation,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+    KeyCertSign,
+    CrlSign,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BasicConstraints {
+    pub ca: bool,
+    pub path_length: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Extensions {
+    pub basic_constraints: Option<BasicConstraints>,
+    pub key_usage: Vec<KeyUsage>,
+    pub subject_alt_names: Vec<GeneralName>,
+    pub subject_key_id: Option<Vec<u8>>,
+    pub authority_key_id: Option<Vec<u8>>,
+    // Dotted OIDs of extensions this module does not know
+    pub other: Vec<String>,
+}
+
+// Seconds since the epoch
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Validity {
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+// The signed part of a certificate or request and the signature over it
+#[derive(Clone, PartialEq, Debug)]
+struct Signed {
+    to_be_signed: Vec<u8>,
+    algorithm: String,
+    signature: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Certificate {
+    pub version: u32,
+    pub serial: BigUint,
+    pub issuer: Name,
+    pub subject: Name,
+    pub validity: Validity,
+    pub modulus: BigUint,
+    pub e: BigUint,
+    pub extensions: Extensions,
+    signed: Signed,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CertificateRequest {
+    pub subject: Name,
+    pub modulus: BigUint,
+    pub e: BigUint,
+    pub extensions: Extensions,
+    signed: Signed,
+}
+
+impl Name {
+    // "CN=Alice, O=School, C=DE", commas inside a value can be escaped with '\'
+    pub fn parse(text: &str) -> Result<Name, String> {
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in text.chars() {
+            match c {
+                _ if escaped => {
+                    parts.last_mut().unwrap().push(c);
+                    escaped = false;
+                }
+                '\\' => escaped = true,
+                ',' => parts.push(String::new()),
+                _ => parts.last_mut().unwrap().push(c),
+            }
+        }
+
+        let mut attributes = Vec::new();
+        for part in parts
+            .iter()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+        {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("\"{}\" is not of the form KEY=value", part))?;
+            let key = key.trim();
+            let known = ATTRIBUTES
+                .iter()
+                .find(|(name, oid)| name.eq_ignore_ascii_case(key) || *oid == key);
+            let key = match known {
+                Some((name, _)) => name.to_string(),
+                None => {
+                    return Err(format!(
+                        "unknown attribute \"{}\", use one of CN, O, OU, L, ST, C, emailAddress",
+                        key
+                    ))
+                }
+            };
+            if key == "C" && value.trim().len() != 2 {
+                return Err("the country (C) must be a two letter code".to_string());
+            }
+            attributes.push((key, value.trim().to_string()));
+        }
+
+        if attributes.is_empty() {
+            return Err("the name is empty".to_string());
+        }
+        Ok(Name { attributes })
+    }
+
+    pub fn text(&self) -> String {
+        self.attributes
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value.replace(',', "\\,")))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    pub fn common_name(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == "CN")
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Name ::= SEQUENCE OF SET OF AttributeTypeAndValue, one attribute per set
+    fn to_der(&self) -> Vec<u8> {
+        let rdns: Vec<Vec<u8>> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| {
+                let oid = ATTRIBUTES
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .map_or(key.as_str(), |(_, oid)| oid);
+                // Countries must be PrintableString and e-mail addresses IA5String
+                let tag = match key.as_str() {
+                    "C" => der::TAG_PRINTABLE_STRING,
+                    "emailAddress" => der::TAG_IA5_STRING,
+                    _ => der::TAG_UTF8_STRING,
+                };
+                der::encode_set(&[der::encode_sequence(&[
+                    der::encode_oid(oid),
+                    der::encode_tlv(tag, value.as_bytes()),
+                ])])
+            })
+            .collect();
+        der::encode_sequence(&rdns)
+    }
+
+    fn from_der(input: &[u8]) -> Result<(Name, &[u8]), String> {
+        let (mut rdns, rest) = der::decode_sequence(input)?;
+        let mut attributes = Vec::new();
+        while !rdns.is_empty() {
+            let (mut set, next) = der::decode_expected(rdns, der::TAG_SET)?;
+            while !set.is_empty() {
+                let (attribute, next) = der::decode_sequence(set)?;
+                let (oid, value) = der::decode_oid(attribute)?;
+                let (value, _) = der::decode_string(value)?;
+                let key = ATTRIBUTES
+                    .iter()
+                    .find(|(_, known)| *known == oid)
+                    .map_or(oid.clone(), |(name, _)| name.to_string());
+                attributes.push((key, value));
+                set = next;
+            }
+            rdns = next;
+        }
+        Ok((Name { attributes }, rest))
+    }
+}
+
+impl GeneralName {
+    // "DNS:example.org", "IP:10.0.0.1", "email:alice@example.org" or "URI:https://..."
+    pub fn parse(text: &str) -> Result<GeneralName, String> {
+        let (kind, value) = text
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("\"{}\" needs a DNS:, IP:, email: or URI: prefix", text))?;
+        let value = value.trim().to_string();
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "dns" => Ok(GeneralName::Dns(value)),
+            "email" => Ok(GeneralName::Email(value)),
+            "uri" => Ok(GeneralName::Uri(value)),
+            "ip" => value
+                .parse()
+                .map(GeneralName::Ip)
+                .map_err(|_| format!("\"{}\" is not an IP address", value)),
+            _ => Err(format!("unknown alternative name type \"{}\"", kind)),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self {
+            GeneralName::Dns(name) => format!("DNS:{}", name),
+            GeneralName::Email(address) => format!("email:{}", address),
+            GeneralName::Uri(uri) => format!("URI:{}", uri),
+            GeneralName::Ip(address) => format!("IP:{}", address),
+            GeneralName::Other(tag) => format!("other name [{}]", tag),
+        }
+    }
+
+    // GeneralName is a CHOICE of IMPLICIT context tags
+    fn to_der(&self) -> Vec<u8> {
+        match self {
+            GeneralName::Email(address) => {
+                der::encode_tlv(der::context_tag(1, false), address.as_bytes())
+            }
+            GeneralName::Dns(name) => der::encode_tlv(der::context_tag(2, false), name.as_bytes()),
+            GeneralName::Uri(uri) => der::encode_tlv(der::context_tag(6, false), uri.as_bytes()),
+            GeneralName::Ip(IpAddr::V4(address)) => {
+                der::encode_tlv(der::context_tag(7, false), &address.octets())
+            }
+            GeneralName::Ip(IpAddr::V6(address)) => {
+                der::encode_tlv(der::context_tag(7, false), &address.octets())
+            }
+            GeneralName::Other(_) => Vec::new(),
+        }
+    }
+
+    fn from_der(tag: u8, value: &[u8]) -> GeneralName {
+        let text = || String::from_utf8_lossy(value).into_owned();
+        match tag & 0x1f {
+            1 => GeneralName::Email(text()),
+            2 => GeneralName::Dns(text()),
+            6 => GeneralName::Uri(text()),
+            7 if value.len() == 4 => {
+                GeneralName::Ip(IpAddr::from(<[u8; 4]>::try_from(value).unwrap()))
+            }
+            7 if value.len() == 16 => {
+                GeneralName::Ip(IpAddr::from(<[u8; 16]>::try_from(value).unwrap()))
+            }
+            number => GeneralName::Other(number),
+        }
+    }
+}
+
+// Comma separated alternative names, empty text gives none
+pub fn parse_alt_names(text: &str) -> Result<Vec<GeneralName>, String> {
+    text.split(',')
+        .filter(|part| !part.trim().is_empty())
+        .map(GeneralName::parse)
+        .collect()
+}
+
+impl KeyUsage {
+    pub const ALL: [KeyUsage; 7] = [
+        KeyUsage::DigitalSignature,
+        KeyUsage::NonRepudiation,
+        KeyUsage::KeyEncipherment,
+        KeyUsage::DataEncipherment,
+        KeyUsage::KeyAgreement,
+        KeyUsage::KeyCertSign,
+        KeyUsage::CrlSign,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyUsage::DigitalSignature => "digitalSignature",
+            KeyUsage::NonRepudiation => "nonRepudiation",
+            KeyUsage::KeyEncipherment => "keyEncipherment",
+            KeyUsage::DataEncipherment => "dataEncipherment",
+            KeyUsage::KeyAgreement => "keyAgreement",
+            KeyUsage::KeyCertSign => "keyCertSign",
+            KeyUsage::CrlSign => "cRLSign",
+        }
+    }
+
+    fn bit(&self) -> usize {
+        KeyUsage::ALL
+            .iter()
+            .position(|usage| usage == self)
+            .unwrap()
+    }
+}
+
+// Comma separated key usage names like "digitalSignature, keyEncipherment"
+pub fn parse_key_usage(text: &str) -> Result<Vec<KeyUsage>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            KeyUsage::ALL
+                .into_iter()
+                .find(|usage| usage.name().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("unknown key usage \"{}\"", name))
+        })
+        .collect()
+}
+
+// Usual key usage for an end entity or, with `ca`, for a certificate authority
+pub fn default_key_usage(ca: bool) -> Vec<KeyUsage> {
+    if ca {
+        vec![
+            KeyUsage::DigitalSignature,
+            KeyUsage::KeyCertSign,
+            KeyUsage::CrlSign,
+        ]
+    } else {
+        vec![KeyUsage::DigitalSignature, KeyUsage::KeyEncipherment]
+    }
+}
+
+// Extensions from the text the CLI and the TUI ask for, an empty key usage gets the
+// default for the kind of certificate
+pub fn extensions_from_text(
+    alt_names: &str,
+    ca: bool,
+    path_length: Option<u32>,
+    key_usage: &str,
+) -> Result<Extensions, String> {
+    let key_usage = parse_key_usage(key_usage)?;
+    Ok(Extensions {
+        basic_constraints: Some(BasicConstraints { ca, path_length }),
+        key_usage: if key_usage.is_empty() {
+            default_key_usage(ca)
+        } else {
+            key_usage
+        },
+        subject_alt_names: parse_alt_names(alt_names)?,
+        ..Extensions::default()
+    })
+}
+
+// RFC 7093 method 1: the leftmost 160 bits of the SHA-256 of the subjectPublicKey. RFC 5280
+// suggests SHA-1, any method works as long as it is used consistently
+pub fn key_identifier(modulus: &BigUint, e: &BigUint) -> Vec<u8> {
+    Sha256::digest(pkcs1::public_key_to_der(modulus, e))[..20].to_vec()
+}
+
+impl Extensions {
+    // Extension ::= SEQUENCE { extnID, critical BOOLEAN DEFAULT FALSE, extnValue OCTET STRING }
+    fn to_der(&self) -> Vec<u8> {
+        let extension = |oid: &str, critical: bool, value: Vec<u8>| {
+            let mut fields = vec![der::encode_oid(oid)];
+            if critical {
+                fields.push(der::encode_boolean(true));
+            }
+            fields.push(der::encode_tlv(der::TAG_OCTET_STRING, &value));
+            der::encode_sequence(&fields)
+        };
+
+        let mut extensions = Vec::new();
+        if let Some(constraints) = &self.basic_constraints {
+            let mut fields = Vec::new();
+            if constraints.ca {
+                fields.push(der::encode_boolean(true));
+            }
+            if let Some(length) = constraints.path_length {
+                fields.push(der::encode_integer(&BigUint::from(length)));
+            }
+            extensions.push(extension(
+                BASIC_CONSTRAINTS,
+                true,
+                der::encode_sequence(&fields),
+            ));
+        }
+        if !self.key_usage.is_empty() {
+            let bits: Vec<usize> = self.key_usage.iter().map(KeyUsage::bit).collect();
+            extensions.push(extension(KEY_USAGE, true, der::encode_named_bits(&bits)));
+        }
+        if !self.subject_alt_names.is_empty() {
+            let names: Vec<Vec<u8>> = self
+                .subject_alt_names
+                .iter()
+                .map(GeneralName::to_der)
+                .collect();
+            extensions.push(extension(
+                SUBJECT_ALT_NAME,
+                false,
+                der::encode_sequence(&names),
+            ));
+        }
+        if let Some(id) = &self.subject_key_id {
+            extensions.push(extension(
+                SUBJECT_KEY_IDENTIFIER,
+                false,
+                der::encode_tlv(der::TAG_OCTET_STRING, id),
+            ));
+        }
+        if let Some(id) = &self.authority_key_id {
+            extensions.push(extension(
+                AUTHORITY_KEY_IDENTIFIER,
+                false,
+                der::encode_sequence(&[der::encode_tlv(der::context_tag(0, false), id)]),
+            ));
+        }
+        der::encode_sequence(&extensions)
+    }
+
+    fn from_der(input: &[u8]) -> Result<Extensions, String> {
+        let (mut sequence, _) = der::decode_sequence(input)?;
+        let mut extensions = Extensions::default();
+
+        while !sequence.is_empty() {
+            let (extension, next) = der::decode_sequence(sequence)?;
+            sequence = next;
+            let (oid, mut fields) = der::decode_oid(extension)?;
+            if let Ok((_, rest)) = der::decode_expected(fields, der::TAG_BOOLEAN) {
+                fields = rest;
+            }
+            let (value, _) = der::decode_expected(fields, der::TAG_OCTET_STRING)?;
+
+            match oid.as_str() {
+                BASIC_CONSTRAINTS => {
+                    let (mut fields, _) = der::decode_sequence(value)?;
+                    let mut constraints = BasicConstraints {
+                        ca: false,
+                        path_length: None,
+                    };
+                    if let Ok((ca, rest)) = der::decode_expected(fields, der::TAG_BOOLEAN) {
+                        constraints.ca = ca.first().is_some_and(|&byte| byte != 0);
+                        fields = rest;
+                    }
+                    if let Ok((length, _)) = der::decode_integer(fields) {
+                        constraints.path_length = length.to_u32();
+                    }
+                    extensions.basic_constraints = Some(constraints);
+                }
+                KEY_USAGE => {
+                    let (bits, _) = der::decode_expected(value, der::TAG_BIT_STRING)?;
+                    extensions.key_usage = der::decode_named_bits(bits)
+                        .into_iter()
+                        .filter_map(|bit| KeyUsage::ALL.get(bit).cloned())
+                        .collect();
+                }
+                SUBJECT_ALT_NAME => {
+                    let (mut names, _) = der::decode_sequence(value)?;
+                    while !names.is_empty() {
+                        let (tag, name, next) = der::decode_tlv(names)?;
+                        extensions
+                            .subject_alt_names
+                            .push(GeneralName::from_der(tag, name));
+                        names = next;
+                    }
+                }
+                SUBJECT_KEY_IDENTIFIER => {
+                    let (id, _) = der::decode_expected(value, der::TAG_OCTET_STRING)?;
+                    extensions.subject_key_id = Some(id.to_vec());
+                }
+                AUTHORITY_KEY_IDENTIFIER => {
+                    let (fields, _) = der::decode_sequence(value)?;
+                    if let Ok((id, _)) = der::decode_expected(fields, der::context_tag(0, false)) {
+                        extensions.authority_key_id = Some(id.to_vec());
+                    }
+                }
+                _ => extensions.other.push(oid),
+            }
+        }
+        Ok(extensions)
+    }
+
+    // The extensions a CSR can ask for, the key identifiers are up to the issuer
+    fn requested(&self) -> Extensions {
+        Extensions {
+            basic_constraints: self.basic_constraints.clone(),
+            key_usage: self.key_usage.clone(),
+            subject_alt_names: self.subject_alt_names.clone(),
+            ..Extensions::default()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Extensions::default()
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(constraints) = &self.basic_constraints {
+            lines.push(format!(
+                "Basic constraints: CA:{}{}",
+                if constraints.ca { "TRUE" } else { "FALSE" },
+                constraints
+                    .path_length
+                    .map_or(String::new(), |length| format!(", pathlen:{}", length))
+            ));
+        }
+        if !self.key_usage.is_empty() {
+            lines.push(format!(
+                "Key usage: {}",
+                self.key_usage
+                    .iter()
+                    .map(KeyUsage::name)
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ));
+        }
+        if !self.subject_alt_names.is_empty() {
+            lines.push(format!(
+                "Subject alternative names: {}",
+                self.subject_alt_names
+                    .iter()
+                    .map(GeneralName::text)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+        if let Some(id) = &self.subject_key_id {
+            lines.push(format!("Subject key identifier: {}", colon_hex(id)));
+        }
+        if let Some(id) = &self.authority_key_id {
+            lines.push(format!("Authority key identifier: {}", colon_hex(id)));
+        }
+        for oid in self.other.iter() {
+            lines.push(format!("Unknown extension {}", oid));
+        }
+        lines
+    }
+}
+
+impl Validity {
+    pub fn days_from_now(days: i64) -> Validity {
+        let now = Utc::now().timestamp();
+        Validity {
+            not_before: now,
+            not_after: now + days * 24 * 60 * 60,
+        }
+    }
+
+    fn to_der(self) -> Vec<u8> {
+        der::encode_sequence(&[encode_time(self.not_before), encode_time(self.not_after)])
+    }
+
+    fn from_der(input: &[u8]) -> Result<(Validity, &[u8]), String> {
+        let (sequence, rest) = der::decode_sequence(input)?;
+        let (not_before, sequence) = decode_time(sequence)?;
+        let (not_after, _) = decode_time(sequence)?;
+        Ok((
+            Validity {
+                not_before,
+                not_after,
+            },
+            rest,
+        ))
+    }
+}
+
+fn encode_time(seconds: i64) -> Vec<u8> {
+    let time = Utc.timestamp_opt(seconds, 0).unwrap();
+    if (1950..=LAST_UTC_TIME_YEAR).contains(&time.year()) {
+        der::encode_tlv(
+            der::TAG_UTC_TIME,
+            time.format("%y%m%d%H%M%SZ").to_string().as_bytes(),
+        )
+    } else {
+        der::encode_tlv(
+            der::TAG_GENERALIZED_TIME,
+            time.format("%Y%m%d%H%M%SZ").to_string().as_bytes(),
+        )
+    }
+}
+
+fn decode_time(input: &[u8]) -> Result<(i64, &[u8]), String> {
+    let (tag, value, rest) = der::decode_tlv(input)?;
+    let text = std::str::from_utf8(value).map_err(|_| "invalid time".to_string())?;
+    let digits = text.strip_suffix('Z').ok_or("times must be in UTC")?;
+    let full = match tag {
+        der::TAG_UTC_TIME if digits.len() == 12 => {
+            let year: i32 = digits[..2].parse().map_err(|_| "invalid time")?;
+            format!("{}{}", if year >= 50 { 19 } else { 20 }, digits)
+        }
+        der::TAG_GENERALIZED_TIME if digits.len() == 14 => digits.to_string(),
+        _ => return Err(format!("unsupported time \"{}\"", text)),
+    };
+    let time = chrono::NaiveDateTime::parse_from_str(&full, "%Y%m%d%H%M%S")
+        .map_err(|_| format!("invalid time \"{}\"", text))?;
+    Ok((time.and_utc().timestamp(), rest))
+}
+
+pub fn time_text(seconds: i64) -> String {
+    match Utc.timestamp_opt(seconds, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => seconds.to_string(),
+    }
+}
+
+fn colon_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<String>>()
+        .join(":")
+}
+
+// AlgorithmIdentifier of sha256WithRSAEncryption, the parameters are NULL
+fn signature_algorithm() -> Vec<u8> {
+    der::encode_sequence(&[
+        der::encode_oid(SHA256_WITH_RSA),
+        der::encode_tlv(der::TAG_NULL, &[]),
+    ])
+}
+
+// SEQUENCE { toBeSigned, signatureAlgorithm, signature BIT STRING }, the layout of both
+// certificates and CSRs
+fn sign_structure(to_be_signed: Vec<u8>, key_pair: &KeyPair) -> Result<Vec<u8>, String> {
+    let signature = sign::sign_pkcs1_v15(&to_be_signed, HashAlgorithm::Sha256, key_pair)?;
+    Ok(der::encode_sequence(&[
+        to_be_signed,
+        signature_algorithm(),
+        der::encode_bit_string(&signature),
+    ]))
+}
+
+fn decode_signed(input: &[u8]) -> Result<(Signed, &[u8]), String> {
+    let (sequence, _) = der::decode_sequence(input)?;
+    let (_, _, after_to_be_signed) = der::decode_tlv(sequence)?;
+    let to_be_signed = &sequence[..sequence.len() - after_to_be_signed.len()];
+
+    let (algorithm, rest) = der::decode_sequence(after_to_be_signed)?;
+    let (algorithm, _) = der::decode_oid(algorithm)?;
+    let (signature, _) = der::decode_expected(rest, der::TAG_BIT_STRING)?;
+    let signature = match signature.split_first() {
+        Some((0, signature)) => signature.to_vec(),
+        _ => return Err("malformed signature".to_string()),
+    };
+
+    let (contents, _) = der::decode_sequence(to_be_signed)?;
+    Ok((
+        Signed {
+            to_be_signed: to_be_signed.to_vec(),
+            algorithm,
+            signature,
+        },
+        contents,
+    ))
+}
+
+impl Signed {
+    fn algorithm_name(&self) -> String {
+        match self.algorithm.as_str() {
+            SHA256_WITH_RSA => "sha256WithRSAEncryption".to_string(),
+            SHA384_WITH_RSA => "sha384WithRSAEncryption".to_string(),
+            SHA512_WITH_RSA => "sha512WithRSAEncryption".to_string(),
+            oid => oid.to_string(),
+        }
+    }
+
+    fn verify(&self, modulus: &BigUint, e: &BigUint) -> Result<(), String> {
+        let hash = match self.algorithm.as_str() {
+            SHA256_WITH_RSA => HashAlgorithm::Sha256,
+            SHA384_WITH_RSA => HashAlgorithm::Sha384,
+            SHA512_WITH_RSA => HashAlgorithm::Sha512,
+            _ => {
+                return Err(format!(
+                    "unsupported signature algorithm {}",
+                    self.algorithm_name()
+                ))
+            }
+        };
+        if sign::verify_pkcs1_v15(&self.to_be_signed, &self.signature, hash, modulus, e) {
+            Ok(())
+        } else {
+            Err("the signature is not valid".to_string())
+        }
+    }
+}
+
+// Positive 127 bit random serial, RFC 5280 allows up to 20 bytes
+fn random_serial() -> BigUint {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[0] = (bytes[0] & 0x7f) | 0x01;
+    BigUint::from_bytes_be(&bytes)
+}
+
+// TBSCertificate ::= SEQUENCE { version [0] EXPLICIT, serialNumber, signature, issuer,
+//     validity, subject, subjectPublicKeyInfo, ..., extensions [3] EXPLICIT }
+fn certificate(
+    subject: &Name,
+    subject_key: (&BigUint, &BigUint),
+    extensions: &Extensions,
+    issuer: &Name,
+    issuer_key: &KeyPair,
+    validity: Validity,
+) -> Result<Vec<u8>, String> {
+    let mut extensions = extensions.requested();
+    extensions.subject_key_id = Some(key_identifier(subject_key.0, subject_key.1));
+    extensions.authority_key_id = Some(key_identifier(&issuer_key.modulus, &issuer_key.e));
+
+    let to_be_signed = der::encode_sequence(&[
+        der::encode_tlv(
+            der::context_tag(0, true),
+            &der::encode_integer(&BigUint::from(VERSION_3)),
+        ),
+        der::encode_integer(&random_serial()),
+        signature_algorithm(),
+        issuer.to_der(),
+        validity.to_der(),
+        subject.to_der(),
+        pkcs1::public_key_to_subject_public_key_info(subject_key.0, subject_key.1),
+        der::encode_tlv(der::context_tag(3, true), &extensions.to_der()),
+    ]);
+    sign_structure(to_be_signed, issuer_key)
+}
+
+// Self-signed certificate, issuer and subject are the same and the key signs itself
+pub fn self_signed_certificate(
+    subject: &Name,
+    extensions: &Extensions,
+    validity: Validity,
+    key_pair: &KeyPair,
+) -> Result<Vec<u8>, String> {
+    certificate(
+        subject,
+        (&key_pair.modulus, &key_pair.e),
+        extensions,
+        subject,
+        key_pair,
+        validity,
+    )
+}
+
+// CertificationRequestInfo ::= SEQUENCE { version, subject, subjectPKInfo,
+//     attributes [0] IMPLICIT SET OF Attribute }, the wanted extensions go into an
+// extensionRequest attribute
+pub fn certificate_request(
+    subject: &Name,
+    extensions: &Extensions,
+    key_pair: &KeyPair,
+) -> Result<Vec<u8>, String> {
+    let requested = extensions.requested();
+    // There is at most one attribute, so the SET OF needs no sorting
+    let attributes = if requested.is_empty() {
+        Vec::new()
+    } else {
+        der::encode_sequence(&[
+            der::encode_oid(EXTENSION_REQUEST),
+            der::encode_set(&[requested.to_der()]),
+        ])
+    };
+
+    let to_be_signed = der::encode_sequence(&[
+        der::encode_integer(&BigUint::from(CSR_VERSION_1)),
+        subject.to_der(),
+        pkcs1::public_key_to_subject_public_key_info(&key_pair.modulus, &key_pair.e),
+        der::encode_tlv(der::context_tag(0, true), &attributes),
+    ]);
+    sign_structure(to_be_signed, key_pair)
+}
+
+// Issues a certificate for the request with `issuer_key`, whose certificate is `issuer`. The
+// request must carry a valid self-signature, and the issuer must be a CA allowed to sign
+// certificates
+pub fn sign_request(
+    request: &CertificateRequest,
+    issuer: &Certificate,
+    issuer_key: &KeyPair,
+    validity: Validity,
+) -> Result<Vec<u8>, String> {
+    request
+        .verify_signature()
+        .map_err(|error| format!("the request is not signed by its own key: {}", error))?;
+    if issuer.modulus != issuer_key.modulus || issuer.e != issuer_key.e {
+        return Err("the issuer certificate does not belong to the signing key".to_string());
+    }
+    if !issuer.is_ca() {
+        return Err("the issuer certificate is not a CA (basic constraints CA:FALSE)".to_string());
+    }
+    if !issuer.extensions.key_usage.is_empty()
+        && !issuer.extensions.key_usage.contains(&KeyUsage::KeyCertSign)
+    {
+        return Err("the issuer certificate does not allow keyCertSign".to_string());
+    }
+
+    certificate(
+        &request.subject,
+        (&request.modulus, &request.e),
+        &request.extensions,
+        &issuer.subject,
+        issuer_key,
+        validity,
+    )
+}
+
+pub fn parse_certificate(input: &[u8]) -> Result<Certificate, String> {
+    let (signed, contents) = decode_signed(input)?;
+
+    let (version, contents) = match der::decode_tlv(contents)? {
+        (tag, value, rest) if tag == der::context_tag(0, true) => {
+            let (version, _) = der::decode_integer(value)?;
+            (version.to_u32().unwrap_or(0), rest)
+        }
+        _ => (0, contents),
+    };
+    // Serial numbers of other issuers may be negative, they are shown as unsigned bytes
+    let (serial, contents) = der::decode_expected(contents, der::TAG_INTEGER)?;
+    let (_, contents) = der::decode_sequence(contents)?;
+    let (issuer, contents) = Name::from_der(contents)?;
+    let (validity, contents) = Validity::from_der(contents)?;
+    let (subject, contents) = Name::from_der(contents)?;
+    let (_, _, rest) = der::decode_tlv(contents)?;
+    let (modulus, e) =
+        pkcs1::public_key_from_subject_public_key_info(&contents[..contents.len() - rest.len()])?;
+
+    let mut extensions = Extensions::default();
+    let mut fields = rest;
+    while !fields.is_empty() {
+        let (tag, value, next) = der::decode_tlv(fields)?;
+        if tag == der::context_tag(3, true) {
+            extensions = Extensions::from_der(value)?;
+        }
+        fields = next;
+    }
+
+    Ok(Certificate {
+        version: version + 1,
+        serial: BigUint::from_bytes_be(serial),
+        issuer,
+        subject,
+        validity,
+        modulus,
+        e,
+        extensions,
+        signed,
+    })
+}
+
+pub fn parse_certificate_request(input: &[u8]) -> Result<CertificateRequest, String> {
+    let (signed, contents) = decode_signed(input)?;
+
+    let (_, contents) = der::decode_integer(contents)?;
+    let (subject, contents) = Name::from_der(contents)?;
+    let (_, _, rest) = der::decode_tlv(contents)?;
+    let (modulus, e) =
+        pkcs1::public_key_from_subject_public_key_info(&contents[..contents.len() - rest.len()])?;
+
+    let mut extensions = Extensions::default();
+    if let Ok((mut attributes, _)) = der::decode_expected(rest, der::context_tag(0, true)) {
+        while !attributes.is_empty() {
+            let (attribute, next) = der::decode_sequence(attributes)?;
+            let (oid, values) = der::decode_oid(attribute)?;
+            if oid == EXTENSION_REQUEST {
+                let (value, _) = der::decode_expected(values, der::TAG_SET)?;
+                extensions = Extensions::from_der(value)?;
+            }
+            attributes = next;
+        }
+    }
+
+    Ok(CertificateRequest {
+        subject,
+        modulus,
+        e,
+        extensions,
+        signed,
+    })
+}
+
+// Reads a certificate from PEM ("CERTIFICATE") or raw DER
+pub fn read_certificate(input: &[u8]) -> Result<Certificate, String> {
+    parse_certificate(&from_pem_or_der(input, "CERTIFICATE")?)
+}
+
+// Reads a request from PEM ("CERTIFICATE REQUEST", or the older "NEW CERTIFICATE REQUEST")
+// or raw DER
+pub fn read_certificate_request(input: &[u8]) -> Result<CertificateRequest, String> {
+    parse_certificate_request(&from_pem_or_der(input, "CERTIFICATE REQUEST")?)
+}
+
+fn from_pem_or_der(input: &[u8], label: &str) -> Result<Vec<u8>, String> {
+    match std::str::from_utf8(input) {
+        Ok(text) if text.contains("-----BEGIN ") => {
+            let (found, der) = pkcs1::from_pem(text)?;
+            if !found.ends_with(label) {
+                return Err(format!("expected a {} PEM block, found {}", label, found));
+            }
+            Ok(der)
+        }
+        _ => Ok(input.to_vec()),
+    }
+}
+
+pub fn certificate_to_pem(der: &[u8]) -> String {
+    pkcs1::to_pem("CERTIFICATE", der)
+}
+
+pub fn certificate_request_to_pem(der: &[u8]) -> String {
+    pkcs1::to_pem("CERTIFICATE REQUEST", der)
+}
+
+impl Certificate {
+    pub fn is_self_issued(&self) -> bool {
+        self.issuer == self.subject
+    }
+
+    pub fn is_ca(&self) -> bool {
+        self.extensions
+            .basic_constraints
+            .as_ref()
+            .is_some_and(|constraints| constraints.ca)
+    }
+
+    pub fn signature_algorithm(&self) -> String {
+        self.signed.algorithm_name()
+    }
+
+    // Checks the signature with the public key of the issuer
+    pub fn verify_signature(&self, modulus: &BigUint, e: &BigUint) -> Result<(), String> {
+        self.signed.verify(modulus, e)
+    }
+
+    pub fn verify_signed_by(&self, issuer: &Certificate) -> Result<(), String> {
+        if self.issuer != issuer.subject {
+            return Err(format!(
+                "issued by \"{}\", not by \"{}\"",
+                self.issuer.text(),
+                issuer.subject.text()
+            ));
+        }
+        self.verify_signature(&issuer.modulus, &issuer.e)
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Version: {}", self.version),
+            format!("Serial number: {}", colon_hex(&self.serial.to_bytes_be())),
+            format!("Signature algorithm: {}", self.signature_algorithm()),
+            format!("Issuer: {}", self.issuer.text()),
+            format!("Not before: {}", time_text(self.validity.not_before)),
+            format!("Not after: {}", time_text(self.validity.not_after)),
+            format!("Subject: {}", self.subject.text()),
+            format!(
+                "Public key: RSA {} bits, e = {}",
+                self.modulus.bits(),
+                self.e
+            ),
+        ];
+        lines.extend(self.extensions.describe());
+        if self.is_self_issued() {
+            lines.push(format!(
+                "Self-signature: {}",
+                match self.verify_signature(&self.modulus, &self.e) {
+                    Ok(()) => "valid".to_string(),
+                    Err(error) => error,
+                }
+            ));
+        }
+        lines
+    }
+}
+
+impl CertificateRequest {
+    // A request is signed with its own key, proving the requester holds the private key
+    pub fn verify_signature(&self) -> Result<(), String> {
+        self.signed.verify(&self.modulus, &self.e)
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Subject: {}", self.subject.text()),
+            format!(
+                "Public key: RSA {} bits, e = {}",
+                self.modulus.bits(),
+                self.e
+            ),
+            format!("Signature algorithm: {}", self.signed.algorithm_name()),
+        ];
+        lines.extend(self.extensions.describe());
+        lines.push(format!(
+            "Self-signature: {}",
+            match self.verify_signature() {
+                Ok(()) => "valid".to_string(),
+                Err(error) => error,
+            }
+        ));
+        lines
+    }
+}
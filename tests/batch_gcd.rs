@@ -0,0 +1,100 @@
+use num_bigint_dig::BigUint;
+
+use student_rsa_tool::batch_gcd;
+use student_rsa_tool::key_storage::GenerationMethod;
+
+mod common;
+use common::fixture_path;
+
+const SHARED: u64 = 1000003;
+
+fn moduli() -> Vec<BigUint> {
+    [
+        SHARED * 1000033,
+        SHARED * 1000037,
+        1000039 * 1000081,
+        1000033 * 1000081,
+    ]
+    .into_iter()
+    .map(BigUint::from)
+    .collect()
+}
+
+#[test]
+fn trees_and_batch_gcd() {
+    let moduli = moduli();
+    let tree = batch_gcd::product_tree(&moduli);
+    assert_eq!(tree.len(), 3);
+    assert_eq!(tree[1][0], &moduli[0] * &moduli[1]);
+    assert_eq!(tree[2][0], moduli.iter().product::<BigUint>());
+
+    // Both primes of the first and the last modulus appear in other moduli, so their
+    // divisor is the whole modulus
+    let divisors = batch_gcd::batch_gcd(&moduli);
+    assert_eq!(divisors[0], moduli[0]);
+    assert_eq!(divisors[1], BigUint::from(SHARED));
+    assert_eq!(divisors[2], BigUint::from(1000081u64));
+    assert_eq!(divisors[3], moduli[3]);
+    assert!(batch_gcd::batch_gcd(&[]).is_empty());
+
+    let shared: Vec<(usize, usize, BigUint)> = batch_gcd::find_shared_factors(&moduli)
+        .into_iter()
+        .map(|shared| (shared.first, shared.second, shared.factor))
+        .collect();
+    assert_eq!(
+        shared,
+        vec![
+            (0, 1, BigUint::from(SHARED)),
+            (0, 3, BigUint::from(1000033u64)),
+            (2, 3, BigUint::from(1000081u64)),
+        ]
+    );
+}
+
+#[test]
+fn shared_prime_recovers_both_private_keys() {
+    let public_keys = batch_gcd::load_public_keys_from_file(
+        fixture_path("batch_gcd/moduli.txt").to_str().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(public_keys.len(), 3);
+    assert_eq!(public_keys[0].name, "alice");
+    assert_eq!(public_keys[1].name, "line 3");
+    assert_eq!(public_keys[2].e, BigUint::from(65537u32));
+
+    let results = batch_gcd::scan(&public_keys);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].factor, BigUint::from(SHARED));
+
+    let (first, second) = results[0].recovered.as_ref().unwrap();
+    let message = BigUint::from(424242u32);
+    for (key_pair, public_key) in [(first, &public_keys[0]), (second, &public_keys[1])] {
+        assert_eq!(key_pair.modulus, public_key.modulus);
+        assert_eq!(key_pair.generation_method, GenerationMethod::Recovered);
+        assert_eq!(&key_pair.p * &key_pair.q, key_pair.modulus);
+
+        let phi = (&key_pair.p - 1u32) * (&key_pair.q - 1u32);
+        let d = key_pair.d.to_biguint().unwrap();
+        assert_eq!((&key_pair.e * &d) % &phi, BigUint::from(1u32));
+        let ciphertext = message.modpow(&key_pair.e, &key_pair.modulus);
+        assert_eq!(ciphertext.modpow(&d, &key_pair.modulus), message);
+    }
+
+    // A factor of one or the modulus itself recovers nothing
+    assert!(batch_gcd::recover_key_pair(&public_keys[0], &BigUint::from(1u32)).is_none());
+    assert!(batch_gcd::recover_key_pair(&public_keys[0], &public_keys[0].modulus).is_none());
+}
+
+#[test]
+fn invalid_moduli_files_are_reported() {
+    let error = batch_gcd::load_public_keys_from_file(
+        fixture_path("batch_gcd/invalid.txt").to_str().unwrap(),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(error, "line 2: invalid number");
+    assert!(batch_gcd::load_public_keys_from_file(
+        fixture_path("batch_gcd/missing.txt").to_str().unwrap()
+    )
+    .is_err());
+}
@@ -0,0 +1,60 @@
+use num_bigint_dig::RandBigInt;
+
+use student_rsa_tool::blinding;
+use student_rsa_tool::crt;
+use student_rsa_tool::decrypt;
+use student_rsa_tool::encrypt;
+use student_rsa_tool::exponentiation::Exponentiation;
+use student_rsa_tool::key_generator;
+use student_rsa_tool::sign;
+
+#[test]
+fn blinded_operations_match_unblinded_ones() {
+    let key_pair = key_generator::generate_key_pair(256);
+    let d = key_pair.d.to_biguint().unwrap();
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..10 {
+        let input = rng.gen_biguint_below(&key_pair.modulus);
+        let expected = input.modpow(&d, &key_pair.modulus);
+        for variant in [
+            Exponentiation::Builtin,
+            Exponentiation::SquareAndMultiply,
+            Exponentiation::MontgomeryLadder,
+        ] {
+            assert_eq!(
+                blinding::blinded_modpow(&input, &key_pair.modulus, &key_pair.e, &d, variant),
+                expected
+            );
+        }
+        assert_eq!(
+            blinding::blind(&input, &key_pair.modulus, &key_pair.e, |blinded| {
+                crt::crt_modpow(blinded, &key_pair)
+            }),
+            expected
+        );
+    }
+
+    // The switch is global, so blinded and unblinded results are compared in one test
+    let ciphertext = encrypt::encrypt("blind me", &key_pair.modulus, &key_pair.e).unwrap();
+    let mut results = Vec::new();
+    for enabled in [true, false] {
+        blinding::set_enabled(enabled);
+        assert_eq!(blinding::is_enabled(), enabled);
+        results.push((
+            decrypt::decrypt(&ciphertext, &key_pair.modulus, &key_pair.e, &key_pair.d).unwrap(),
+            decrypt::decrypt_crt(&ciphertext, &key_pair).unwrap(),
+            sign::sign("blind me", &key_pair.modulus, &key_pair.e, &key_pair.d),
+        ));
+    }
+    blinding::set_enabled(true);
+
+    assert_eq!(results[0], results[1]);
+    assert_eq!(results[0].0, "blind me");
+    assert!(sign::verify(
+        "blind me",
+        &results[0].2,
+        &key_pair.modulus,
+        &key_pair.e
+    ));
+}
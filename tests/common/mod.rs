@@ -0,0 +1,32 @@
+// Helpers shared by the integration tests. Each test crate uses only some of them
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+use student_rsa_tool::jwk;
+use student_rsa_tool::key_storage::KeyPair;
+
+// `name` is relative to tests/fixtures, e.g. "jwk/rsa2048.private.json"
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+pub fn fixture(name: &str) -> String {
+    fs::read_to_string(fixture_path(name)).unwrap()
+}
+
+pub fn fixture_bytes(name: &str) -> Vec<u8> {
+    fs::read(fixture_path(name)).unwrap()
+}
+
+// The 2048 bit key pair of jwk/rsa2048.private.json, named "fixture"
+pub fn fixture_key_pair() -> KeyPair {
+    jwk::parse(&fixture("jwk/rsa2048.private.json"))
+        .unwrap()
+        .remove(0)
+        .to_key_pair("fixture".to_string())
+        .unwrap()
+}
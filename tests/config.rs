@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::PathBuf;
+
+use student_rsa_tool::config::{self, Overrides};
+use student_rsa_tool::key_storage::Storage;
+
+// The settings are global, so everything runs in one test, in order
+#[test]
+fn config_errors_are_reported_instead_of_panicking() {
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config_errors");
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    // Without `init` the first use reads the config file named in the environment
+    let broken_config = directory.join("config.json");
+    fs::write(&broken_config, "{").unwrap();
+    std::env::set_var(config::CONFIG_VARIABLE, &broken_config);
+    let error = config::keyring().err().unwrap();
+    assert!(error.starts_with("invalid config file"), "{}", error);
+    assert!(config::storage_path().is_err());
+    assert!(Storage::load_storage().is_err());
+
+    // A data directory that is a file cannot hold the keyrings directory
+    let data_file = directory.join("data");
+    fs::write(&data_file, "").unwrap();
+    config::init(Overrides {
+        config: Some(directory.join("missing.json")),
+        data_dir: Some(data_file),
+        keyring: None,
+    })
+    .unwrap();
+    let error = config::storage_path().err().unwrap();
+    assert!(
+        error.starts_with("could not create the keyring directory"),
+        "{}",
+        error
+    );
+    assert!(Storage::load_storage().is_err());
+    assert!(Storage::new().save_storage().is_err());
+}
@@ -0,0 +1,75 @@
+use student_rsa_tool::decrypt;
+use student_rsa_tool::encoding::{self, Encoding};
+use student_rsa_tool::encrypt;
+use student_rsa_tool::envelope;
+
+mod common;
+use common::fixture_key_pair;
+
+// Test vectors of RFC 4648 section 10 and the Bitcoin base58 alphabet
+#[test]
+fn known_encodings() {
+    let data = b"foobar";
+    assert_eq!(Encoding::Hex.encode(data), "666f6f626172");
+    assert_eq!(Encoding::Base64.encode(b"fooba"), "Zm9vYmE=");
+    assert_eq!(Encoding::Base64Url.encode(&[0xfb, 0xff]), "-_8");
+    assert_eq!(Encoding::Base32.encode(data), "MZXW6YTBOI======");
+    assert_eq!(
+        Encoding::Base58.encode(b"Hello World!"),
+        "2NEpo7TZRRrLZSi2U"
+    );
+    assert_eq!(Encoding::Decimal.encode(&[1, 0]), "256");
+
+    let armored = Encoding::Armor.encode(data);
+    assert!(armored.starts_with("-----BEGIN RSA MESSAGE-----\n"));
+    assert_eq!(Encoding::Armor.decode(&armored).unwrap(), data);
+
+    // Legacy hex ciphertexts may have an odd number of digits
+    assert_eq!(Encoding::Hex.decode("abc").unwrap(), vec![0x0a, 0xbc]);
+    assert_eq!(Encoding::from_name("Base64URL"), Ok(Encoding::Base64Url));
+    assert!(Encoding::from_name("base16").is_err());
+}
+
+#[test]
+fn every_encoding_is_detected() {
+    let key_pair = fixture_key_pair();
+    let sealed = envelope::seal(b"for both of you", &[(&key_pair.modulus, &key_pair.e)]).unwrap();
+
+    for encoding in Encoding::ALL {
+        let text = encoding.encode(&sealed);
+        assert_eq!(encoding::detect(&text), Some(encoding), "{}", text);
+        let (data, detected) = encoding::decode_auto(&text).unwrap();
+        assert_eq!(detected, encoding);
+        assert_eq!(data, sealed);
+    }
+    assert_eq!(Encoding::Armor.next(), Encoding::Hex);
+}
+
+#[test]
+fn ciphertexts_decrypt_from_any_encoding() {
+    let key_pair = fixture_key_pair();
+    let ciphertext = encrypt::encrypt("attack at dawn", &key_pair.modulus, &key_pair.e).unwrap();
+
+    for encoding in Encoding::ALL {
+        // Wrapped lines are pasted with their line breaks
+        let text = encoding.encode(&ciphertext);
+        let wrapped: Vec<String> = text
+            .as_bytes()
+            .chunks(60)
+            .map(|line| String::from_utf8(line.to_vec()).unwrap())
+            .collect();
+        let (data, _) = encoding::decode_auto(&wrapped.join("\n")).unwrap();
+        assert_eq!(
+            decrypt::decrypt_crt(&data, &key_pair).unwrap(),
+            "attack at dawn"
+        );
+    }
+
+    let pgp = "-----BEGIN PGP MESSAGE-----\n\nZm9v\n-----END PGP MESSAGE-----\n";
+    assert!(encoding::decode_auto(pgp)
+        .unwrap_err()
+        .contains("pgp decrypt"));
+    assert!(encoding::decode_auto("not a ciphertext!").is_err());
+    assert!(Encoding::Base58.decode("0OIl").is_err());
+    assert!(Encoding::Decimal.decode("").is_err());
+}
@@ -0,0 +1,123 @@
+use num_bigint_dig::{BigInt, BigUint};
+
+use student_rsa_tool::decrypt;
+use student_rsa_tool::encoding::Encoding;
+use student_rsa_tool::encrypt;
+use student_rsa_tool::jwk;
+use student_rsa_tool::key_generator;
+use student_rsa_tool::key_storage::KeyPair;
+
+mod common;
+use common::{fixture, fixture_key_pair};
+
+#[test]
+fn ciphertexts_are_whole_blocks() {
+    let key_pair = fixture_key_pair();
+    // A block of a 2048 bit key holds 255 bytes, one of them is taken by the padding
+    for (length, blocks) in [(0, 1), (1, 1), (254, 1), (255, 2), (600, 3)] {
+        let message = "x".repeat(length);
+        let ciphertext = encrypt::encrypt(&message, &key_pair.modulus, &key_pair.e).unwrap();
+        assert_eq!(ciphertext.len(), blocks * 256);
+        assert_eq!(
+            decrypt::decrypt_crt(&ciphertext, &key_pair).unwrap(),
+            message
+        );
+    }
+
+    // Zero bytes at the start or the end of any block survive
+    for message in [
+        "\0\0hi".to_string(),
+        "hi\0".to_string(),
+        format!("\0\0{}", "y".repeat(300)),
+        format!("{}\0\0tail", "y".repeat(255)),
+    ] {
+        let ciphertext = encrypt::encrypt(&message, &key_pair.modulus, &key_pair.e).unwrap();
+        assert_eq!(
+            decrypt::decrypt(&ciphertext, &key_pair.modulus, &key_pair.e, &key_pair.d).unwrap(),
+            message
+        );
+        assert_eq!(
+            decrypt::decrypt_crt(&ciphertext, &key_pair).unwrap(),
+            message
+        );
+    }
+
+    let ciphertext = encrypt::encrypt("secret", &key_pair.modulus, &key_pair.e).unwrap();
+
+    let other = key_generator::generate_key_pair(512);
+    assert!(decrypt::decrypt_crt(&ciphertext, &other).is_err());
+}
+
+#[test]
+fn decimal_drops_leading_zeros_and_decryption_restores_them() {
+    let key_pair = fixture_key_pair();
+    // About one in a few hundred ciphertexts starts with a zero byte
+    let (message, ciphertext) = (0..)
+        .map(|count| {
+            let message = format!("message {}", count);
+            let ciphertext = encrypt::encrypt(&message, &key_pair.modulus, &key_pair.e).unwrap();
+            (message, ciphertext)
+        })
+        .find(|(_, ciphertext)| ciphertext[0] == 0)
+        .unwrap();
+
+    let data = Encoding::Decimal
+        .decode(&Encoding::Decimal.encode(&ciphertext))
+        .unwrap();
+    assert!(data.len() < ciphertext.len());
+    assert_eq!(decrypt::decrypt_crt(&data, &key_pair).unwrap(), message);
+}
+
+// baseline.hex was written by `encrypt` of the baseline b89c65f, whose decimal blocks are
+// one digit wider than the modulus of baseline.private.json
+#[test]
+fn baseline_ciphertexts_still_decrypt() {
+    let key_pair = jwk::parse(&fixture("encoding/baseline.private.json"))
+        .unwrap()
+        .remove(0)
+        .to_key_pair("baseline".to_string())
+        .unwrap();
+    let ciphertext = Encoding::Hex
+        .decode(&fixture("encoding/baseline.hex"))
+        .unwrap();
+    let expected = fixture("encoding/baseline.txt");
+    assert_eq!(
+        decrypt::decrypt_crt(&ciphertext, &key_pair).unwrap(),
+        expected
+    );
+    assert_eq!(
+        decrypt::decrypt(&ciphertext, &key_pair.modulus, &key_pair.e, &key_pair.d).unwrap(),
+        expected
+    );
+}
+
+// Key pair of two small primes with e = 7
+fn small_key_pair(p: u32, q: u32, d: u32) -> KeyPair {
+    KeyPair::new(
+        "small".to_string(),
+        BigUint::from(p),
+        BigUint::from(q),
+        BigUint::from(p) * BigUint::from(q),
+        BigUint::from(7u32),
+        BigInt::from(d),
+    )
+}
+
+#[test]
+fn keys_too_small_for_a_block_are_refused() {
+    // 143 has 8 bits and 60491 16 bits, a block of them cannot hold a byte and the padding
+    for key_pair in [small_key_pair(11, 13, 43), small_key_pair(251, 241, 5143)] {
+        let error = encrypt::encrypt("hi", &key_pair.modulus, &key_pair.e).unwrap_err();
+        assert!(error.contains("too small"), "{}", error);
+        assert!(decrypt::decrypt_crt(&[1, 2, 3], &key_pair).is_err());
+    }
+
+    // 16744463 has 24 bits, two bytes per block
+    let key_pair = small_key_pair(4093, 4091, 5977243);
+    let ciphertext = encrypt::encrypt("hi there", &key_pair.modulus, &key_pair.e).unwrap();
+    assert_eq!(ciphertext.len(), 5 * 3);
+    assert_eq!(
+        decrypt::decrypt_crt(&ciphertext, &key_pair).unwrap(),
+        "hi there"
+    );
+}
@@ -0,0 +1,77 @@
+use student_rsa_tool::decrypt;
+use student_rsa_tool::encoding::Encoding;
+use student_rsa_tool::encrypt;
+use student_rsa_tool::envelope;
+use student_rsa_tool::key_generator;
+
+mod common;
+use common::{fixture_bytes, fixture_key_pair};
+
+// node_oaep.bin is "OAEP from Node.js" encrypted by `crypto.publicEncrypt` to the JWK
+// fixture key with RSA_PKCS1_OAEP_PADDING and oaepHash "sha256"
+#[test]
+fn oaep_matches_node() {
+    let key_pair = fixture_key_pair();
+    assert_eq!(
+        decrypt::decrypt_oaep(&fixture_bytes("envelope/node_oaep.bin"), &key_pair).unwrap(),
+        b"OAEP from Node.js"
+    );
+
+    let ciphertext = encrypt::encrypt_oaep(b"key", &key_pair.modulus, &key_pair.e).unwrap();
+    assert_eq!(ciphertext.len(), 256);
+    assert_eq!(
+        decrypt::decrypt_oaep(&ciphertext, &key_pair).unwrap(),
+        b"key"
+    );
+
+    let mut tampered = ciphertext;
+    tampered[100] ^= 1;
+    assert!(decrypt::decrypt_oaep(&tampered, &key_pair).is_err());
+}
+
+#[test]
+fn every_recipient_can_open_the_envelope() {
+    let alice = fixture_key_pair();
+    let bob = key_generator::generate_key_pair(512);
+    let eve = key_generator::generate_key_pair(512);
+
+    let sealed = envelope::seal(
+        "for both of you".as_bytes(),
+        &[(&alice.modulus, &alice.e), (&bob.modulus, &bob.e)],
+    )
+    .unwrap();
+    let hex = Encoding::Hex.encode(&sealed);
+    let parsed = envelope::parse(&Encoding::Hex.decode(&hex).unwrap()).unwrap();
+    assert_eq!(parsed.recipients.len(), 2);
+
+    for recipient in [&alice, &bob] {
+        let (message, used) = parsed.open(&[&eve, recipient]).unwrap();
+        assert_eq!(message, b"for both of you");
+        assert_eq!(used.modulus, recipient.modulus);
+    }
+    let error = parsed.open(&[&eve]).err().unwrap();
+    assert!(error.contains("none of your key pairs"), "{}", error);
+}
+
+#[test]
+fn changed_envelopes_are_refused() {
+    let alice = fixture_key_pair();
+    let sealed = envelope::seal(b"text", &[(&alice.modulus, &alice.e)]).unwrap();
+
+    // GCM notices any change to the ciphertext
+    let mut tampered = sealed.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    let error = envelope::parse(&tampered)
+        .unwrap()
+        .open(&[&alice])
+        .err()
+        .unwrap();
+    assert!(error.contains("changed"), "{}", error);
+
+    assert!(envelope::parse(&sealed[..40]).is_err());
+    assert!(Encoding::Hex.decode("not hex").is_err());
+
+    let small = key_generator::generate_key_pair(128);
+    assert!(envelope::seal(b"text", &[(&small.modulus, &small.e)]).is_err());
+}
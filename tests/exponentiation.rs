@@ -0,0 +1,97 @@
+use num_bigint_dig::{BigUint, RandBigInt};
+
+use student_rsa_tool::exponentiation::{self, Exponentiation};
+use student_rsa_tool::key_generator;
+use student_rsa_tool::timing_lab::{self, TimingStats};
+
+const VARIANTS: [Exponentiation; 3] = [
+    Exponentiation::Builtin,
+    Exponentiation::SquareAndMultiply,
+    Exponentiation::MontgomeryLadder,
+];
+
+#[test]
+fn every_variant_agrees_with_modpow() {
+    let mut rng = rand::thread_rng();
+    let modulus = key_generator::generate_key_pair(128).modulus;
+
+    let mut cases: Vec<(BigUint, BigUint)> = (0..20)
+        .map(|_| (rng.gen_biguint(300), rng.gen_biguint(256)))
+        .collect();
+    // Zero exponent, zero base and a base larger than the modulus
+    cases.push((BigUint::from(7u32), BigUint::from(0u32)));
+    cases.push((BigUint::from(0u32), BigUint::from(5u32)));
+    cases.push((&modulus * 3u32 + 2u32, BigUint::from(65537u32)));
+
+    for (base, exponent) in cases.iter() {
+        let expected = base.modpow(exponent, &modulus);
+        for variant in VARIANTS {
+            assert_eq!(
+                exponentiation::modpow(base, exponent, &modulus, variant),
+                expected,
+                "{}",
+                variant.name()
+            );
+        }
+    }
+
+    let one = BigUint::from(1u32);
+    for variant in VARIANTS {
+        assert_eq!(
+            exponentiation::modpow(&BigUint::from(5u32), &BigUint::from(3u32), &one, variant),
+            BigUint::from(0u32)
+        );
+    }
+}
+
+fn ladder_steps(base: &BigUint, exponent: u64, modulus: &BigUint) -> usize {
+    let mut steps = 0;
+    exponentiation::montgomery_ladder_traced(base, &BigUint::from(exponent), modulus, || {
+        steps += 1
+    });
+    steps
+}
+
+fn square_and_multiply_steps(base: &BigUint, exponent: u64, modulus: &BigUint) -> usize {
+    let mut steps = 0;
+    exponentiation::square_and_multiply_traced(base, &BigUint::from(exponent), modulus, || {
+        steps += 1
+    });
+    steps
+}
+
+#[test]
+fn ladder_steps_do_not_depend_on_the_exponent() {
+    let modulus = BigUint::from(1000003u64 * 1000033);
+    let base = BigUint::from(12345u32);
+
+    assert_eq!(ladder_steps(&base, 3, &modulus), modulus.bits());
+    assert_eq!(ladder_steps(&base, 1 << 30, &modulus), modulus.bits());
+    assert_eq!(square_and_multiply_steps(&base, 3, &modulus), 2);
+    assert_eq!(square_and_multiply_steps(&base, 1 << 30, &modulus), 31);
+
+    assert_eq!(
+        exponentiation::bits_msb_first(&BigUint::from(5u32), 5),
+        vec![false, false, true, false, true]
+    );
+}
+
+#[test]
+fn timing_report_covers_every_bit() {
+    let stats = TimingStats::from_samples(&[1.0, 2.0, 3.0, 6.0]);
+    assert_eq!(stats.samples, 4);
+    assert_eq!(stats.mean, 3.0);
+    assert_eq!(stats.min, 1.0);
+    assert_eq!(stats.max, 6.0);
+    assert!((stats.std_dev - 3.5f64.sqrt()).abs() < 1e-9);
+
+    let key_pair = key_generator::generate_key_pair(64);
+    let d = key_pair.d.to_biguint().unwrap();
+    let report = timing_lab::run_timing_experiment(&key_pair, 3);
+    assert_eq!(report.bits.len(), d.bits());
+    for variant in report.variants.iter() {
+        assert_eq!(variant.step_times.len(), report.bits.len());
+        assert_eq!(variant.guessed_bits.len(), report.bits.len());
+        assert!((0.0..=1.0).contains(&variant.accuracy));
+    }
+}
@@ -0,0 +1,36 @@
+use student_rsa_tool::fingerprint;
+use student_rsa_tool::key_storage::Contact;
+use student_rsa_tool::pkcs1;
+
+mod common;
+use common::fixture;
+
+// rsa2048.pub.pem is `ssh-keygen -e -m PEM` of rsa2048.pub and rsa2048.fingerprint the
+// output of `ssh-keygen -lv` for it: the fingerprint line and then the randomart
+#[test]
+fn matches_ssh_keygen() {
+    let (modulus, e) = pkcs1::public_key_from_pem(&fixture("openssh/rsa2048.pub.pem")).unwrap();
+    let expected = fixture("openssh/rsa2048.fingerprint");
+    let mut lines = expected.lines();
+
+    let first_line: Vec<&str> = lines.next().unwrap().split_whitespace().collect();
+    assert_eq!(first_line[0], "2048");
+    assert_eq!(fingerprint::openssh(&modulus, &e), first_line[1]);
+
+    let randomart: Vec<&str> = lines.collect();
+    assert_eq!(fingerprint::openssh_randomart(&modulus, &e), randomart);
+}
+
+#[test]
+fn canonical_fingerprint_does_not_depend_on_the_pem_flavour() {
+    let (modulus, e) = pkcs1::public_key_from_pem(&fixture("openssh/rsa2048.pub.pem")).unwrap();
+    let contact = Contact::new("fixture".to_string(), modulus.clone(), e.clone());
+
+    let canonical = fingerprint::canonical(&modulus, &e);
+    assert_eq!(contact.fingerprint, canonical);
+    assert_eq!(canonical.len(), 32 * 3 - 1);
+
+    // Same key written as PKCS#1 again gives the same fingerprint
+    let (modulus, e) = pkcs1::public_key_from_pem(&pkcs1::public_key_to_pem(&modulus, &e)).unwrap();
+    assert_eq!(fingerprint::canonical(&modulus, &e), canonical);
+}
@@ -0,0 +1,127 @@
+use std::sync::atomic::AtomicBool;
+
+use num_bigint_dig::prime::probably_prime;
+use num_bigint_dig::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+
+use student_rsa_tool::fips186::{self, PrimeKind};
+use student_rsa_tool::key_generator;
+use student_rsa_tool::key_storage::{GenerationMethod, KeyPair};
+
+const NLEN: usize = 2048;
+
+// Checks the A.1 conditions on a generated key pair
+fn check_key_pair(key_pair: &KeyPair) {
+    let one = BigUint::one();
+    let half = NLEN / 2;
+    let (p, q) = (&key_pair.p, &key_pair.q);
+
+    assert_eq!(key_pair.modulus, p * q);
+    assert_eq!(key_pair.modulus.bits(), NLEN);
+    assert_eq!(p.bits(), half);
+    assert_eq!(q.bits(), half);
+    // p, q >= sqrt(2) * 2^(nlen/2 - 1)
+    assert!(p * p >= &one << (NLEN - 1));
+    assert!(q * q >= &one << (NLEN - 1));
+
+    let distance = if p > q { p - q } else { q - p };
+    assert!(distance > &one << (half - 100));
+
+    let d = key_pair.d.to_biguint().unwrap();
+    let lcm = (p - &one).lcm(&(q - &one));
+    assert!(d > &one << half);
+    assert!(d < lcm);
+    assert!((&key_pair.e * &d % &lcm).is_one());
+}
+
+#[test]
+fn table_b1_round_counts() {
+    assert_eq!(fips186::miller_rabin_rounds(2048), 5);
+    assert_eq!(fips186::miller_rabin_rounds(3071), 5);
+    assert_eq!(fips186::miller_rabin_rounds(3072), 4);
+    assert_eq!(fips186::miller_rabin_rounds(4096), 4);
+    assert_eq!(fips186::miller_rabin_rounds(8192), 4);
+}
+
+#[test]
+fn exponents_outside_the_a11_range_are_rejected() {
+    let one = BigUint::one();
+
+    for e in [
+        BigUint::from(3u32),
+        BigUint::from(65535u32),
+        &one << 16,
+        BigUint::from(65538u32),
+        &one << 256,
+        (&one << 256) + &one,
+    ] {
+        assert!(fips186::check_exponent(&e).is_err(), "accepted e = {}", e);
+        assert!(
+            fips186::generate_key_pair(NLEN, &e, PrimeKind::Probable, &AtomicBool::new(false))
+                .is_err()
+        );
+    }
+
+    assert!(fips186::check_exponent(&BigUint::from(key_generator::EXPONENT)).is_ok());
+    assert!(fips186::check_exponent(&((&one << 256) - &one)).is_ok());
+    // A valid e does not make up for a modulus below 2048 bits
+    let e = BigUint::from(key_generator::EXPONENT);
+    assert!(
+        fips186::generate_key_pair(1024, &e, PrimeKind::Probable, &AtomicBool::new(false)).is_err()
+    );
+}
+
+#[test]
+fn probable_prime_keys_meet_the_a1_bounds() {
+    let e = BigUint::from(key_generator::EXPONENT);
+    let key_pair =
+        fips186::generate_key_pair(NLEN, &e, PrimeKind::Probable, &AtomicBool::new(false)).unwrap();
+
+    check_key_pair(&key_pair);
+    assert_eq!(
+        key_pair.generation_method,
+        GenerationMethod::Fips186ProbablePrimes
+    );
+}
+
+#[test]
+fn provable_prime_keys_meet_the_a1_bounds() {
+    let e = BigUint::from(key_generator::EXPONENT);
+    let key_pair =
+        fips186::generate_key_pair(NLEN, &e, PrimeKind::Provable, &AtomicBool::new(false)).unwrap();
+
+    check_key_pair(&key_pair);
+    assert_eq!(
+        key_pair.generation_method,
+        GenerationMethod::Fips186ProvablePrimes
+    );
+}
+
+#[test]
+fn shawe_taylor_is_deterministic_and_proves_primes() {
+    let seed_bytes = 2 * fips186::security_strength(NLEN) / 8;
+    let seed = BigUint::from_bytes_be(&[0x5a; 28]);
+
+    // Lengths below 33 bits take the trial division path, the others Pocklington
+    for length in [2, 17, 32, 33, 64, 256] {
+        let (prime, next_seed) =
+            fips186::shawe_taylor_random_prime(length, &seed, seed_bytes).unwrap();
+        let (again, again_seed) =
+            fips186::shawe_taylor_random_prime(length, &seed, seed_bytes).unwrap();
+
+        assert_eq!(prime, again);
+        assert_eq!(next_seed, again_seed);
+        assert!(next_seed > seed);
+        assert_eq!(prime.bits(), length);
+        assert!(probably_prime(&prime, 20), "{} bits: {}", length, prime);
+
+        // The updated seed leads to another prime
+        let (next, _) = fips186::shawe_taylor_random_prime(length, &next_seed, seed_bytes).unwrap();
+        if length > 8 {
+            assert_ne!(prime, next);
+        }
+    }
+
+    assert!(fips186::shawe_taylor_random_prime(1, &seed, seed_bytes).is_err());
+}
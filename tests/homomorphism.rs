@@ -0,0 +1,44 @@
+use num_bigint_dig::BigUint;
+
+use student_rsa_tool::homomorphism::{self, DecryptionOracle};
+use student_rsa_tool::key_generator;
+
+#[test]
+fn product_of_ciphertexts_decrypts_to_product() {
+    let key_pair = key_generator::generate_key_pair(256);
+    let a = BigUint::from(123456789u64);
+    let b = BigUint::from(987654321u64);
+
+    let demo = homomorphism::demonstrate_homomorphism(&a, &b, &key_pair);
+    assert_eq!(demo.decrypted_product, &a * &b);
+    assert_eq!(demo.decrypted_product, demo.expected_product);
+    assert_eq!(
+        demo.encrypted_product,
+        homomorphism::encrypt_number(&(&a * &b), &key_pair.modulus, &key_pair.e)
+    );
+
+    // Products larger than the modulus wrap around
+    let large = &key_pair.modulus - 1u32;
+    let demo = homomorphism::demonstrate_homomorphism(&large, &large, &key_pair);
+    assert_eq!(demo.decrypted_product, BigUint::from(1u32));
+}
+
+#[test]
+fn blinding_attack_recovers_the_refused_message() {
+    let key_pair = key_generator::generate_key_pair(256);
+    let message = BigUint::from(4242424242u64);
+    let target = homomorphism::encrypt_number(&message, &key_pair.modulus, &key_pair.e);
+
+    let oracle = DecryptionOracle::new(&key_pair, target.clone());
+    assert!(oracle.decrypt(&target).is_none());
+    assert!(oracle.decrypt(&(&target + &key_pair.modulus)).is_none());
+
+    let attack =
+        homomorphism::blinding_attack(&target, &key_pair.modulus, &key_pair.e, &oracle).unwrap();
+    assert_ne!(attack.blinded_ciphertext, target);
+    assert_eq!(attack.recovered, message);
+    assert_eq!(
+        attack.blinded_plaintext,
+        (&message * &attack.r) % &key_pair.modulus
+    );
+}
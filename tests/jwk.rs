@@ -0,0 +1,184 @@
+use std::sync::atomic::AtomicBool;
+
+use num_bigint_dig::{BigInt, BigUint};
+
+use student_rsa_tool::jwk::{self, Jwk, OtherPrime};
+use student_rsa_tool::key_generator;
+use student_rsa_tool::key_storage::{Contact, GenerationMethod, KeyPair, Storage};
+use student_rsa_tool::openssh;
+
+mod common;
+use common::fixture;
+
+// rsa2048.private.json and rsa2048.public.json are the OpenSSH fixture key exported by
+// Node.js `KeyObject.export({ format: "jwk" })`, rfc7638.json is the example of RFC 7638 3.1
+fn read_jwk(name: &str) -> Jwk {
+    let mut keys = jwk::parse(&fixture(&format!("jwk/{}", name))).unwrap();
+    assert_eq!(keys.len(), 1);
+    keys.remove(0)
+}
+
+#[test]
+fn thumbprint_matches_rfc_7638() {
+    let example = read_jwk("rfc7638.json");
+    let (modulus, e) = example.public_key().unwrap();
+
+    assert_eq!(
+        jwk::thumbprint(&modulus, &e),
+        "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+    );
+    // The kid of the example is not a thumbprint, ours always is
+    assert_eq!(example.kid.as_deref(), Some("2011-04-29"));
+    assert_eq!(
+        jwk::public_key_to_jwk(&modulus, &e).kid.as_deref(),
+        Some("NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs")
+    );
+}
+
+#[test]
+fn private_key_matches_node() {
+    let from_node = read_jwk("rsa2048.private.json");
+    let key_pair =
+        openssh::private_key_from_pem(&fixture("openssh/rsa2048"), "lab".to_string(), None)
+            .unwrap();
+
+    let exported = jwk::private_key_to_jwk(&key_pair);
+    assert_eq!(exported.n, from_node.n);
+    assert_eq!(exported.e, from_node.e);
+    assert_eq!(exported.d, from_node.d);
+    assert_eq!(exported.p, from_node.p);
+    assert_eq!(exported.q, from_node.q);
+    assert_eq!(exported.dp, from_node.dp);
+    assert_eq!(exported.dq, from_node.dq);
+    assert_eq!(exported.qi, from_node.qi);
+
+    let imported = from_node.to_key_pair("node".to_string()).unwrap();
+    assert_eq!(imported.name, "node");
+    assert_eq!(imported.generation_method, GenerationMethod::Imported);
+    assert_eq!(imported.modulus, key_pair.modulus);
+    assert_eq!(imported.d, key_pair.d);
+    assert_eq!(imported.qinv(), key_pair.qinv());
+}
+
+#[test]
+fn public_key_matches_node() {
+    let from_node = read_jwk("rsa2048.public.json");
+    assert!(!from_node.is_private());
+    assert!(from_node.to_key_pair("x".to_string()).is_err());
+
+    let (modulus, e) = from_node.public_key().unwrap();
+    let exported = jwk::public_key_to_jwk(&modulus, &e);
+    assert_eq!(exported.n, from_node.n);
+    assert_eq!(exported.e, "AQAB");
+    assert_eq!(exported.kid, Some(jwk::thumbprint(&modulus, &e)));
+}
+
+#[test]
+fn multi_prime_keys_round_trip_through_oth() {
+    let key_pair = key_generator::generate_multi_prime_key_pair_with_rng(
+        1024,
+        3,
+        &mut key_generator::seeded_rng("jwk"),
+        &AtomicBool::new(false),
+    )
+    .unwrap();
+    let exported = jwk::private_key_to_jwk(&key_pair);
+    assert_eq!(exported.oth.len(), 1);
+
+    let json = exported.to_json();
+    let read_back = jwk::parse(&json).unwrap().remove(0);
+    assert_eq!(read_back, exported);
+
+    let imported = read_back.to_key_pair("copy".to_string()).unwrap();
+    assert_eq!(imported.primes(), key_pair.primes());
+    assert_eq!(
+        imported.other_primes[0].coefficient,
+        key_pair.other_primes[0].coefficient
+    );
+}
+
+#[test]
+fn storage_exports_every_public_key() {
+    let mut storage = Storage::new();
+    let key_pair =
+        key_generator::generate_key_pair_with_rng(512, &mut key_generator::seeded_rng("jwks"));
+    let (modulus, e) = read_jwk("rfc7638.json").public_key().unwrap();
+    storage.add_key_pair(key_pair.clone());
+    storage.add_contact(Contact::new("rfc".to_string(), modulus, e));
+
+    let jwks = jwk::storage_to_jwks(&storage);
+    let keys = jwk::parse(&jwks.to_json()).unwrap();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.iter().all(|key| !key.is_private()));
+    assert_eq!(
+        keys[0].public_key().unwrap(),
+        (key_pair.modulus.clone(), key_pair.e.clone())
+    );
+    assert_eq!(
+        keys[1].kid.as_deref(),
+        Some("NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs")
+    );
+}
+
+#[test]
+fn invalid_keys_are_reported() {
+    assert!(jwk::parse(r#"{"kty":"EC","n":"AQAB","e":"AQAB"}"#).is_err());
+    assert!(
+        jwk::parse(r#"{"kty":"RSA","n":"not base64!","e":"AQAB"}"#).unwrap()[0]
+            .public_key()
+            .is_err()
+    );
+    assert!(jwk::parse("{").is_err());
+
+    // A private key whose p does not belong to n
+    let mut from_node = read_jwk("rsa2048.private.json");
+    from_node.p = from_node.q.clone();
+    assert!(from_node.to_key_pair("x".to_string()).is_err());
+
+    // p = 1 and q = n multiply to n, but must not reach the LCM of p - 1 and q - 1
+    for trivial in ["AQ", "AA"] {
+        let mut from_node = read_jwk("rsa2048.private.json");
+        from_node.p = Some(trivial.to_string());
+        from_node.q = Some(from_node.n.clone());
+        let error = from_node.to_key_pair("x".to_string()).err().unwrap();
+        assert!(error.contains("greater than 1"), "{}", error);
+    }
+}
+
+#[test]
+fn repeated_primes_are_reported() {
+    let integer = |value: u32| jwk::encode_integer(&BigUint::from(value));
+    let other_prime = |r: u32| OtherPrime {
+        r: integer(r),
+        d: integer(1),
+        t: integer(1),
+    };
+
+    // n = 61^2 with e * d = 1 mod lcm(60, 60)
+    let mut key = jwk::public_key_to_jwk(&BigUint::from(3721u32), &BigUint::from(7u32));
+    key.d = Some(integer(43));
+    key.p = Some(integer(61));
+    key.q = Some(integer(61));
+    let error = key.to_key_pair("x".to_string()).err().unwrap();
+    assert!(error.contains("distinct"), "{}", error);
+
+    // n = 61 * 53 * 61, the repeated prime is in "oth"
+    let mut key = jwk::public_key_to_jwk(&BigUint::from(61u32 * 53 * 61), &BigUint::from(7u32));
+    key.d = Some(integer(223));
+    key.p = Some(integer(61));
+    key.q = Some(integer(53));
+    key.oth = vec![other_prime(61)];
+    let error = key.to_key_pair("x".to_string()).err().unwrap();
+    assert!(error.contains("distinct"), "{}", error);
+    // Keys built directly have no qInv instead of panicking
+    let prime = BigUint::from(61u32);
+    let key_pair = KeyPair::new(
+        "x".to_string(),
+        prime.clone(),
+        prime,
+        BigUint::from(3721u32),
+        BigUint::from(7u32),
+        BigInt::from(43),
+    );
+    assert_eq!(key_pair.qinv(), None);
+}
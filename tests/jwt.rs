@@ -0,0 +1,136 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use num_bigint_dig::BigUint;
+
+use student_rsa_tool::jwk::{self, Jwk};
+use student_rsa_tool::jwt::{self, Algorithm, LEEWAY};
+use student_rsa_tool::key_generator;
+
+mod common;
+use common::{fixture, fixture_key_pair};
+
+// RS256.jwt, RS384.jwt, RS512.jwt and PS256.jwt were signed by Node.js `crypto.sign` with
+// the JWK fixture key, PS256 with a 32 byte salt. They are valid from 1700000000 (nbf)
+// until 4102444800 (exp)
+const NOT_BEFORE: i64 = 1700000000;
+const EXPIRES: i64 = 4102444800;
+
+fn read_jwk(name: &str) -> Jwk {
+    jwk::parse(&fixture(&format!("jwk/{}", name)))
+        .unwrap()
+        .remove(0)
+}
+
+fn fixture_public_key() -> (BigUint, BigUint) {
+    read_jwk("rsa2048.public.json").public_key().unwrap()
+}
+
+#[test]
+fn tokens_signed_by_node_verify() {
+    let (modulus, e) = fixture_public_key();
+
+    for algorithm in Algorithm::ALL {
+        let token = jwt::decode(&fixture(&format!("jwt/{}.jwt", algorithm.name()))).unwrap();
+        assert_eq!(token.algorithm().unwrap(), algorithm);
+        assert_eq!(token.kid(), Some(jwk::thumbprint(&modulus, &e).as_str()));
+        assert_eq!(token.claims["sub"], "student");
+        assert_eq!(
+            token.verify(&modulus, &e, NOT_BEFORE),
+            Ok(()),
+            "{}",
+            algorithm.name()
+        );
+    }
+}
+
+#[test]
+fn exp_and_nbf_are_checked_with_leeway() {
+    let (modulus, e) = fixture_public_key();
+    let token = jwt::decode(&fixture("jwt/RS256.jwt")).unwrap();
+
+    assert!(token.verify(&modulus, &e, NOT_BEFORE - LEEWAY).is_ok());
+    let error = token
+        .verify(&modulus, &e, NOT_BEFORE - LEEWAY - 1)
+        .unwrap_err();
+    assert!(error.contains("not valid before"), "{}", error);
+
+    assert!(token.verify(&modulus, &e, EXPIRES + LEEWAY - 1).is_ok());
+    let error = token.verify(&modulus, &e, EXPIRES + LEEWAY).unwrap_err();
+    assert!(error.contains("expired"), "{}", error);
+}
+
+#[test]
+fn created_tokens_round_trip() {
+    let key_pair = fixture_key_pair();
+    let claims = jwt::claims("student", "tool", NOT_BEFORE, 600);
+
+    for algorithm in Algorithm::ALL {
+        let text = jwt::encode(&claims, algorithm, &key_pair).unwrap();
+        let token = jwt::decode(&text).unwrap();
+
+        assert_eq!(token.header["alg"], algorithm.name());
+        assert_eq!(token.header["typ"], "JWT");
+        assert_eq!(token.claims, claims);
+        assert_eq!(
+            token.verify(&key_pair.modulus, &key_pair.e, NOT_BEFORE + 599),
+            Ok(())
+        );
+        assert!(token
+            .verify(&key_pair.modulus, &key_pair.e, NOT_BEFORE + 600 + LEEWAY)
+            .is_err());
+    }
+}
+
+#[test]
+fn tampered_tokens_and_other_keys_are_rejected() {
+    let (modulus, e) = fixture_public_key();
+    let text = fixture("jwt/PS256.jwt");
+    let parts: Vec<&str> = text.trim().split('.').collect();
+
+    let claims = URL_SAFE_NO_PAD.encode(
+        String::from_utf8(URL_SAFE_NO_PAD.decode(parts[1]).unwrap())
+            .unwrap()
+            .replace("student", "teacher"),
+    );
+    let tampered = jwt::decode(&format!("{}.{}.{}", parts[0], claims, parts[2])).unwrap();
+    assert!(tampered.verify_signature(&modulus, &e).is_err());
+
+    let other = key_generator::generate_key_pair(1024);
+    let token = jwt::decode(&text).unwrap();
+    assert!(token.verify_signature(&other.modulus, &other.e).is_err());
+}
+
+#[test]
+fn unsupported_and_malformed_tokens_are_refused() {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(r#"{"sub":"student"}"#);
+    let token = jwt::decode(&format!("{}.{}.", header, claims)).unwrap();
+    let (modulus, e) = fixture_public_key();
+    let error = token.verify_signature(&modulus, &e).unwrap_err();
+    assert!(error.contains("unsupported algorithm"), "{}", error);
+
+    assert!(jwt::decode("only.two").is_err());
+    assert!(jwt::decode("a.b.c").is_err());
+    assert!(jwt::decode(&format!("{}.{}.AA", header, URL_SAFE_NO_PAD.encode("[1]"))).is_err());
+}
+
+#[test]
+fn keys_too_small_for_the_algorithm_are_refused() {
+    // Two 256-bit primes: PS256 needs 522 bits and RS512 745 bits, RS256 fits into 489 bits
+    let key_pair =
+        key_generator::generate_key_pair_with_rng(256, &mut key_generator::seeded_rng("small"));
+    let claims = jwt::claims("student", "tool", NOT_BEFORE, 600);
+
+    let token = jwt::encode(&claims, Algorithm::RS256, &key_pair).unwrap();
+    assert_eq!(
+        jwt::decode(&token)
+            .unwrap()
+            .verify(&key_pair.modulus, &key_pair.e, NOT_BEFORE),
+        Ok(())
+    );
+    for algorithm in [Algorithm::RS512, Algorithm::PS256] {
+        let error = jwt::encode(&claims, algorithm, &key_pair).unwrap_err();
+        assert!(error.contains("too small"), "{}", error);
+        assert!(error.contains(algorithm.name()), "{}", error);
+    }
+}
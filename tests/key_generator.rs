@@ -0,0 +1,88 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+
+use num_bigint_dig::{BigUint, RandPrime};
+
+use student_rsa_tool::fips186::{self, PrimeKind};
+use student_rsa_tool::key_generator;
+use student_rsa_tool::key_storage::GenerationMethod;
+use student_rsa_tool::prime_search;
+
+// Modulus of the 512-bit key generated from the seed phrase "fixed". It changes only if the
+// RNG, the prime search or the key checks change, which would break every seeded exercise
+const FIXED_MODULUS: &str = "b919f38ac54ed335a8e20fa7f0d983f161519a2a305252fed0f8499d940696a0\
+                             62b1d6da0e9ca086256ecaba9b85440373e199beced8586a08bc78ccc025a3e1";
+
+#[test]
+fn seeded_keys_are_reproducible() {
+    let key_pair =
+        key_generator::generate_key_pair_with_rng(256, &mut key_generator::seeded_rng("fixed"));
+    let again =
+        key_generator::generate_key_pair_with_rng(256, &mut key_generator::seeded_rng("fixed"));
+    let other =
+        key_generator::generate_key_pair_with_rng(256, &mut key_generator::seeded_rng("other"));
+
+    assert_eq!(
+        key_pair.modulus,
+        BigUint::parse_bytes(FIXED_MODULUS.as_bytes(), 16).unwrap()
+    );
+    assert_eq!(key_pair.modulus, again.modulus);
+    assert_eq!(key_pair.d, again.d);
+    assert_ne!(key_pair.modulus, other.modulus);
+    assert_eq!(key_pair.generation_method, GenerationMethod::Seeded);
+}
+
+#[test]
+fn cancellable_prime_search_matches_gen_prime() {
+    let expected = key_generator::seeded_rng("prime").gen_prime(256);
+    let prime = prime_search::gen_prime(
+        &mut key_generator::seeded_rng("prime"),
+        256,
+        &AtomicBool::new(false),
+    );
+    assert_eq!(prime, Some(expected));
+}
+
+#[test]
+fn cancelled_generation_returns_at_once() {
+    let cancel = AtomicBool::new(true);
+    let e = BigUint::from(key_generator::EXPONENT);
+
+    assert!(key_generator::generate_multi_prime_key_pair_with_rng(
+        2048,
+        3,
+        &mut key_generator::seeded_rng("fixed"),
+        &cancel
+    )
+    .is_none());
+    assert!(key_generator::generate_weak_key_pair(1024, &cancel).is_none());
+    assert!(fips186::generate_key_pair(2048, &e, PrimeKind::Probable, &cancel).is_err());
+    assert!(fips186::generate_key_pair(2048, &e, PrimeKind::Provable, &cancel).is_err());
+}
+
+#[test]
+fn multi_prime_keys_have_the_requested_modulus_size() {
+    let (progress, _receiver) = mpsc::channel();
+    let cancel = AtomicBool::new(false);
+
+    for prime_count in 2..=key_generator::MAX_PRIMES {
+        for seed in ["a", "b", "c", "d"] {
+            let key_pair = key_generator::generate_multi_prime_key_pair_with_rng(
+                768,
+                prime_count,
+                &mut key_generator::seeded_rng(seed),
+                &cancel,
+            )
+            .unwrap();
+            assert_eq!(key_pair.modulus.bits(), 768, "{} primes", prime_count);
+            assert_eq!(key_pair.primes().len(), prime_count);
+        }
+
+        let key_pair = key_generator::generate_multi_prime_key_pair(768, prime_count);
+        assert_eq!(key_pair.modulus.bits(), 768, "{} primes", prime_count);
+        let key_pair =
+            key_generator::generate_key_pair_parallel(768, prime_count, &cancel, &progress)
+                .unwrap();
+        assert_eq!(key_pair.modulus.bits(), 768, "{} primes", prime_count);
+    }
+}
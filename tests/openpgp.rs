@@ -0,0 +1,210 @@
+use num_bigint_dig::{BigInt, BigUint};
+
+use student_rsa_tool::key_generator;
+use student_rsa_tool::key_storage::KeyPair;
+use student_rsa_tool::openpgp::{self, Key, PublicKey, SecretKey};
+
+mod common;
+use common::fixture_bytes;
+
+// The JWK fixture key exported with its creation time set to CREATED is fixture.pub.asc and
+// fixture.sec.asc. GnuPG 2.2 imported them and wrote to_fixture.asc (compressed),
+// signed_encrypted.asc, signed_by_fixture.gpg (ZIP) and msg.txt.asc (text mode, SHA-384).
+// gnupg.pub.asc and gnupg.sec.asc are a GnuPG key with an encryption subkey, used for
+// to_gnupg.asc and signed_by_gnupg.asc
+const CREATED: i64 = 1700000000;
+const USER_ID: &str = "Fixture <fixture@example.org>";
+const FINGERPRINT: &str = "A8EA0E3973A63009F8C7CE5F0E3A7D410FE84463";
+const GNUPG_FINGERPRINT: &str = "463D89563C302B7EB278CC1AA55708864CEA3642";
+const GNUPG_SUBKEY_ID: &str = "CB1A491D26F28B9C";
+
+fn fixture_key_pair() -> KeyPair {
+    let mut key_pair = common::fixture_key_pair();
+    key_pair.created_at = CREATED;
+    key_pair
+}
+
+#[test]
+fn exported_keys_match_what_gnupg_imported() {
+    let key_pair = fixture_key_pair();
+    let public = openpgp::export_public_key(&key_pair, USER_ID).unwrap();
+    let secret = openpgp::export_secret_key(&key_pair, USER_ID).unwrap();
+    assert_eq!(
+        openpgp::armor(openpgp::PUBLIC_KEY_BLOCK, &public).into_bytes(),
+        fixture_bytes("openpgp/fixture.pub.asc")
+    );
+    assert_eq!(
+        openpgp::armor(openpgp::PRIVATE_KEY_BLOCK, &secret).into_bytes(),
+        fixture_bytes("openpgp/fixture.sec.asc")
+    );
+    assert_eq!(
+        PublicKey::from_key_pair(&key_pair).fingerprint_text(),
+        FINGERPRINT
+    );
+
+    let keys = openpgp::read_keys(&fixture_bytes("openpgp/fixture.sec.asc")).unwrap();
+    assert_eq!(keys[0].user_ids, vec![USER_ID]);
+    let imported = keys[0].primary.to_key_pair("imported".to_string()).unwrap();
+    assert_eq!(imported.modulus, key_pair.modulus);
+    assert_eq!(imported.created_at, CREATED);
+}
+
+#[test]
+fn gnupg_messages_decrypt_and_verify() {
+    let key_pair = fixture_key_pair();
+    let public = PublicKey::from_key_pair(&key_pair);
+    let expected = fixture_bytes("openpgp/msg.txt");
+
+    let (message, used) =
+        openpgp::decrypt(&fixture_bytes("openpgp/to_fixture.asc"), &[&key_pair]).unwrap();
+    assert_eq!(message.literal.data, expected);
+    assert_eq!(message.literal.file_name, "msg.txt");
+    assert_eq!(used.name, "fixture");
+    assert!(message.signatures.is_empty());
+
+    let (message, _) =
+        openpgp::decrypt(&fixture_bytes("openpgp/signed_encrypted.asc"), &[&key_pair]).unwrap();
+    assert_eq!(message.signatures.len(), 1);
+    assert_eq!(message.signatures[0].issuer, Some(public.key_id()));
+    assert_eq!(
+        message.signatures[0].verify(&message.literal.data, &public),
+        Ok(())
+    );
+
+    let message = openpgp::read_message(&fixture_bytes("openpgp/signed_by_fixture.gpg")).unwrap();
+    assert_eq!(message.literal.data, expected);
+    assert_eq!(message.signatures[0].verify(&expected, &public), Ok(()));
+
+    let signature = openpgp::read_signature(&fixture_bytes("openpgp/msg.txt.asc")).unwrap();
+    assert_eq!(signature.verify(&expected, &public), Ok(()));
+    assert!(signature
+        .verify(b"Hello from somebody else\n", &public)
+        .is_err());
+}
+
+#[test]
+fn gnupg_key_with_subkey() {
+    let keys = openpgp::read_keys(&fixture_bytes("openpgp/gnupg.pub.asc")).unwrap();
+    let key = &keys[0];
+    assert_eq!(key.primary.public.fingerprint_text(), GNUPG_FINGERPRINT);
+    assert_eq!(key.user_id(), "GnuPG Student <student@example.org>");
+    assert_eq!(key.subkeys.len(), 1);
+    let encryption_key = key.encryption_key().unwrap();
+    assert_eq!(
+        openpgp::key_id_text(encryption_key.public.key_id()),
+        GNUPG_SUBKEY_ID
+    );
+
+    let message = openpgp::read_message(&fixture_bytes("openpgp/signed_by_gnupg.asc")).unwrap();
+    let signature = &message.signatures[0];
+    let signer = key.key_by_id(signature.issuer.unwrap()).unwrap();
+    assert_eq!(
+        signature.verify(&message.literal.data, &signer.public),
+        Ok(())
+    );
+
+    // The secret subkey decrypts, the primary key cannot
+    let secret = openpgp::read_keys(&fixture_bytes("openpgp/gnupg.sec.asc")).unwrap();
+    let primary = secret[0]
+        .primary
+        .to_key_pair("primary".to_string())
+        .unwrap();
+    let subkey = secret[0].subkeys[0]
+        .to_key_pair("subkey".to_string())
+        .unwrap();
+    let error = openpgp::decrypt(&fixture_bytes("openpgp/to_gnupg.asc"), &[&primary])
+        .err()
+        .unwrap();
+    assert!(error.contains(GNUPG_SUBKEY_ID), "{}", error);
+    let (message, used) =
+        openpgp::decrypt(&fixture_bytes("openpgp/to_gnupg.asc"), &[&primary, &subkey]).unwrap();
+    assert_eq!(used.name, "subkey");
+    assert_eq!(message.literal.data, fixture_bytes("openpgp/msg.txt"));
+}
+
+#[test]
+fn created_messages_round_trip() {
+    let key_pair = fixture_key_pair();
+    let public = PublicKey::from_key_pair(&key_pair);
+    let data = b"attack at dawn";
+
+    let encrypted = openpgp::encrypt(data, "plan.txt", &public, Some(&key_pair)).unwrap();
+    let armored = openpgp::armor(openpgp::MESSAGE, &encrypted);
+    let (message, _) = openpgp::decrypt(armored.as_bytes(), &[&key_pair]).unwrap();
+    assert_eq!(message.literal.data, data);
+    assert_eq!(message.literal.file_name, "plan.txt");
+    assert_eq!(message.signatures[0].verify(data, &public), Ok(()));
+
+    // Flipping a bit of the encrypted data breaks the modification detection code
+    let mut tampered = encrypted.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(openpgp::decrypt(&tampered, &[&key_pair]).is_err());
+
+    let signature = openpgp::sign_detached(data, &key_pair).unwrap();
+    let signature = openpgp::read_signature(&signature).unwrap();
+    assert_eq!(signature.verify(data, &public), Ok(()));
+    let other = PublicKey::from_key_pair(&key_generator::generate_key_pair(1024));
+    assert!(signature.verify(data, &other).is_err());
+}
+
+#[test]
+fn bad_input_is_reported() {
+    let mut armored = String::from_utf8(fixture_bytes("openpgp/to_fixture.asc")).unwrap();
+    armored = armored.replacen("hQEM", "hQEN", 1);
+    let error = openpgp::dearmor(armored.as_bytes()).unwrap_err();
+    assert!(error.contains("checksum"), "{}", error);
+
+    let small = key_generator::generate_key_pair(128);
+    assert!(openpgp::export_public_key(&small, "small").is_err());
+    assert!(openpgp::read_keys(b"\x01\x02").is_err());
+    assert!(
+        openpgp::read_keys(&fixture_bytes("openpgp/gnupg.pub.asc")).unwrap()[0]
+            .primary
+            .to_key_pair("public".to_string())
+            .is_err()
+    );
+}
+
+#[test]
+fn secret_keys_with_invalid_primes_are_refused() {
+    let key = |modulus: u32, e: u32, d: u32, p: u32, q: u32| Key {
+        public: PublicKey {
+            created: CREATED as u32,
+            modulus: BigUint::from(modulus),
+            e: BigUint::from(e),
+        },
+        secret: Some(SecretKey {
+            d: BigUint::from(d),
+            p: BigUint::from(p),
+            q: BigUint::from(q),
+        }),
+        flags: None,
+    };
+
+    assert!(key(3233, 17, 413, 61, 53)
+        .to_key_pair("x".to_string())
+        .is_ok());
+    // p = q, p = 1 and a d that does not belong to e all multiply to n
+    assert!(key(3721, 7, 43, 61, 61)
+        .to_key_pair("x".to_string())
+        .is_err());
+    assert!(key(3233, 17, 413, 1, 3233)
+        .to_key_pair("x".to_string())
+        .is_err());
+    assert!(key(3233, 17, 414, 61, 53)
+        .to_key_pair("x".to_string())
+        .is_err());
+
+    // Exporting a key pair with p = q reports an error instead of panicking on u
+    let prime = key_generator::generate_key_pair(1024).p;
+    let square = KeyPair::new(
+        "square".to_string(),
+        prime.clone(),
+        prime.clone(),
+        &prime * &prime,
+        BigUint::from(key_generator::EXPONENT),
+        BigInt::from(1),
+    );
+    assert!(openpgp::export_secret_key(&square, USER_ID).is_err());
+}
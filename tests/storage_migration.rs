@@ -0,0 +1,266 @@
+use std::fs;
+use std::path::PathBuf;
+
+use num_bigint_dig::BigUint;
+
+use student_rsa_tool::key_storage::{GenerationMethod, Storage};
+use student_rsa_tool::storage_migration::{SCHEMA_VERSION, UNVERSIONED};
+
+mod common;
+use common::{fixture, fixture_path};
+
+// Every format the storage was ever written in, oldest first
+const FIXTURES: [(&str, u64); 6] = [
+    ("v0_baseline.json", UNVERSIONED),
+    ("v0_multi_prime.json", UNVERSIONED),
+    ("v0_generation_method.json", UNVERSIONED),
+    ("v0_contacts.json", UNVERSIONED),
+    ("v0_metadata.json", UNVERSIONED),
+    ("v1.json", 1),
+];
+
+fn read_fixture(name: &str) -> String {
+    fixture(&format!("storage/{}", name))
+}
+
+// Copy of a fixture in a directory of its own, so tests can write next to it
+fn copy_fixture(name: &str, test: &str) -> String {
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test);
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    fs::copy(fixture_path(&format!("storage/{}", name)), &path).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn every_fixture_loads_at_the_current_version() {
+    for (name, version) in FIXTURES {
+        let (storage, migrations) = Storage::from_json(&read_fixture(name)).unwrap();
+        assert_eq!(storage.version(), SCHEMA_VERSION, "{}", name);
+        assert_eq!(
+            migrations.len() as u64,
+            SCHEMA_VERSION - version,
+            "{}",
+            name
+        );
+
+        let alice = storage.get_key_pair_by_id(0).unwrap();
+        assert_eq!(alice.name, "alice", "{}", name);
+        assert_eq!(&alice.p * &alice.q, alice.modulus, "{}", name);
+        assert_eq!(alice.e, BigUint::from(65537u32), "{}", name);
+    }
+}
+
+#[test]
+fn baseline_keys_get_default_metadata() {
+    let (storage, _) = Storage::from_json(&read_fixture("v0_baseline.json")).unwrap();
+    let alice = storage.get_key_pair_by_id(0).unwrap();
+
+    assert!(alice.other_primes.is_empty());
+    assert_eq!(alice.generation_method, GenerationMethod::Unknown);
+    assert_eq!(alice.created_at, 0);
+    assert_eq!(alice.created_at_text(), "unknown");
+    assert!(alice.comment.is_empty());
+    assert!(alice.tags.is_empty());
+    assert!(storage.get_contacts().is_empty());
+}
+
+#[test]
+fn multi_prime_keys_keep_their_other_primes() {
+    for (name, _) in FIXTURES.iter().skip(1) {
+        let (storage, _) = Storage::from_json(&read_fixture(name)).unwrap();
+        let bob = storage.get_key_pair_by_id(1).unwrap();
+
+        assert_eq!(bob.other_primes.len(), 1, "{}", name);
+        let product = bob
+            .primes()
+            .iter()
+            .fold(BigUint::from(1u32), |product, prime| product * prime);
+        assert_eq!(product, bob.modulus, "{}", name);
+    }
+}
+
+#[test]
+fn recorded_metadata_and_contacts_are_kept() {
+    let (storage, _) = Storage::from_json(&read_fixture("v0_contacts.json")).unwrap();
+    assert_eq!(
+        storage.get_key_pair_by_id(0).unwrap().generation_method,
+        GenerationMethod::Student
+    );
+    let carol = storage.get_contact_by_id(0).unwrap();
+    assert_eq!(carol.name, "carol");
+    let names: Vec<&str> = storage
+        .key_pairs()
+        .map(|key_pair| key_pair.name.as_str())
+        .chain(storage.contacts().map(|contact| contact.name.as_str()))
+        .collect();
+    assert_eq!(names, ["alice", "bob", "carol"]);
+    assert_eq!(
+        carol.modulus,
+        storage.get_key_pair_by_id(1).unwrap().modulus
+    );
+
+    let (storage, _) = Storage::from_json(&read_fixture("v0_metadata.json")).unwrap();
+    let bob = storage.get_key_pair_by_id(1).unwrap();
+    assert_eq!(bob.generation_method, GenerationMethod::Seeded);
+    assert_eq!(bob.created_at, 1700000000);
+    assert_eq!(bob.comment, "three primes");
+    assert_eq!(bob.tags, vec!["multi-prime", "demo"]);
+}
+
+#[test]
+fn check_does_not_touch_the_file() {
+    let path = copy_fixture("v0_metadata.json", "check_does_not_touch_the_file");
+
+    let migrations = Storage::check_migration(&path).unwrap();
+    assert_eq!(migrations.len(), 1);
+    assert_eq!(migrations[0].from, UNVERSIONED);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        read_fixture("v0_metadata.json")
+    );
+    assert!(!PathBuf::from(format!("{}.bak.1", path)).exists());
+}
+
+#[test]
+fn migrate_writes_the_new_version_and_keeps_a_backup() {
+    let path = copy_fixture(
+        "v0_generation_method.json",
+        "migrate_writes_the_new_version_and_keeps_a_backup",
+    );
+
+    assert_eq!(Storage::migrate_file(&path).unwrap().len(), 1);
+    assert_eq!(
+        fs::read_to_string(format!("{}.bak.1", path)).unwrap(),
+        read_fixture("v0_generation_method.json")
+    );
+    let (storage, migrations) = Storage::from_json(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert!(migrations.is_empty());
+    assert_eq!(storage.get_key_pairs().len(), 2);
+
+    // A second run has nothing left to do
+    assert!(Storage::migrate_file(&path).unwrap().is_empty());
+    assert!(Storage::check_migration(&path).unwrap().is_empty());
+}
+
+#[test]
+fn loading_upgrades_the_file() {
+    let path = copy_fixture("v0_baseline.json", "loading_upgrades_the_file");
+
+    let storage = Storage::load_storage_from_file(&path).unwrap();
+    assert_eq!(storage.get_key_pairs().len(), 1);
+    assert!(Storage::check_migration(&path).unwrap().is_empty());
+}
+
+#[test]
+fn empty_file_loads_as_empty_storage() {
+    let path = copy_fixture("v0_empty.json", "empty_file_loads_as_empty_storage");
+
+    assert!(Storage::check_migration(&path).unwrap().is_empty());
+    let storage = Storage::load_storage_from_file(&path).unwrap();
+    assert!(storage.get_key_pairs().is_empty());
+    assert!(storage.get_contacts().is_empty());
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let json = format!(
+        r#"{{"version":{},"key_pairs":{{}},"contacts":{{}}}}"#,
+        SCHEMA_VERSION + 1
+    );
+    let error = Storage::from_json(&json).err().unwrap();
+    assert!(error.contains("update the tool"), "{}", error);
+}
+
+#[test]
+fn broken_files_are_reported_instead_of_panicking() {
+    let error = Storage::from_json(r#"{"key_pairs":{"0":{"name":"x"}}}"#)
+        .err()
+        .unwrap();
+    assert!(error.contains("key pair 0 has no p"), "{}", error);
+
+    assert!(Storage::from_json("[1, 2]").is_err());
+    assert!(Storage::from_json("{").is_err());
+}
+
+#[test]
+fn failed_saves_are_reported_instead_of_panicking() {
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("failed_saves_are_reported");
+    let _ = fs::remove_dir_all(&directory);
+    // A directory where the storage file should be cannot be replaced
+    let path = directory.join("storage.json");
+    fs::create_dir_all(&path).unwrap();
+    let path = path.to_string_lossy().into_owned();
+
+    let error = Storage::new().save_storage_to_path(&path).err().unwrap();
+    assert!(
+        error.starts_with("could not save the key storage"),
+        "{}",
+        error
+    );
+    assert!(Storage::update_at_path(&path, |_| {}).is_err());
+
+    // The temporary file is removed again
+    let mut names: Vec<String> = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["storage.json", "storage.json.lock"]);
+}
+
+#[test]
+fn lock_errors_are_reported_instead_of_panicking() {
+    // The lock file next to the storage cannot be created in a missing directory
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("missing_directory")
+        .join("storage.json");
+    let path = path.to_string_lossy().into_owned();
+
+    let error = Storage::load_storage_from_file(&path).err().unwrap();
+    assert!(
+        error.starts_with("could not lock the key storage"),
+        "{}",
+        error
+    );
+    assert!(Storage::new().save_storage_to_path(&path).is_err());
+    assert!(Storage::update_at_path(&path, |_| {}).is_err());
+}
+
+#[test]
+fn broken_files_are_not_overwritten() {
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("broken_files");
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    let newer = format!(
+        r#"{{"version":{},"key_pairs":{{}},"contacts":{{}}}}"#,
+        SCHEMA_VERSION + 1
+    );
+    for (name, content) in [("corrupt.json", "{\"key_pairs\":"), ("newer.json", &newer)] {
+        let path = directory.join(name).to_string_lossy().into_owned();
+        fs::write(&path, content).unwrap();
+
+        let error = Storage::load_storage_from_file(&path).err().unwrap();
+        assert!(error.contains("storage migrate"), "{}", error);
+        assert!(error.contains(&format!("{}.bak.1", path)), "{}", error);
+        assert!(Storage::update_at_path(&path, |_| {}).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+}
+
+#[test]
+fn refused_changes_are_not_saved() {
+    let path = copy_fixture("v1.json", "refused_changes_are_not_saved");
+    let content = fs::read_to_string(&path).unwrap();
+
+    let error = Storage::try_update_at_path(&path, |storage| {
+        storage.rename_key_pair(0, "renamed".to_string());
+        Err("conflict".to_string())
+    })
+    .err()
+    .unwrap();
+    assert_eq!(error, "conflict");
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+}
@@ -0,0 +1,22 @@
+use num_bigint_dig::BigUint;
+
+use student_rsa_tool::utils;
+
+// The conversions are little-endian, like the decimal block layout that still uses them
+#[test]
+fn bytes_and_integers_convert_both_ways() {
+    assert_eq!(
+        utils::bytes_array_to_integer(&[1, 2]),
+        BigUint::from(513u32)
+    );
+    assert_eq!(utils::bytes_array_to_integer(&[]), BigUint::from(0u32));
+    assert_eq!(
+        utils::integer_to_bytes_array(BigUint::from(513u32)),
+        vec![1, 2]
+    );
+    assert!(utils::integer_to_bytes_array(BigUint::from(0u32)).is_empty());
+
+    let message: Vec<u8> = (0..100_000).map(|index| (index % 251) as u8 + 1).collect();
+    let integer = utils::bytes_array_to_integer(&message);
+    assert_eq!(utils::integer_to_bytes_array(integer), message);
+}
@@ -0,0 +1,177 @@
+use num_bigint_dig::BigUint;
+use num_traits::Num;
+
+use student_rsa_tool::der;
+use student_rsa_tool::key_generator;
+use student_rsa_tool::x509::{self, BasicConstraints, GeneralName, KeyUsage, Name, Validity};
+
+mod common;
+use common::{fixture_bytes, fixture_key_pair};
+
+// openssl_ca.pem is a self-signed sha384WithRSAEncryption certificate from `openssl req -x509`
+// valid until 2081, so it has a GeneralizedTime, and openssl.csr.pem a request of the same key
+const OPENSSL_NOT_BEFORE: i64 = 1792391766;
+const OPENSSL_NOT_AFTER: i64 = 3520391766;
+
+#[test]
+fn object_identifiers_round_trip() {
+    for oid in ["2.5.4.3", "1.2.840.113549.1.1.11", "2.5.29.35", "2.999.1"] {
+        let encoded = der::encode_oid(oid);
+        assert_eq!(der::decode_oid(&encoded).unwrap().0, oid);
+    }
+    assert_eq!(
+        der::encode_oid("1.2.840.113549.1.1.1"),
+        [0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01]
+    );
+}
+
+#[test]
+fn openssl_certificate_is_read() {
+    let certificate = x509::read_certificate(&fixture_bytes("x509/openssl_ca.pem")).unwrap();
+
+    assert_eq!(certificate.version, 3);
+    assert_eq!(
+        certificate.serial,
+        BigUint::from_str_radix("738F7EDA7707EB388C4BD80DDC1D8349AAA9A47B", 16).unwrap()
+    );
+    assert_eq!(
+        certificate.subject.text(),
+        "C=DE, O=OpenSSL Fixture, CN=Fixture Root"
+    );
+    assert!(certificate.is_self_issued());
+    assert_eq!(
+        certificate.validity,
+        Validity {
+            not_before: OPENSSL_NOT_BEFORE,
+            not_after: OPENSSL_NOT_AFTER,
+        }
+    );
+    assert_eq!(
+        certificate.extensions.basic_constraints,
+        Some(BasicConstraints {
+            ca: true,
+            path_length: Some(2),
+        })
+    );
+    assert_eq!(
+        certificate.extensions.key_usage,
+        vec![KeyUsage::KeyCertSign, KeyUsage::CrlSign]
+    );
+    assert_eq!(
+        certificate.extensions.subject_alt_names,
+        vec![
+            GeneralName::Dns("root.example".to_string()),
+            GeneralName::Ip("192.0.2.1".parse().unwrap()),
+        ]
+    );
+    assert_eq!(certificate.signature_algorithm(), "sha384WithRSAEncryption");
+    assert_eq!(
+        certificate.verify_signature(&certificate.modulus, &certificate.e),
+        Ok(())
+    );
+}
+
+#[test]
+fn openssl_request_is_read() {
+    let request = x509::read_certificate_request(&fixture_bytes("x509/openssl.csr.pem")).unwrap();
+
+    assert_eq!(request.subject.text(), "CN=request.example, OU=Lab");
+    assert_eq!(
+        request.extensions.key_usage,
+        vec![KeyUsage::DigitalSignature]
+    );
+    assert_eq!(
+        request.extensions.subject_alt_names,
+        vec![
+            GeneralName::Dns("request.example".to_string()),
+            GeneralName::Email("lab@example.org".to_string()),
+        ]
+    );
+    assert_eq!(request.verify_signature(), Ok(()));
+}
+
+#[test]
+fn local_ca_issues_a_chain() {
+    let root_key = fixture_key_pair();
+    let root_der = x509::self_signed_certificate(
+        &Name::parse("CN=Student Root, O=School, C=DE").unwrap(),
+        &x509::extensions_from_text("", true, Some(0), "").unwrap(),
+        Validity::days_from_now(30),
+        &root_key,
+    )
+    .unwrap();
+    let root = x509::read_certificate(x509::certificate_to_pem(&root_der).as_bytes()).unwrap();
+    assert!(root.is_ca());
+    assert_eq!(
+        root.extensions.subject_key_id,
+        Some(x509::key_identifier(&root_key.modulus, &root_key.e))
+    );
+    assert_eq!(root.verify_signed_by(&root), Ok(()));
+
+    let leaf_key = key_generator::generate_key_pair(1024);
+    let request_der = x509::certificate_request(
+        &Name::parse("CN=leaf.example, emailAddress=leaf@example.org").unwrap(),
+        &x509::extensions_from_text("DNS:leaf.example, IP:::1", false, None, "").unwrap(),
+        &leaf_key,
+    )
+    .unwrap();
+    let request = x509::parse_certificate_request(&request_der).unwrap();
+    assert_eq!(request.verify_signature(), Ok(()));
+
+    let leaf_der =
+        x509::sign_request(&request, &root, &root_key, Validity::days_from_now(7)).unwrap();
+    let leaf = x509::parse_certificate(&leaf_der).unwrap();
+    assert_eq!(leaf.issuer, root.subject);
+    assert_eq!(leaf.subject, request.subject);
+    assert_eq!(leaf.modulus, leaf_key.modulus);
+    assert_eq!(
+        leaf.extensions.key_usage,
+        vec![KeyUsage::DigitalSignature, KeyUsage::KeyEncipherment]
+    );
+    assert_eq!(leaf.extensions.subject_alt_names.len(), 2);
+    assert_eq!(
+        leaf.extensions.authority_key_id,
+        root.extensions.subject_key_id
+    );
+    assert_eq!(leaf.verify_signed_by(&root), Ok(()));
+
+    // A certificate that is not a CA cannot issue, and the key must match the certificate
+    let error =
+        x509::sign_request(&request, &leaf, &leaf_key, Validity::days_from_now(7)).unwrap_err();
+    assert!(error.contains("not a CA"), "{}", error);
+    let error =
+        x509::sign_request(&request, &root, &leaf_key, Validity::days_from_now(7)).unwrap_err();
+    assert!(error.contains("does not belong"), "{}", error);
+}
+
+#[test]
+fn foreign_issuers_are_told_apart() {
+    let openssl = x509::read_certificate(&fixture_bytes("x509/openssl_ca.pem")).unwrap();
+    let root_key = fixture_key_pair();
+    let root_der = x509::self_signed_certificate(
+        &openssl.subject,
+        &x509::extensions_from_text("", true, None, "").unwrap(),
+        Validity::days_from_now(1),
+        &root_key,
+    )
+    .unwrap();
+    let root = x509::parse_certificate(&root_der).unwrap();
+
+    // Same name, different key: only the signature tells them apart
+    let error = root.verify_signed_by(&openssl).unwrap_err();
+    assert!(error.contains("not valid"), "{}", error);
+}
+
+#[test]
+fn bad_input_is_reported() {
+    assert!(Name::parse("CN=x, C=Germany").is_err());
+    assert!(Name::parse("XX=y").is_err());
+    assert_eq!(
+        Name::parse(r"O=School\, Inc.").unwrap().attributes,
+        vec![("O".to_string(), "School, Inc.".to_string())]
+    );
+    assert!(x509::parse_alt_names("example.org").is_err());
+    assert!(x509::parse_key_usage("signEverything").is_err());
+    assert!(x509::read_certificate(&fixture_bytes("x509/openssl.csr.pem")).is_err());
+    assert!(x509::read_certificate(b"not a certificate").is_err());
+}